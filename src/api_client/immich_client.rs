@@ -1,16 +1,20 @@
-use std::sync::OnceLock;
+use std::{cmp::Ordering, sync::OnceLock};
 
 use anyhow::{bail, Result};
 use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime};
 use regex::Regex;
+use serde_json::Value;
 
 use crate::{
     api_client::{
-        immich_client::dto::{Album, AlbumInfo, Asset, AssetsInfo},
-        ApiClient, SharingId, SortBy,
+        immich_client::dto::{Album, AlbumInfo, Asset, AssetOrder, AssetsInfo},
+        is_supported_image, ApiClient, ShareLinkExpired, SharingId, SortBy,
     },
     cli::SourceSize,
-    http::{read_response, HttpClient, HttpResponse, Url},
+    http::{read_response, HttpClient, HttpResponse, InvalidHttpResponse, StatusCode, Url},
+    metadata::Metadata,
+    metadata_cache::CachedPhoto,
     LoginError,
 };
 
@@ -19,45 +23,75 @@ pub struct ImmichApiClient<'a, H> {
     api_url: Url,
     sharing_id: SharingId,
     password: &'a Option<String>,
+    /// Id of the album the share link points to, resolved once by [ApiClient::login] or the first
+    /// metadata fetch
+    album_id: OnceLock<String>,
 }
 
 impl<H: HttpClient> ApiClient for ImmichApiClient<'_, H> {
     type Photo = Asset;
 
     fn is_logged_in(&self) -> bool {
-        false
+        self.album_id.get().is_some()
     }
 
     fn login(&self) -> Result<(), LoginError> {
         /* Immich does not need logging in. Check if shared link is pointing to an album,
          * and if not, return LoginError so the app terminates. */
-        self.get_my_shared_link_album().map_err(LoginError)?;
+        self.album_id()
+            .map_err(|error| error.downcast::<LoginError>().unwrap_or_else(LoginError))?;
         Ok(())
     }
 
     fn get_photo_metadata(&self, sort_by: SortBy) -> Result<Vec<Self::Photo>> {
-        let Album { id, .. } = self.get_my_shared_link_album()?;
+        let id = self.album_id()?;
         let url = Url::parse(&format!("{}/albums/{id}", self.api_url))?;
         let response = self
             .http_client
             .get(url.as_str(), &[("key", &self.sharing_id)])?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            /* Share link may have expired since the album id was resolved, which terminates the
+             * slideshow */
+            self.get_my_shared_link_album()?;
+        }
         read_response(response, |r| {
             let mut dto = r.json::<AssetsInfo>()?;
-            Self::sort_assets(&mut dto.assets, sort_by);
+            /* Immich returns album assets sorted by date, in the direction set in the album
+             * options. The server's date may differ from the time taken used here (e.g. for
+             * assets without EXIF), so its order is reused only when it matches. */
+            if let (SortBy::TakenTime, Some(AssetOrder::Desc)) = (sort_by, dto.order) {
+                dto.assets.reverse();
+            }
+            if !Self::is_sorted(&dto.assets, sort_by) {
+                Self::sort_assets(&mut dto.assets, sort_by);
+            }
             Ok(dto.assets)
         })
     }
 
     fn get_photo_bytes(
         &self,
-        Self::Photo { id, .. }: &Self::Photo,
-        _: SourceSize,
+        Self::Photo {
+            id,
+            original_file_name,
+            ..
+        }: &Self::Photo,
+        source_size: SourceSize,
     ) -> Result<Bytes> {
-        let url = Url::parse(&format!("{}/assets/{id}/thumbnail", self.api_url))?;
-        let response = self.http_client.get(
-            url.as_str(),
-            &[("key", &self.sharing_id), ("size", "preview")],
-        )?;
+        /* Originals can be large, and formats which cannot be decoded (e.g. HEIC) are fetched as
+         * the server-generated preview instead */
+        let (endpoint, size) = match source_size {
+            SourceSize::S => ("thumbnail", Some("thumbnail")),
+            SourceSize::M => ("thumbnail", Some("preview")),
+            SourceSize::L if is_supported_image(original_file_name) => ("original", None),
+            SourceSize::L => ("thumbnail", Some("preview")),
+        };
+        let url = Url::parse(&format!("{}/assets/{id}/{endpoint}", self.api_url))?;
+        let mut query: Vec<(&str, &str)> = vec![("key", &self.sharing_id)];
+        if let Some(size) = size {
+            query.push(("size", size));
+        }
+        let response = self.http_client.get(url.as_str(), &query)?;
         read_response(response, |r| {
             let bytes = r.bytes()?;
             Ok(bytes)
@@ -65,7 +99,68 @@ impl<H: HttpClient> ApiClient for ImmichApiClient<'_, H> {
     }
 }
 
+impl Metadata for Asset {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn date(&self) -> Option<NaiveDateTime> {
+        let exif_info = self.exif_info.as_ref()?;
+        DateTime::parse_from_rfc3339(&exif_info.date_time_original)
+            .ok()
+            .map(|date_time| date_time.naive_local())
+    }
+
+    fn location(&self) -> Option<String> {
+        let exif_info = self.exif_info.as_ref()?;
+        match (&exif_info.city, &exif_info.country) {
+            (Some(city), Some(country)) => Some(format!("{city}, {country}")),
+            (Some(place), None) | (None, Some(place)) => Some(place.clone()),
+            (None, None) => None,
+        }
+    }
+
+    fn file_name(&self) -> Option<String> {
+        Some(self.original_file_name.clone())
+    }
+
+    fn camera(&self) -> Option<String> {
+        let exif_info = self.exif_info.as_ref()?;
+        match (&exif_info.make, &exif_info.model) {
+            /* Model often already starts with the make, e.g. "Canon EOS R6" */
+            (Some(make), Some(model)) if model.starts_with(make.as_str()) => Some(model.clone()),
+            (Some(make), Some(model)) => Some(format!("{make} {model}")),
+            (Some(camera), None) | (None, Some(camera)) => Some(camera.clone()),
+            (None, None) => None,
+        }
+    }
+
+    fn caption(&self) -> Option<String> {
+        let description = self.exif_info.as_ref()?.description.as_deref()?.trim();
+        /* Immich reports an empty description when none was entered */
+        (!description.is_empty()).then(|| description.to_string())
+    }
+}
+
+impl CachedPhoto for Asset {
+    fn to_cache(&self) -> Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn from_cache(value: Value) -> Result<Self> {
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
 impl<H: HttpClient> ImmichApiClient<'_, H> {
+    fn album_id(&self) -> Result<&str> {
+        if let Some(id) = self.album_id.get() {
+            return Ok(id);
+        }
+        let Album { id, .. } = self.get_my_shared_link_album()?;
+        Ok(self.album_id.get_or_init(|| id))
+    }
+
     fn get_my_shared_link_album(&self) -> Result<Album> {
         let url = Url::parse(&format!("{}/shared-links/me", self.api_url))?;
         let response = self.http_client.get(
@@ -75,6 +170,14 @@ impl<H: HttpClient> ImmichApiClient<'_, H> {
                 ("password", self.password.as_deref().unwrap_or_default()),
             ],
         )?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            /* Immich responds the same way to expired, removed and non-existent share links */
+            let body = response.text().unwrap_or_default();
+            if body.contains("Invalid share key") {
+                bail!(LoginError(ShareLinkExpired.into()));
+            }
+            bail!(InvalidHttpResponse(StatusCode::UNAUTHORIZED));
+        }
         read_response(response, |r| {
             let dto = r.json::<AlbumInfo>()?;
             Ok(dto.album)
@@ -82,12 +185,22 @@ impl<H: HttpClient> ImmichApiClient<'_, H> {
     }
 
     fn sort_assets(assets: &mut [Asset], sort_by: SortBy) {
-        assets.sort_by(|a, b| match (&a.exif_info, &b.exif_info) {
+        assets.sort_by(|a, b| Self::compare_assets(a, b, sort_by))
+    }
+
+    fn is_sorted(assets: &[Asset], sort_by: SortBy) -> bool {
+        assets
+            .windows(2)
+            .all(|pair| Self::compare_assets(&pair[0], &pair[1], sort_by) != Ordering::Greater)
+    }
+
+    fn compare_assets(a: &Asset, b: &Asset, sort_by: SortBy) -> Ordering {
+        match (&a.exif_info, &b.exif_info) {
             (Some(a_exif), Some(b_exif)) if matches!(sort_by, SortBy::TakenTime) => {
                 a_exif.date_time_original.cmp(&b_exif.date_time_original)
             }
             _ => a.original_file_name.cmp(&b.original_file_name),
-        })
+        }
     }
 }
 
@@ -99,6 +212,7 @@ impl<'a, H> ImmichApiClient<'a, H> {
             api_url,
             sharing_id,
             password: &None,
+            album_id: OnceLock::new(),
         })
     }
 
@@ -120,7 +234,7 @@ fn parse_share_link(share_link: &Url) -> Result<(Url, SharingId)> {
 }
 
 mod dto {
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
 
     #[derive(Debug, Deserialize)]
     pub struct AlbumInfo {
@@ -136,9 +250,18 @@ mod dto {
     #[derive(Debug, Deserialize)]
     pub struct AssetsInfo {
         pub assets: Vec<Asset>,
+        /// Order of assets returned by the server. Older Immich versions do not report it.
+        pub order: Option<AssetOrder>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Clone, Copy, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    pub enum AssetOrder {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Debug, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct Asset {
         pub id: String,
@@ -146,10 +269,15 @@ mod dto {
         pub exif_info: Option<ExifInfo>,
     }
 
-    #[derive(Debug, Deserialize)]
+    #[derive(Debug, Serialize, Deserialize)]
     #[serde(rename_all = "camelCase")]
     pub struct ExifInfo {
         pub date_time_original: String,
+        pub city: Option<String>,
+        pub country: Option<String>,
+        pub make: Option<String>,
+        pub model: Option<String>,
+        pub description: Option<String>,
     }
 }
 
@@ -157,6 +285,13 @@ mod dto {
 mod tests {
     use super::*;
 
+    use crate::{
+        http::MockHttpResponse,
+        test_helpers::{self, MockHttpClient},
+    };
+
+    use dto::ExifInfo;
+
     #[test]
     fn parse_share_link_is_ok_for_valid_link() {
         test_case(
@@ -179,4 +314,286 @@ mod tests {
             assert_eq!(sharing_id.0, "fake-Sharing-Id");
         }
     }
+
+    #[test]
+    fn when_album_is_sorted_by_server_then_get_photo_metadata_returns_assets_by_date_taken() {
+        /* Server order matching the date taken is reused */
+        test_case(Some(AssetOrder::Asc), &["1", "2", "3"]);
+        test_case(Some(AssetOrder::Desc), &["3", "2", "1"]);
+        /* Server order not matching the date taken, e.g. sorted by file date instead */
+        test_case(Some(AssetOrder::Asc), &["2", "1", "3"]);
+        test_case(Some(AssetOrder::Desc), &["1", "3", "2"]);
+
+        fn test_case(order: Option<AssetOrder>, server_ids: &[&str]) {
+            let client_stub = new_album_client_stub(
+                server_ids
+                    .iter()
+                    .map(|id| new_asset(id, &format!("2024-01-0{id}")))
+                    .collect(),
+                order,
+            );
+            let api_client = new_immich_client(&client_stub);
+
+            let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+            assert!(result.is_ok());
+            assert_eq!(
+                asset_ids(&result.unwrap()),
+                ["1", "2", "3"],
+                "{order:?} {server_ids:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn when_server_order_is_unknown_then_get_photo_metadata_sorts_in_memory() {
+        let client_stub = new_album_client_stub(
+            vec![
+                new_asset("2", "2024-01-02"),
+                new_asset("1", "2024-01-01"),
+                new_asset("3", "2024-01-03"),
+            ],
+            None,
+        );
+        let api_client = new_immich_client(&client_stub);
+
+        let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+        assert!(result.is_ok());
+        assert_eq!(asset_ids(&result.unwrap()), ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn when_sorting_by_file_name_then_get_photo_metadata_ignores_server_order() {
+        let client_stub = new_album_client_stub(
+            vec![
+                new_asset("b", "2024-01-01"),
+                new_asset("c", "2024-01-02"),
+                new_asset("a", "2024-01-03"),
+            ],
+            Some(AssetOrder::Asc),
+        );
+        let api_client = new_immich_client(&client_stub);
+
+        let result = api_client.get_photo_metadata(SortBy::FileName);
+
+        assert!(result.is_ok());
+        assert_eq!(asset_ids(&result.unwrap()), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn album_id_resolved_by_login_is_reused_by_get_photo_metadata() {
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_get()
+            .withf(|url, _| url == "http://fake.immich.addr/api/shared-links/me")
+            .times(1)
+            .returning(|_, _| Ok(new_album_info_response()));
+        client_mock
+            .expect_get()
+            .withf(|url, _| url == "http://fake.immich.addr/api/albums/FakeAlbumId")
+            .times(2)
+            .returning(|_, _| {
+                let mut response = test_helpers::new_ok_response();
+                response.expect_json::<AssetsInfo>().return_once(|| {
+                    Ok(AssetsInfo {
+                        assets: vec![new_asset("1", "2024-01-01")],
+                        order: None,
+                    })
+                });
+                Ok(response)
+            });
+        let api_client = new_immich_client(&client_mock);
+
+        let login_result = api_client.login();
+        let metadata_results = [
+            api_client.get_photo_metadata(SortBy::TakenTime),
+            api_client.get_photo_metadata(SortBy::TakenTime),
+        ];
+
+        assert!(login_result.is_ok());
+        assert!(api_client.is_logged_in());
+        assert!(metadata_results.iter().all(Result::is_ok));
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_share_link_expired_then_login_fails_with_expired_link_error() {
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_get()
+            .withf(|url, _| url == "http://fake.immich.addr/api/shared-links/me")
+            .returning(|_, _| {
+                let mut response = MockHttpResponse::new();
+                response
+                    .expect_status()
+                    .return_const(StatusCode::UNAUTHORIZED);
+                response.expect_text().return_once(|| {
+                    Ok(r#"{"message":"Invalid share key","error":"Unauthorized","statusCode":401}"#
+                        .to_string())
+                });
+                Ok(response)
+            });
+        let api_client = new_immich_client(&client_stub);
+
+        let login_result = api_client.login();
+        let metadata_result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+        assert!(login_result.is_err_and(|LoginError(e)| e.is::<ShareLinkExpired>()));
+        /* Link expiring while the slideshow runs terminates it too */
+        assert!(metadata_result.is_err_and(|e| e.is::<LoginError>()));
+    }
+
+    #[test]
+    fn when_share_link_password_is_wrong_then_login_fails_with_http_error() {
+        let mut client_stub = MockHttpClient::new();
+        client_stub.expect_get().returning(|_, _| {
+            let mut response = MockHttpResponse::new();
+            response
+                .expect_status()
+                .return_const(StatusCode::UNAUTHORIZED);
+            response.expect_text().return_once(|| {
+                Ok(
+                    r#"{"message":"Invalid password","error":"Unauthorized","statusCode":401}"#
+                        .to_string(),
+                )
+            });
+            Ok(response)
+        });
+        let api_client = new_immich_client(&client_stub);
+
+        let result = api_client.login();
+
+        assert!(result.is_err_and(|LoginError(e)| e.is::<InvalidHttpResponse>()));
+    }
+
+    #[test]
+    fn get_photo_bytes_requests_size_matching_source_size() {
+        const ASSET_URL: &str = "http://fake.immich.addr/api/assets/1";
+        test_case(
+            SourceSize::S,
+            "a.jpg",
+            &format!("{ASSET_URL}/thumbnail"),
+            &[("key", "FakeSharingId"), ("size", "thumbnail")],
+        );
+        test_case(
+            SourceSize::M,
+            "a.jpg",
+            &format!("{ASSET_URL}/thumbnail"),
+            &[("key", "FakeSharingId"), ("size", "preview")],
+        );
+        test_case(
+            SourceSize::L,
+            "a.JPG",
+            &format!("{ASSET_URL}/original"),
+            &[("key", "FakeSharingId")],
+        );
+        /* Cannot be decoded */
+        test_case(
+            SourceSize::L,
+            "a.heic",
+            &format!("{ASSET_URL}/thumbnail"),
+            &[("key", "FakeSharingId"), ("size", "preview")],
+        );
+
+        fn test_case(
+            source_size: SourceSize,
+            original_file_name: &str,
+            expected_url: &str,
+            expected_query: &'static [(&'static str, &'static str)],
+        ) {
+            let mut client_mock = MockHttpClient::new();
+            let expected_url = expected_url.to_string();
+            client_mock
+                .expect_get()
+                .withf(move |url, query| url == expected_url && query == expected_query)
+                .return_once(|_, _| {
+                    let mut response = test_helpers::new_ok_response();
+                    response
+                        .expect_bytes()
+                        .return_once(|| Ok(Bytes::from_static(&[1])));
+                    Ok(response)
+                });
+            let api_client = new_immich_client(&client_mock);
+            let mut asset = new_asset("1", "2024-01-01");
+            asset.original_file_name = original_file_name.to_string();
+
+            let result = api_client.get_photo_bytes(&asset, source_size);
+
+            assert!(result.is_ok(), "{source_size:?} {original_file_name}");
+        }
+    }
+
+    #[test]
+    fn caption_is_asset_description_when_present() {
+        test_case(
+            Some("  Grandma's 90th birthday "),
+            Some("Grandma's 90th birthday"),
+        );
+        test_case(Some(""), None);
+        test_case(None, None);
+
+        fn test_case(description: Option<&str>, expected: Option<&str>) {
+            let mut asset = new_asset("1", "2024-01-01");
+            asset.exif_info.as_mut().unwrap().description = description.map(str::to_string);
+
+            assert_eq!(asset.caption().as_deref(), expected);
+        }
+    }
+
+    const SHARE_LINK: &str = "http://fake.immich.addr/share/FakeSharingId";
+
+    fn new_immich_client(client_stub: &MockHttpClient) -> ImmichApiClient<'_, MockHttpClient> {
+        ImmichApiClient::build(client_stub, &Url::parse(SHARE_LINK).unwrap()).unwrap()
+    }
+
+    fn new_album_client_stub(assets: Vec<Asset>, order: Option<AssetOrder>) -> MockHttpClient {
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_get()
+            .withf(|url, _| url == "http://fake.immich.addr/api/shared-links/me")
+            .returning(|_, _| Ok(new_album_info_response()));
+        client_stub
+            .expect_get()
+            .withf(|url, _| url == "http://fake.immich.addr/api/albums/FakeAlbumId")
+            .return_once(move |_, _| {
+                let mut response = test_helpers::new_ok_response();
+                response
+                    .expect_json::<AssetsInfo>()
+                    .return_once(move || Ok(AssetsInfo { assets, order }));
+                Ok(response)
+            });
+        client_stub
+    }
+
+    fn new_album_info_response() -> MockHttpResponse {
+        let mut response = test_helpers::new_ok_response();
+        response.expect_json::<AlbumInfo>().return_once(|| {
+            Ok(AlbumInfo {
+                album: Album {
+                    id: "FakeAlbumId".to_string(),
+                },
+            })
+        });
+        response
+    }
+
+    fn new_asset(id: &str, date_time_original: &str) -> Asset {
+        Asset {
+            id: id.to_string(),
+            original_file_name: format!("{id}.jpg"),
+            exif_info: Some(ExifInfo {
+                date_time_original: date_time_original.to_string(),
+                city: None,
+                country: None,
+                make: None,
+                model: None,
+                description: None,
+            }),
+        }
+    }
+
+    fn asset_ids(assets: &[Asset]) -> Vec<&str> {
+        assets.iter().map(|a| a.id.as_str()).collect()
+    }
 }
@@ -0,0 +1,156 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+
+use crate::{
+    api_client::{
+        is_supported_image,
+        manifest_client::{self, ManifestPhoto},
+        ApiClient, SortBy,
+    },
+    cli::{LocalDateSource, SourceSize},
+    http::Url,
+    LoginError,
+};
+
+/// Reads photos from a local directory (including subdirectories), e.g. synced to the SD card of
+/// a frame without network access. The directory is scanned again for every slideshow pass, so
+/// added and removed photos are picked up
+pub struct LocalApiClient {
+    dir: PathBuf,
+    date_source: LocalDateSource,
+}
+
+impl ApiClient for LocalApiClient {
+    type Photo = ManifestPhoto;
+
+    fn is_logged_in(&self) -> bool {
+        true
+    }
+
+    fn login(&self) -> Result<(), LoginError> {
+        Ok(())
+    }
+
+    fn get_photo_metadata(&self, sort_by: SortBy) -> Result<Vec<Self::Photo>> {
+        let mut paths = vec![];
+        find_photos(&self.dir, &mut paths)?;
+        /* Directory entries come in no particular order */
+        paths.sort();
+        let mut photos = paths
+            .into_iter()
+            .map(|path| ManifestPhoto {
+                date: manifest_client::file_date(&path, self.date_source),
+                path,
+                location: None,
+            })
+            .collect::<Vec<_>>();
+        manifest_client::sort_photos(&mut photos, sort_by);
+        Ok(photos)
+    }
+
+    fn get_photo_bytes(&self, photo: &Self::Photo, _: SourceSize) -> Result<Bytes> {
+        manifest_client::read_photo(&photo.path)
+    }
+}
+
+impl LocalApiClient {
+    /// `dir_link` is a `file://` URL of the directory
+    pub fn build(dir_link: &Url) -> Result<Self> {
+        let dir = dir_link
+            .to_file_path()
+            .map_err(|_| anyhow!("Invalid local directory {dir_link}"))?;
+        Ok(Self {
+            dir,
+            date_source: LocalDateSource::Exif,
+        })
+    }
+
+    pub fn with_date_source(mut self, date_source: LocalDateSource) -> Self {
+        self.date_source = date_source;
+        self
+    }
+}
+
+/// Collects photo files in `dir` and its subdirectories, skipping hidden ones (e.g. thumbnails
+/// created by file managers). Symbolic links to directories are not followed
+fn find_photos(dir: &Path, photos: &mut Vec<PathBuf>) -> Result<()> {
+    let entries =
+        fs::read_dir(dir).map_err(|error| anyhow!("{}: {error}", dir.to_string_lossy()))?;
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            find_photos(&path, photos)?;
+        } else if is_supported_image(&entry.file_name().to_string_lossy()) {
+            photos.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    use super::*;
+
+    #[test]
+    fn get_photo_metadata_finds_photos_in_directory_and_subdirectories() {
+        let dir = new_temp_dir("find");
+        fs::create_dir_all(dir.join("2019/july")).unwrap();
+        fs::create_dir_all(dir.join(".thumbnails")).unwrap();
+        fs::copy("assets/test_exif.jpeg", dir.join("2019/july/beach.JPEG")).unwrap();
+        fs::copy("assets/test_loading.jpeg", dir.join("garden.jpg")).unwrap();
+        fs::copy(
+            "assets/test_loading.jpeg",
+            dir.join(".thumbnails/garden.jpg"),
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "not a photo").unwrap();
+        let api_client = LocalApiClient::build(&Url::from_directory_path(&dir).unwrap()).unwrap();
+
+        let result = api_client.get_photo_metadata(SortBy::FileName);
+
+        fs::remove_dir_all(&dir).unwrap();
+        let photos = result.unwrap();
+        assert_eq!(
+            photos.iter().map(|photo| &photo.path).collect::<Vec<_>>(),
+            [&dir.join("2019/july/beach.JPEG"), &dir.join("garden.jpg")]
+        );
+        assert_eq!(photos[0].date, Some(new_date_time(2019, 7, 14, 9, 30)));
+        assert!(photos[1].date.is_some());
+    }
+
+    #[test]
+    fn when_directory_does_not_exist_then_get_photo_metadata_returns_error() {
+        let api_client = LocalApiClient::build(
+            &Url::from_directory_path(new_temp_dir("missing").join("photos")).unwrap(),
+        )
+        .unwrap();
+
+        let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+        assert!(result.is_err());
+    }
+
+    fn new_temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("syno-photo-frame-local-{}-{name}", process::id()))
+    }
+
+    fn new_date_time(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+}
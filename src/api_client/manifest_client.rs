@@ -0,0 +1,400 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    api_client::{ApiClient, SortBy},
+    cli::{LocalDateSource, SourceSize},
+    metadata::Metadata,
+    metadata_cache::CachedPhoto,
+    LoginError,
+};
+
+/// Reads photos from local files listed in a JSON or CSV manifest, e.g. for frames without
+/// network access.
+///
+/// JSON manifest is an array of objects, CSV manifest has a header row. In both cases the fields
+/// are `path`, and optionally `date` (e.g. `2024-05-01` or `2024-05-01T14:30:00`) and
+/// `location`. Relative paths are resolved against the manifest's directory. Photos without a
+/// `date` are dated according to [ManifestApiClient::with_date_source].
+pub struct ManifestApiClient {
+    photos: Vec<ManifestPhoto>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestPhoto {
+    pub path: PathBuf,
+    pub date: Option<NaiveDateTime>,
+    pub location: Option<String>,
+}
+
+impl ApiClient for ManifestApiClient {
+    type Photo = ManifestPhoto;
+
+    fn is_logged_in(&self) -> bool {
+        true
+    }
+
+    fn login(&self) -> Result<(), LoginError> {
+        Ok(())
+    }
+
+    fn get_photo_metadata(&self, sort_by: SortBy) -> Result<Vec<Self::Photo>> {
+        let mut photos = self.photos.clone();
+        /* Sorting is stable, so photos without dates keep the order of the manifest */
+        sort_photos(&mut photos, sort_by);
+        Ok(photos)
+    }
+
+    fn get_photo_bytes(&self, photo: &Self::Photo, _: SourceSize) -> Result<Bytes> {
+        read_photo(&photo.path)
+    }
+}
+
+pub(super) fn sort_photos(photos: &mut [ManifestPhoto], sort_by: SortBy) {
+    match sort_by {
+        SortBy::TakenTime => photos.sort_by_key(|photo| photo.date),
+        SortBy::FileName => photos.sort_by(|a, b| a.path.file_name().cmp(&b.path.file_name())),
+    }
+}
+
+pub(super) fn read_photo(path: &Path) -> Result<Bytes> {
+    let bytes =
+        std::fs::read(path).map_err(|error| anyhow!("{}: {error}", path.to_string_lossy()))?;
+    Ok(Bytes::from(bytes))
+}
+
+impl Metadata for ManifestPhoto {
+    fn id(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+
+    fn date(&self) -> Option<NaiveDateTime> {
+        self.date
+    }
+
+    fn location(&self) -> Option<String> {
+        self.location.clone()
+    }
+
+    fn file_name(&self) -> Option<String> {
+        self.path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+    }
+
+    fn camera(&self) -> Option<String> {
+        None
+    }
+
+    fn caption(&self) -> Option<String> {
+        None
+    }
+}
+
+impl CachedPhoto for ManifestPhoto {
+    fn to_cache(&self) -> Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn from_cache(value: Value) -> Result<Self> {
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl ManifestApiClient {
+    pub fn build(manifest_path: &Path) -> Result<Self> {
+        let format = match manifest_path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("json") => ManifestFormat::Json,
+            Some(e) if e.eq_ignore_ascii_case("csv") => ManifestFormat::Csv,
+            _ => bail!(
+                "Unsupported manifest format: {}. Expected a .json or .csv file",
+                manifest_path.to_string_lossy()
+            ),
+        };
+        let content = std::fs::read_to_string(manifest_path)
+            .map_err(|error| anyhow!("{}: {error}", manifest_path.to_string_lossy()))?;
+        let base_dir = manifest_path.parent().unwrap_or(Path::new(""));
+        let photos = parse_manifest(&content, format, base_dir)?;
+        if photos.is_empty() {
+            bail!("Manifest is empty");
+        }
+        Ok(Self { photos })
+    }
+
+    /// Reads dates of photos which have none in the manifest from the photo files
+    pub fn with_date_source(mut self, date_source: LocalDateSource) -> Self {
+        for photo in self.photos.iter_mut().filter(|photo| photo.date.is_none()) {
+            photo.date = file_date(&photo.path, date_source);
+        }
+        self
+    }
+}
+
+pub(super) fn file_date(path: &Path, date_source: LocalDateSource) -> Option<NaiveDateTime> {
+    let exif_date = match date_source {
+        LocalDateSource::Exif => exif_date_taken(path),
+        LocalDateSource::Mtime => None,
+    };
+    exif_date.or_else(|| modification_time(path))
+}
+
+/// Reads the `DateTimeOriginal` EXIF tag
+fn exif_date_taken(path: &Path) -> Option<NaiveDateTime> {
+    let file = File::open(path).ok()?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut BufReader::new(file))
+        .ok()?;
+    let field = exif.get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)?;
+    let exif::Value::Ascii(values) = &field.value else {
+        return None;
+    };
+    let date = exif::DateTime::from_ascii(values.first()?).ok()?;
+    NaiveDate::from_ymd_opt(date.year.into(), date.month.into(), date.day.into())?.and_hms_opt(
+        date.hour.into(),
+        date.minute.into(),
+        date.second.into(),
+    )
+}
+
+fn modification_time(path: &Path) -> Option<NaiveDateTime> {
+    let modified = std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?;
+    Some(DateTime::<Local>::from(modified).naive_local())
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ManifestFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestRecord {
+    path: PathBuf,
+    date: Option<String>,
+    location: Option<String>,
+}
+
+fn parse_manifest(
+    content: &str,
+    format: ManifestFormat,
+    base_dir: &Path,
+) -> Result<Vec<ManifestPhoto>> {
+    let records = match format {
+        ManifestFormat::Json => serde_json::from_str::<Vec<ManifestRecord>>(content)?,
+        ManifestFormat::Csv => csv::Reader::from_reader(content.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<ManifestRecord>, _>>()?,
+    };
+    records
+        .into_iter()
+        .map(|record| {
+            let date = match record.date.as_deref().map(str::trim) {
+                None | Some("") => None,
+                Some(date) => Some(parse_date(date).ok_or_else(|| {
+                    anyhow!(
+                        "Invalid date '{date}' of {} in manifest",
+                        record.path.to_string_lossy()
+                    )
+                })?),
+            };
+            Ok(ManifestPhoto {
+                path: base_dir.join(record.path),
+                date,
+                location: record.location.filter(|location| !location.is_empty()),
+            })
+        })
+        .collect()
+}
+
+fn parse_date(date: &str) -> Option<NaiveDateTime> {
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest_reads_json_manifest() {
+        const MANIFEST: &str = r#"[
+            {"path": "photos/b.jpg", "date": "2024-05-01T14:30:00", "location": "Paris, France"},
+            {"path": "/absolute/a.jpg", "date": "2023-01-02"},
+            {"path": "c.jpg"}
+        ]"#;
+
+        let result = parse_manifest(MANIFEST, ManifestFormat::Json, Path::new("/frame"));
+
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                ManifestPhoto {
+                    path: PathBuf::from("/frame/photos/b.jpg"),
+                    date: Some(new_date_time(2024, 5, 1, 14, 30)),
+                    location: Some("Paris, France".to_string()),
+                },
+                ManifestPhoto {
+                    path: PathBuf::from("/absolute/a.jpg"),
+                    date: Some(new_date_time(2023, 1, 2, 0, 0)),
+                    location: None,
+                },
+                ManifestPhoto {
+                    path: PathBuf::from("/frame/c.jpg"),
+                    date: None,
+                    location: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_manifest_reads_csv_manifest() {
+        const MANIFEST: &str = "path,date,location\n\
+            photos/b.jpg,2024-05-01 14:30:00,\"Paris, France\"\n\
+            c.jpg,,\n";
+
+        let result = parse_manifest(MANIFEST, ManifestFormat::Csv, Path::new("/frame"));
+
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                ManifestPhoto {
+                    path: PathBuf::from("/frame/photos/b.jpg"),
+                    date: Some(new_date_time(2024, 5, 1, 14, 30)),
+                    location: Some("Paris, France".to_string()),
+                },
+                ManifestPhoto {
+                    path: PathBuf::from("/frame/c.jpg"),
+                    date: None,
+                    location: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn when_date_is_invalid_then_parse_manifest_returns_error() {
+        const MANIFEST: &str = r#"[{"path": "a.jpg", "date": "yesterday"}]"#;
+
+        let result = parse_manifest(MANIFEST, ManifestFormat::Json, Path::new(""));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_photo_metadata_sorts_photos() {
+        const MANIFEST: &str = r#"[
+            {"path": "b.jpg", "date": "2024-01-01"},
+            {"path": "c.jpg", "date": "2022-01-01"},
+            {"path": "a.jpg", "date": "2023-01-01"}
+        ]"#;
+        let api_client = ManifestApiClient {
+            photos: parse_manifest(MANIFEST, ManifestFormat::Json, Path::new("")).unwrap(),
+        };
+
+        let by_date = api_client.get_photo_metadata(SortBy::TakenTime).unwrap();
+        let by_name = api_client.get_photo_metadata(SortBy::FileName).unwrap();
+
+        let paths = |photos: &[ManifestPhoto]| {
+            photos
+                .iter()
+                .map(|p| p.path.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&by_date), ["c.jpg", "a.jpg", "b.jpg"]);
+        assert_eq!(paths(&by_name), ["a.jpg", "b.jpg", "c.jpg"]);
+    }
+
+    #[test]
+    fn with_date_source_exif_reads_date_taken_from_exif_data() {
+        let api_client = new_api_client(&["assets/test_exif.jpeg"]);
+
+        let result = api_client.with_date_source(LocalDateSource::Exif);
+
+        assert_eq!(
+            result.photos[0].date,
+            Some(new_date_time(2019, 7, 14, 9, 30))
+        );
+    }
+
+    #[test]
+    fn when_photo_has_no_exif_date_then_with_date_source_exif_uses_modification_time() {
+        let api_client = new_api_client(&["assets/test_loading.jpeg"]);
+
+        let result = api_client.with_date_source(LocalDateSource::Exif);
+
+        assert!(result.photos[0].date.is_some());
+        assert_eq!(
+            result.photos[0].date,
+            modification_time(Path::new("assets/test_loading.jpeg"))
+        );
+    }
+
+    #[test]
+    fn with_date_source_mtime_ignores_exif_data() {
+        let api_client = new_api_client(&["assets/test_exif.jpeg"]);
+
+        let result = api_client.with_date_source(LocalDateSource::Mtime);
+
+        assert_eq!(
+            result.photos[0].date,
+            modification_time(Path::new("assets/test_exif.jpeg"))
+        );
+        assert_ne!(
+            result.photos[0].date,
+            Some(new_date_time(2019, 7, 14, 9, 30))
+        );
+    }
+
+    #[test]
+    fn with_date_source_keeps_dates_from_manifest() {
+        let mut api_client = new_api_client(&["assets/test_exif.jpeg"]);
+        api_client.photos[0].date = Some(new_date_time(2024, 5, 1, 14, 30));
+
+        let result = api_client.with_date_source(LocalDateSource::Exif);
+
+        assert_eq!(
+            result.photos[0].date,
+            Some(new_date_time(2024, 5, 1, 14, 30))
+        );
+    }
+
+    fn new_api_client(paths: &[&str]) -> ManifestApiClient {
+        ManifestApiClient {
+            photos: paths
+                .iter()
+                .map(|path| ManifestPhoto {
+                    path: PathBuf::from(path),
+                    date: None,
+                    location: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn new_date_time(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+}
@@ -7,13 +7,18 @@ use regex::Regex;
 use crate::{
     cli::{Backend, Order, SourceSize},
     http::Url,
+    metadata::Metadata,
+    metadata_cache::CachedPhoto,
 };
 
 pub mod immich_client;
+pub mod local_client;
+pub mod manifest_client;
 pub mod syno_client;
+pub mod webdav_client;
 
 pub trait ApiClient {
-    type Photo: Send;
+    type Photo: Metadata + CachedPhoto + Send;
 
     fn is_logged_in(&self) -> bool;
 
@@ -24,6 +29,27 @@ pub trait ApiClient {
     fn get_photo_bytes(&self, photo: &Self::Photo, source_size: SourceSize) -> Result<Bytes>;
 }
 
+/// Allows sharing a client, e.g. to fetch metadata in the background while photos are fetched
+impl<A: ApiClient> ApiClient for &A {
+    type Photo = A::Photo;
+
+    fn is_logged_in(&self) -> bool {
+        (*self).is_logged_in()
+    }
+
+    fn login(&self) -> Result<(), LoginError> {
+        (*self).login()
+    }
+
+    fn get_photo_metadata(&self, sort_by: SortBy) -> Result<Vec<Self::Photo>> {
+        (*self).get_photo_metadata(sort_by)
+    }
+
+    fn get_photo_bytes(&self, photo: &Self::Photo, source_size: SourceSize) -> Result<Bytes> {
+        (*self).get_photo_bytes(photo, source_size)
+    }
+}
+
 #[derive(Debug)]
 pub struct LoginError(pub anyhow::Error);
 
@@ -35,6 +61,18 @@ impl fmt::Display for LoginError {
 
 impl std::error::Error for LoginError {}
 
+/// Share link is no longer valid, e.g. because it expired
+#[derive(Debug)]
+pub struct ShareLinkExpired;
+
+impl fmt::Display for ShareLinkExpired {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "This Immich share link has expired or been revoked.")
+    }
+}
+
+impl std::error::Error for ShareLinkExpired {}
+
 #[derive(Debug)]
 struct SharingId(String);
 
@@ -57,7 +95,7 @@ impl From<Order> for SortBy {
         match value {
             /* Random is not an option in the API. Randomization is implemented client-side and
              * essentially makes the sort_by query parameter irrelevant. */
-            Order::ByDate | Order::Random => SortBy::TakenTime,
+            Order::ByDate | Order::Random | Order::RandomDays => SortBy::TakenTime,
             Order::ByName => SortBy::FileName,
         }
     }
@@ -76,7 +114,27 @@ impl fmt::Display for SortBy {
     }
 }
 
+/// Whether a photo file can be decoded, judging by its name
+fn is_supported_image(file_name: &str) -> bool {
+    let Some((_, extension)) = file_name.rsplit_once('.') else {
+        return false;
+    };
+    let heif_extensions: &[&str] = if cfg!(feature = "heif") {
+        &["heic", "heif"]
+    } else {
+        &[]
+    };
+    ["jpg", "jpeg", "png"]
+        .iter()
+        .chain(heif_extensions)
+        .any(|supported| extension.eq_ignore_ascii_case(supported))
+}
+
 pub fn detect_backend(share_link: &Url) -> Result<Backend> {
+    if share_link.scheme() == "file" {
+        return Ok(Backend::Local);
+    }
+
     static SYNO_LINK_RE: OnceLock<Regex> = OnceLock::new();
     let syno_link_re = SYNO_LINK_RE
         .get_or_init(|| Regex::new(r"^https?://.+/[[:word:]]{2}/sharing/[^/]+/?$").unwrap());
@@ -137,6 +195,15 @@ mod tests {
         assert!(matches!(result, Ok(Backend::Immich)));
     }
 
+    #[test]
+    fn when_file_url_then_detect_backend_returns_local() {
+        const SHARE_LINK: &str = "file:///home/pi/photos/";
+
+        let result = detect_backend(&Url::parse(SHARE_LINK).unwrap());
+
+        assert!(matches!(result, Ok(Backend::Local)));
+    }
+
     #[test]
     fn when_invalid_share_link_then_detect_backend_returns_error() {
         const SHARE_LINK: &str = "http://fake.backend/unknown/path/fake-sharing-link";
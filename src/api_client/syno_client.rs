@@ -6,16 +6,22 @@ use std::{
 
 use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
+use chrono::{DateTime, NaiveDateTime};
 use regex::Regex;
 use serde::Deserialize;
+use serde_json::Value;
 use syno_api::dto::{ApiResponse, List};
 
 use crate::{
     api_client::{ApiClient, LoginError, SharingId, SortBy},
     cli::SourceSize,
     http::{read_response, CookieStore, HttpClient, HttpResponse, InvalidHttpResponse, Url},
+    metadata::Metadata,
+    metadata_cache::CachedPhoto,
 };
 
+pub const DEFAULT_SHARING_HEADER: &str = "X-SYNO-SHARING";
+
 pub struct SynoApiClient<'a, H, C> {
     http_client: &'a H,
     cookie_store: &'a C,
@@ -23,6 +29,11 @@ pub struct SynoApiClient<'a, H, C> {
     api_thumbnail_get_url: Url,
     sharing_id: SharingId,
     password: &'a Option<String>,
+    album_name: &'a Option<String>,
+    /// Id of the album selected by `album_name`, resolved on first use
+    album_id: OnceLock<u32>,
+    /// Name of the HTTP header carrying the sharing id
+    sharing_header: &'a str,
 }
 
 impl<H: HttpClient, C: CookieStore> ApiClient for SynoApiClient<'_, H, C> {
@@ -38,6 +49,8 @@ impl<H: HttpClient, C: CookieStore> ApiClient for SynoApiClient<'_, H, C> {
             ("method", "login"),
             ("version", "1"),
             ("sharing_id", &self.sharing_id),
+            /* Sent as is, without quoting. Special characters are escaped by the form
+             * encoding of the HTTP client */
             ("password", self.password.as_deref().unwrap_or_default()),
         ];
         let response = self
@@ -61,20 +74,25 @@ impl<H: HttpClient, C: CookieStore> ApiClient for SynoApiClient<'_, H, C> {
     }
 
     fn get_photo_metadata(&self, sort_by: SortBy) -> Result<Vec<Self::Photo>> {
-        let params = [
+        let album_id = self.album_id()?.map(|id| id.to_string());
+        let sort_by = sort_by.to_string();
+        let mut params = vec![
             ("api", syno_api::foto::browse::item::API),
             ("method", "list"),
             ("version", "4"),
             ("additional", "[\"thumbnail\"]"),
             ("offset", "0"),
             ("limit", "5000"), // Limit imposed by API
-            ("sort_by", &sort_by.to_string()),
+            ("sort_by", &sort_by),
             ("sort_direction", "asc"),
         ];
+        if let Some(album_id) = &album_id {
+            params.push(("album_id", album_id));
+        }
         let response = self.http_client.post(
             self.api_url.as_str(),
             &params,
-            Some(("X-SYNO-SHARING", &self.sharing_id)),
+            Some((self.sharing_header, &self.sharing_id)),
         )?;
         read_response(response, |response| {
             let dto = response.json::<ApiResponse<List<Self::Photo>>>()?;
@@ -83,7 +101,9 @@ impl<H: HttpClient, C: CookieStore> ApiClient for SynoApiClient<'_, H, C> {
             } else {
                 Ok(dto
                     .data
-                    .expect("data field should be populated for successful response")
+                    .ok_or_else(|| {
+                        anyhow!("Invalid Synology API 'get album' response without data")
+                    })?
                     .list)
             }
         })
@@ -119,6 +139,127 @@ impl<H: HttpClient, C: CookieStore> ApiClient for SynoApiClient<'_, H, C> {
     }
 }
 
+impl Metadata for syno_api::foto::browse::item::dto::Item {
+    fn id(&self) -> String {
+        self.id.to_string()
+    }
+
+    fn cache_key(&self) -> String {
+        match self
+            .additional
+            .as_ref()
+            .and_then(|additional| additional.thumbnail.as_ref())
+        {
+            Some(thumbnail) => format!("{}_{}", thumbnail.unit_id, thumbnail.cache_key),
+            None => self.id(),
+        }
+    }
+
+    fn date(&self) -> Option<NaiveDateTime> {
+        /* Synology Photos reports the time taken as wall-clock time of the place where the photo
+         * was taken, encoded as if it was UTC */
+        DateTime::from_timestamp(i64::try_from(self.time).ok()?, 0)
+            .map(|date_time| date_time.naive_utc())
+    }
+
+    fn location(&self) -> Option<String> {
+        None
+    }
+
+    fn file_name(&self) -> Option<String> {
+        Some(self.filename.clone())
+    }
+
+    fn camera(&self) -> Option<String> {
+        None
+    }
+
+    fn caption(&self) -> Option<String> {
+        None
+    }
+}
+
+impl CachedPhoto for syno_api::foto::browse::item::dto::Item {
+    fn to_cache(&self) -> Result<Value> {
+        Ok(serde_json::to_value(self)?)
+    }
+
+    fn from_cache(value: Value) -> Result<Self> {
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+impl<H: HttpClient, C: CookieStore> SynoApiClient<'_, H, C> {
+    /// Enumerates albums exposed by the share link. Usually there is only one, but some shares
+    /// contain multiple albums.
+    fn get_albums(&self) -> Result<Vec<Album>> {
+        let params = [
+            ("api", "SYNO.Foto.Browse.Album"),
+            ("method", "get"),
+            ("version", "1"),
+            ("passphrase", &self.sharing_id),
+        ];
+        let response = self.http_client.post(
+            self.api_url.as_str(),
+            &params,
+            Some((self.sharing_header, &self.sharing_id)),
+        )?;
+        read_response(response, |response| {
+            let dto = response.json::<ApiResponse<List<Album>>>()?;
+            if !dto.success {
+                match dto.error {
+                    Some(error) => bail!(InvalidApiResponse("get album", error.code)),
+                    None => bail!("Invalid Synology API 'get album' response without error code"),
+                }
+            } else {
+                Ok(dto
+                    .data
+                    .ok_or_else(|| {
+                        anyhow!("Invalid Synology API 'get album' response without data")
+                    })?
+                    .list)
+            }
+        })
+    }
+
+    /// Returns id of the album selected with `album_name`, `None` when no album is selected.
+    /// Albums are enumerated only once, the id is reused by the following calls
+    fn album_id(&self) -> Result<Option<u32>> {
+        let Some(album_name) = self.album_name else {
+            return Ok(None);
+        };
+        if let Some(id) = self.album_id.get() {
+            return Ok(Some(*id));
+        }
+        let id = self.find_album_id(album_name)?;
+        Ok(Some(*self.album_id.get_or_init(|| id)))
+    }
+
+    /// Selects an album by its name, or else by its position (starting at 1) in the share
+    fn find_album_id(&self, album_name: &str) -> Result<u32> {
+        let albums = self.get_albums()?;
+        let by_name = albums.iter().find(|album| album.name == album_name);
+        let by_index = || {
+            album_name
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| albums.get(index.checked_sub(1)?))
+        };
+        match by_name.or_else(by_index) {
+            Some(album) => Ok(album.id),
+            None => {
+                let available = albums
+                    .iter()
+                    .enumerate()
+                    .map(|(index, album)| format!("{} '{}'", index + 1, album.name))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                bail!("Album '{album_name}' not found. Available albums: {available}")
+            }
+        }
+    }
+}
+
 impl<'a, H, C> SynoApiClient<'a, H, C> {
     pub fn build(http_client: &'a H, cookie_store: &'a C, share_link: &Url) -> Result<Self> {
         let (api_url, api_thumbnail_get_url, sharing_id) = parse_share_link(share_link)?;
@@ -129,6 +270,9 @@ impl<'a, H, C> SynoApiClient<'a, H, C> {
             api_thumbnail_get_url,
             sharing_id,
             password: &None,
+            album_name: &None,
+            album_id: OnceLock::new(),
+            sharing_header: DEFAULT_SHARING_HEADER,
         })
     }
 
@@ -136,6 +280,19 @@ impl<'a, H, C> SynoApiClient<'a, H, C> {
         self.password = password;
         self
     }
+
+    /// Selects an album by name (or position in the share, starting at 1) when the share link
+    /// exposes more than one
+    pub fn with_album_name(mut self, album_name: &'a Option<String>) -> Self {
+        self.album_name = album_name;
+        self
+    }
+
+    /// Overrides the name of the header carrying the sharing id, e.g. for proxies rewriting it
+    pub fn with_sharing_header(mut self, sharing_header: &'a str) -> Self {
+        self.sharing_header = sharing_header;
+        self
+    }
 }
 
 /// Returns Synology Photos API URL and sharing id extracted from album share link
@@ -162,6 +319,12 @@ fn parse_share_link(share_link: &Url) -> Result<(Url, Url, SharingId)> {
 #[derive(Debug, Deserialize)]
 pub struct Login {/* Empty brackets are needed for the deserializer to work */}
 
+#[derive(Debug, Deserialize)]
+pub struct Album {
+    pub id: u32,
+    pub name: String,
+}
+
 #[derive(Debug)]
 pub struct InvalidApiResponse(&'static str, u16);
 
@@ -177,8 +340,15 @@ impl Display for InvalidApiResponse {
 
 #[cfg(test)]
 mod tests {
+    use syno_api::foto::browse::item::dto::Item;
+
     use super::*;
 
+    use crate::{
+        http::Jar,
+        test_helpers::{self, MockHttpClient},
+    };
+
     #[test]
     fn parse_share_link_is_ok_for_valid_link() {
         test_case(
@@ -211,4 +381,261 @@ mod tests {
             assert_eq!(sharing_id.0, "FakeSharingId");
         }
     }
+
+    #[test]
+    fn cached_photo_round_trips_through_json() {
+        let photo = Item {
+            id: 42,
+            r#type: "photo".to_string(),
+            filename: "IMG_0042.jpg".to_string(),
+            filesize: 1024,
+            time: 1_714_573_800,
+            indexed_time: 1_714_600_000,
+            owner_user_id: 2,
+            folder_id: 7,
+            ..test_helpers::new_photo_dto(42, "abc")
+        };
+
+        let result = Item::from_cache(photo.to_cache().unwrap());
+
+        assert_eq!(result.unwrap(), photo);
+    }
+
+    #[test]
+    fn login_sends_password_with_special_characters_unmodified() {
+        test_case(r#"pass"word"#);
+        test_case(r"pass\word");
+        test_case(r#""quoted\""#);
+        test_case("pa&ss=wo rd%");
+
+        fn test_case(password: &'static str) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+            let mut client_mock = MockHttpClient::new();
+            client_mock
+                .expect_post()
+                .withf(move |_, form, _| form.contains(&("password", password)))
+                .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+            let cookie_store = Jar::default();
+            let password = Some(password.to_string());
+            let api_client = SynoApiClient::build(
+                &client_mock,
+                &cookie_store,
+                &Url::parse(SHARE_LINK).unwrap(),
+            )
+            .unwrap()
+            .with_password(&password);
+
+            let result = api_client.login();
+
+            assert!(result.is_ok());
+            client_mock.checkpoint();
+        }
+    }
+
+    #[test]
+    fn when_sharing_header_is_overridden_then_get_photo_metadata_sends_it() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, header| {
+                test_helpers::is_list_form(form)
+                    && *header == Some(("x-syno-sharing-custom", "FakeSharingId"))
+            })
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(42, "photo42")],
+                }))
+            });
+        let cookie_store = Jar::default();
+        let api_client = SynoApiClient::build(
+            &client_mock,
+            &cookie_store,
+            &Url::parse(SHARE_LINK).unwrap(),
+        )
+        .unwrap()
+        .with_sharing_header("x-syno-sharing-custom");
+
+        let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+        assert!(result.is_ok());
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_album_name_matches_then_get_photo_metadata_lists_selected_album() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, header| {
+                is_get_album_form(form) && *header == Some(("X-SYNO-SHARING", "FakeSharingId"))
+            })
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![new_album_dto(1, "Holidays"), new_album_dto(2, "Family")],
+                }))
+            });
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| {
+                form.starts_with(&[("api", syno_api::foto::browse::item::API)])
+                    && form.ends_with(&[("album_id", "2")])
+            })
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(42, "photo42")],
+                }))
+            });
+        let cookie_store = Jar::default();
+        let album_name = Some("Family".to_string());
+        let api_client = SynoApiClient::build(
+            &client_mock,
+            &cookie_store,
+            &Url::parse(SHARE_LINK).unwrap(),
+        )
+        .unwrap()
+        .with_album_name(&album_name);
+
+        let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            vec![test_helpers::new_photo_dto(42, "photo42")]
+        );
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_album_name_does_not_match_then_get_photo_metadata_returns_error_with_available_names() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| is_get_album_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![new_album_dto(1, "Holidays"), new_album_dto(2, "Family")],
+                }))
+            });
+        let cookie_store = Jar::default();
+        let album_name = Some("Missing".to_string());
+        let api_client = SynoApiClient::build(
+            &client_mock,
+            &cookie_store,
+            &Url::parse(SHARE_LINK).unwrap(),
+        )
+        .unwrap()
+        .with_album_name(&album_name);
+
+        let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+        assert!(result.is_err_and(|e| {
+            e.to_string() == "Album 'Missing' not found. Available albums: 1 'Holidays', 2 'Family'"
+        }));
+    }
+
+    #[test]
+    fn when_album_is_selected_by_position_then_get_photo_metadata_resolves_album_once() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| is_get_album_form(form))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![new_album_dto(7, "Holidays"), new_album_dto(9, "Family")],
+                }))
+            });
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| {
+                form.starts_with(&[("api", syno_api::foto::browse::item::API)])
+                    && form.ends_with(&[("album_id", "9")])
+            })
+            .times(2)
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(42, "photo42")],
+                }))
+            });
+        let cookie_store = Jar::default();
+        let album_name = Some("2".to_string());
+        let api_client = SynoApiClient::build(
+            &client_mock,
+            &cookie_store,
+            &Url::parse(SHARE_LINK).unwrap(),
+        )
+        .unwrap()
+        .with_album_name(&album_name);
+
+        let results = [
+            api_client.get_photo_metadata(SortBy::TakenTime),
+            api_client.get_photo_metadata(SortBy::TakenTime),
+        ];
+
+        assert!(results.iter().all(Result::is_ok));
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_get_album_response_is_incomplete_then_get_photo_metadata_returns_error() {
+        test_case(false, "without error code");
+        test_case(true, "without data");
+
+        fn test_case(success: bool, expected_error: &str) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+            let mut client_stub = MockHttpClient::new();
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| is_get_album_form(form))
+                .return_once(move |_, _, _| {
+                    let mut response = test_helpers::new_ok_response();
+                    response
+                        .expect_json::<ApiResponse<List<Album>>>()
+                        .return_once(move || {
+                            Ok(ApiResponse {
+                                success,
+                                error: None,
+                                data: None,
+                            })
+                        });
+                    Ok(response)
+                });
+            let cookie_store = Jar::default();
+            let album_name = Some("Family".to_string());
+            let api_client = SynoApiClient::build(
+                &client_stub,
+                &cookie_store,
+                &Url::parse(SHARE_LINK).unwrap(),
+            )
+            .unwrap()
+            .with_album_name(&album_name);
+
+            let result = api_client.get_photo_metadata(SortBy::TakenTime);
+
+            assert!(
+                result.is_err_and(|e| e.to_string().contains(expected_error)),
+                "{success}"
+            );
+        }
+    }
+
+    fn is_get_album_form(form: &[(&str, &str)]) -> bool {
+        form.eq(&[
+            ("api", "SYNO.Foto.Browse.Album"),
+            ("method", "get"),
+            ("version", "1"),
+            ("passphrase", "FakeSharingId"),
+        ])
+    }
+
+    fn new_album_dto(id: u32, name: &str) -> Album {
+        Album {
+            id,
+            name: name.to_string(),
+        }
+    }
 }
@@ -0,0 +1,338 @@
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+use bytes::Bytes;
+use chrono::NaiveDateTime;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    api_client::{is_supported_image, ApiClient, SortBy},
+    cli::SourceSize,
+    http::{read_response, HttpClient, HttpResponse, Url},
+    metadata::Metadata,
+    metadata_cache::CachedPhoto,
+    LoginError,
+};
+
+/// Reads photos from an HTTP directory listing, e.g. nginx autoindex, Apache mod_autoindex or a
+/// WebDAV share browsed with GET requests. Subdirectories are not traversed.
+pub struct WebDavApiClient<'a, H> {
+    http_client: &'a H,
+    directory_url: Url,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebDavPhoto {
+    pub url: Url,
+    pub file_name: String,
+    /// Modification time shown in the directory listing
+    pub modified: Option<NaiveDateTime>,
+}
+
+impl<H: HttpClient> ApiClient for WebDavApiClient<'_, H> {
+    type Photo = WebDavPhoto;
+
+    fn is_logged_in(&self) -> bool {
+        false
+    }
+
+    fn login(&self) -> Result<(), LoginError> {
+        /* There is nothing to log in to. Check that the directory can be listed, and if not,
+         * return LoginError so the app terminates. */
+        self.get_photo_metadata(SortBy::FileName)
+            .map_err(LoginError)?;
+        Ok(())
+    }
+
+    fn get_photo_metadata(&self, sort_by: SortBy) -> Result<Vec<Self::Photo>> {
+        let response = self.http_client.get(self.directory_url.as_str(), &[])?;
+        let listing = read_response(response, |r| r.text())?;
+        let mut photos = parse_listing(&listing, &self.directory_url)?;
+        /* Sorting is stable, so photos without dates keep the order of the listing */
+        match sort_by {
+            SortBy::TakenTime => photos.sort_by_key(|photo| photo.modified),
+            SortBy::FileName => photos.sort_by(|a, b| a.file_name.cmp(&b.file_name)),
+        }
+        Ok(photos)
+    }
+
+    fn get_photo_bytes(&self, photo: &Self::Photo, _: SourceSize) -> Result<Bytes> {
+        let response = self.http_client.get(photo.url.as_str(), &[])?;
+        read_response(response, |r| r.bytes())
+    }
+}
+
+impl Metadata for WebDavPhoto {
+    fn id(&self) -> String {
+        self.url.to_string()
+    }
+
+    fn date(&self) -> Option<NaiveDateTime> {
+        self.modified
+    }
+
+    fn location(&self) -> Option<String> {
+        None
+    }
+
+    fn file_name(&self) -> Option<String> {
+        Some(self.file_name.clone())
+    }
+
+    fn camera(&self) -> Option<String> {
+        None
+    }
+
+    fn caption(&self) -> Option<String> {
+        None
+    }
+}
+
+impl CachedPhoto for WebDavPhoto {
+    fn to_cache(&self) -> Result<Value> {
+        Ok(json!({
+            "url": self.url.as_str(),
+            "file_name": self.file_name,
+            "modified": self.modified,
+        }))
+    }
+
+    fn from_cache(value: Value) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Record {
+            url: String,
+            file_name: String,
+            modified: Option<NaiveDateTime>,
+        }
+
+        let record: Record = serde_json::from_value(value)?;
+        Ok(Self {
+            url: Url::parse(&record.url)?,
+            file_name: record.file_name,
+            modified: record.modified,
+        })
+    }
+}
+
+impl<'a, H> WebDavApiClient<'a, H> {
+    pub fn build(http_client: &'a H, directory_url: &Url) -> Result<Self> {
+        let mut directory_url = directory_url.clone();
+        if !directory_url.path().ends_with('/') {
+            /* Otherwise relative links would resolve against the parent directory */
+            directory_url.set_path(&format!("{}/", directory_url.path()));
+        }
+        Ok(Self {
+            http_client,
+            directory_url,
+        })
+    }
+}
+
+/// Extracts links to supported image files from a directory listing HTML page
+fn parse_listing(listing: &str, directory_url: &Url) -> Result<Vec<WebDavPhoto>> {
+    static LINK_RE: OnceLock<Regex> = OnceLock::new();
+    let link_re = LINK_RE.get_or_init(|| {
+        /* Link, followed by the rest of the line which may contain the modification time */
+        Regex::new(r#"(?i)<a\s[^>]*href="([^"?#]+)"[^>]*>.*?</a>([^\n]*)"#).unwrap()
+    });
+    let mut photos = vec![];
+    for captures in link_re.captures_iter(listing) {
+        let href = &captures[1];
+        if !is_supported_image(href) {
+            continue;
+        }
+        let url = directory_url.join(href)?;
+        let Some(file_name) = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .map(percent_decode)
+        else {
+            continue;
+        };
+        photos.push(WebDavPhoto {
+            url,
+            file_name,
+            modified: parse_modified(&captures[2]),
+        });
+    }
+    if photos.is_empty() {
+        bail!("No photos found in directory listing of {directory_url}");
+    }
+    Ok(photos)
+}
+
+/// Parses modification time as shown by nginx (`01-May-2024 14:30`) or Apache
+/// (`2024-05-01 14:30`) directory listings
+fn parse_modified(text: &str) -> Option<NaiveDateTime> {
+    static DATE_RE: OnceLock<Regex> = OnceLock::new();
+    let date_re = DATE_RE.get_or_init(|| {
+        Regex::new(r"\d{2}-[[:alpha:]]{3}-\d{4} \d{2}:\d{2}|\d{4}-\d{2}-\d{2} \d{2}:\d{2}").unwrap()
+    });
+    let date = date_re.find(text)?.as_str();
+    ["%d-%b-%Y %H:%M", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(date, format).ok())
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    use crate::test_helpers::{self, MockHttpClient};
+
+    const DIRECTORY_URL: &str = "http://fake.server/photos/";
+
+    const NGINX_LISTING: &str = r#"<html>
+<head><title>Index of /photos/</title></head>
+<body>
+<h1>Index of /photos/</h1><hr><pre><a href="../">../</a>
+<a href="2024/">2024/</a>                                              02-Jan-2024 10:00       -
+<a href="b.jpg">b.jpg</a>                                              01-May-2024 14:30   123456
+<a href="notes.txt">notes.txt</a>                                      01-May-2024 14:30      100
+<a href="Summer%20Trip.JPEG">Summer Trip.JPEG</a>                      03-Apr-2023 08:15   654321
+</pre><hr></body>
+</html>"#;
+
+    #[test]
+    fn parse_listing_reads_images_from_nginx_listing() {
+        let result = parse_listing(NGINX_LISTING, &Url::parse(DIRECTORY_URL).unwrap());
+
+        assert!(result.is_ok());
+        assert_eq!(
+            result.unwrap(),
+            vec![
+                WebDavPhoto {
+                    url: Url::parse("http://fake.server/photos/b.jpg").unwrap(),
+                    file_name: "b.jpg".to_string(),
+                    modified: Some(new_date_time(2024, 5, 1, 14, 30)),
+                },
+                WebDavPhoto {
+                    url: Url::parse("http://fake.server/photos/Summer%20Trip.JPEG").unwrap(),
+                    file_name: "Summer Trip.JPEG".to_string(),
+                    modified: Some(new_date_time(2023, 4, 3, 8, 15)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_listing_reads_images_from_apache_listing() {
+        const LISTING: &str = r#"<table>
+<tr><td><a href="/photos/a.png">a.png</a></td><td align="right">2022-12-24 18:00  </td></tr>
+<tr><td><a href="c.jpg">c.jpg</a></td><td align="right">  - </td></tr>
+</table>"#;
+
+        let result = parse_listing(LISTING, &Url::parse(DIRECTORY_URL).unwrap());
+
+        assert!(result.is_ok());
+        let photos = result.unwrap();
+        assert_eq!(photos.len(), 2);
+        assert_eq!(photos[0].url.as_str(), "http://fake.server/photos/a.png");
+        assert_eq!(photos[0].modified, Some(new_date_time(2022, 12, 24, 18, 0)));
+        assert_eq!(photos[1].url.as_str(), "http://fake.server/photos/c.jpg");
+        assert_eq!(photos[1].modified, None);
+    }
+
+    #[test]
+    fn when_listing_has_no_images_then_parse_listing_returns_error() {
+        const LISTING: &str = r#"<a href="../">../</a><a href="notes.txt">notes.txt</a>"#;
+
+        let result = parse_listing(LISTING, &Url::parse(DIRECTORY_URL).unwrap());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn get_photo_metadata_lists_directory_and_sorts_photos() {
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_get()
+            .withf(|url, query| url == "http://fake.server/photos/" && query.is_empty())
+            .returning(|_, _| {
+                let mut response = test_helpers::new_ok_response();
+                response
+                    .expect_text()
+                    .return_once(|| Ok(NGINX_LISTING.to_string()));
+                Ok(response)
+            });
+        /* Missing trailing slash is added */
+        let api_client = WebDavApiClient::build(
+            &client_stub,
+            &Url::parse("http://fake.server/photos").unwrap(),
+        )
+        .unwrap();
+
+        let by_date = api_client.get_photo_metadata(SortBy::TakenTime).unwrap();
+        let by_name = api_client.get_photo_metadata(SortBy::FileName).unwrap();
+
+        let names = |photos: &[WebDavPhoto]| {
+            photos
+                .iter()
+                .map(|photo| photo.file_name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&by_date), ["Summer Trip.JPEG", "b.jpg"]);
+        assert_eq!(names(&by_name), ["Summer Trip.JPEG", "b.jpg"]);
+    }
+
+    #[test]
+    fn get_photo_bytes_fetches_photo_url() {
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_get()
+            .withf(|url, _| url == "http://fake.server/photos/b.jpg")
+            .return_once(|_, _| {
+                let mut response = test_helpers::new_ok_response();
+                response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(b"photo bytes")));
+                Ok(response)
+            });
+        let api_client =
+            WebDavApiClient::build(&client_stub, &Url::parse(DIRECTORY_URL).unwrap()).unwrap();
+        let photo = WebDavPhoto {
+            url: Url::parse("http://fake.server/photos/b.jpg").unwrap(),
+            file_name: "b.jpg".to_string(),
+            modified: None,
+        };
+
+        let result = api_client.get_photo_bytes(&photo, SourceSize::L);
+
+        assert!(result.is_ok());
+        assert_eq!(result.unwrap(), Bytes::from_static(b"photo bytes"));
+    }
+
+    fn new_date_time(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, min, 0)
+            .unwrap()
+    }
+}
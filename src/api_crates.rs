@@ -1,9 +1,19 @@
+use std::time::Duration;
+
 use anyhow::{anyhow, bail, Result};
 
 use crate::http::{HttpClient, HttpResponse};
 
+/// Update check is not essential, so it should fail fast instead of waiting for the (potentially
+/// long) timeout configured for the album server
+const TIMEOUT: Duration = Duration::from_secs(5);
+
 pub fn get_latest_version(client: &impl HttpClient) -> Result<dto::Crate> {
-    let response = client.get("https://index.crates.io/sy/no/syno-photo-frame", &[])?;
+    let response = client.get_with_timeout(
+        "https://index.crates.io/sy/no/syno-photo-frame",
+        &[],
+        TIMEOUT,
+    )?;
     let status = response.status();
     if status.is_success() {
         response
@@ -51,8 +61,9 @@ mod tests {
             .return_once(|| Ok(TEXT.to_string()));
         let mut client_mock = MockHttpClient::new();
         client_mock
-            .expect_get()
-            .return_once(|_, _| Ok(response_mock));
+            .expect_get_with_timeout()
+            .withf(|_, _, timeout| *timeout == TIMEOUT)
+            .return_once(|_, _, _| Ok(response_mock));
 
         let result = get_latest_version(&client_mock);
 
@@ -1,7 +1,12 @@
+use std::path::Path;
+
 use anyhow::Result;
+use image::Luma;
+use qrcode::QrCode;
 
 use crate::{
     cli::Rotation,
+    http::Url,
     img::{self, DynamicImage, Framed},
 };
 
@@ -13,7 +18,19 @@ pub fn welcome_screen(screen_size: (u32, u32), rotation: Rotation) -> Result<Dyn
     load_and_resize(LOADING, screen_size, rotation)
 }
 
-pub fn error_screen(screen_size: (u32, u32), rotation: Rotation) -> Result<DynamicImage> {
+/// Error screen loaded from `path` (see [crate::cli::Cli::error_image]), or the embedded one when
+/// `path` is `None` or fails to load
+pub fn error_screen(
+    path: Option<&Path>,
+    screen_size: (u32, u32),
+    rotation: Rotation,
+) -> Result<DynamicImage> {
+    if let Some(path) = path {
+        match img::open(path) {
+            Ok(image) => return Ok(image.fit_to_screen(screen_size, rotation)),
+            Err(error) => log::error!("Error image {}: {error}", path.to_string_lossy()),
+        }
+    }
     #[cfg(not(target_os = "windows"))]
     const ERROR_BYTES: &[u8] = include_bytes!("../assets/Error.jpeg");
     #[cfg(target_os = "windows")]
@@ -32,6 +49,32 @@ pub fn update_icon((screen_width, _): (u32, u32), rotation: Rotation) -> Result<
     Ok(Framed::resize(&img::load_from_memory(UPDATE_BYTES)?, icon_w, icon_h).rotate(rotation))
 }
 
+pub fn logo(
+    path: &Path,
+    (screen_width, _): (u32, u32),
+    rotation: Rotation,
+) -> Result<DynamicImage> {
+    /* Fit the logo within 1/8th of the screen width, preserving its aspect ratio */
+    let (logo_w, logo_h) = (screen_width / 8, screen_width / 8);
+    Ok(Framed::resize(&img::open(path)?, logo_w, logo_h).rotate(rotation))
+}
+
+/// QR code linking to the shared album. Each QR module is rendered as a square of
+/// `ALBUM_QR_MODULE_SIZE` pixels, surrounded by the standard quiet zone.
+pub fn album_qr(share_link: &Url) -> Result<DynamicImage> {
+    let qr_code = QrCode::new(share_link.as_str())?;
+    let image = qr_code
+        .render::<Luma<u8>>()
+        .module_dimensions(ALBUM_QR_MODULE_SIZE, ALBUM_QR_MODULE_SIZE)
+        .build();
+    /* Textures expect RGB pixels */
+    Ok(DynamicImage::ImageRgb8(
+        DynamicImage::ImageLuma8(image).into_rgb8(),
+    ))
+}
+
+const ALBUM_QR_MODULE_SIZE: u32 = 8;
+
 fn load_and_resize(
     bytes: &[u8],
     screen_size: (u32, u32),
@@ -39,3 +82,73 @@ fn load_and_resize(
 ) -> Result<DynamicImage> {
     Ok(img::load_from_memory(bytes)?.fit_to_screen(screen_size, rotation))
 }
+
+#[cfg(test)]
+mod tests {
+    use image::GenericImageView;
+    use qrcode::Color;
+
+    use super::*;
+
+    #[test]
+    fn when_error_image_is_provided_then_error_screen_uses_it() {
+        const SCREEN_SIZE: (u32, u32) = (198, 102);
+        let path = Path::new("assets/test_loading.jpeg");
+
+        let result = error_screen(Some(path), SCREEN_SIZE, Rotation::D0);
+
+        assert!(result.is_ok());
+        let expected = load_and_resize(&std::fs::read(path).unwrap(), SCREEN_SIZE, Rotation::D0);
+        assert!(result.unwrap() == expected.unwrap());
+    }
+
+    #[test]
+    fn when_error_image_is_missing_then_error_screen_falls_back_to_embedded_one() {
+        const SCREEN_SIZE: (u32, u32) = (198, 102);
+
+        let result = error_screen(
+            Some(Path::new("assets/missing.jpeg")),
+            SCREEN_SIZE,
+            Rotation::D0,
+        );
+
+        assert!(result.is_ok());
+        let embedded = error_screen(None, SCREEN_SIZE, Rotation::D0);
+        assert!(result.unwrap() == embedded.unwrap());
+    }
+
+    #[test]
+    fn album_qr_encodes_share_link() {
+        const SHARE_LINK: &str = "https://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const QUIET_ZONE_MODULES: u32 = 4;
+        let share_link = Url::parse(SHARE_LINK).unwrap();
+
+        let result = album_qr(&share_link);
+
+        assert!(result.is_ok());
+        let image = result.unwrap();
+        let expected = QrCode::new(SHARE_LINK).unwrap();
+        let modules = expected.width() as u32;
+        let image_modules = modules + 2 * QUIET_ZONE_MODULES;
+        assert_eq!(
+            image.dimensions(),
+            (
+                image_modules * ALBUM_QR_MODULE_SIZE,
+                image_modules * ALBUM_QR_MODULE_SIZE
+            )
+        );
+        for (i, color) in expected.to_colors().into_iter().enumerate() {
+            let (x, y) = (i as u32 % modules, i as u32 / modules);
+            /* Sample the center of each module */
+            let pixel = image.get_pixel(
+                (x + QUIET_ZONE_MODULES) * ALBUM_QR_MODULE_SIZE + ALBUM_QR_MODULE_SIZE / 2,
+                (y + QUIET_ZONE_MODULES) * ALBUM_QR_MODULE_SIZE + ALBUM_QR_MODULE_SIZE / 2,
+            );
+            let expected_pixel = match color {
+                Color::Dark => [0, 0, 0, 255],
+                Color::Light => [255, 255, 255, 255],
+            };
+            assert_eq!(pixel.0, expected_pixel, "module ({x}, {y})");
+        }
+    }
+}
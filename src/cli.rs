@@ -2,12 +2,34 @@
 
 pub use clap::Parser;
 
-use std::{path::PathBuf, time::Duration};
+use std::{
+    ffi::OsString,
+    fs,
+    io::BufRead,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use anyhow::{bail, Result};
-use clap::{builder::TypedValueParser as _, ValueEnum};
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveTime;
+use clap::{
+    builder::{Resettable, TypedValueParser as _},
+    error::ErrorKind,
+    Command, CommandFactory, FromArgMatches, ValueEnum,
+};
+use globset::{GlobBuilder, GlobMatcher};
+use image::{ImageFormat, Rgb};
 
-use crate::http::Url;
+use crate::{
+    api_client::syno_client::DEFAULT_SHARING_HEADER,
+    env::{self, Env, EnvImpl},
+    http::Url,
+    slideshow::{DEFAULT_LARGE_ALBUM_WARN, DEFAULT_MAX_RETRIES},
+};
+
+#[cfg(test)]
+use {crate::env::MockEnv, mockall::predicate::eq};
 
 /// Synology Photos or Immich album fullscreen slideshow
 ///
@@ -19,15 +41,41 @@ pub struct Cli {
     #[arg(long = "backend", value_enum, default_value_t = Backend::Auto)]
     pub backend: Backend,
 
-    /// Link to a publicly shared album on Synology Photos or Immich
+    /// Link to a publicly shared album on Synology Photos or Immich, or a local directory with
+    /// photos
+    ///
+    /// Note that the album's privacy settings must be set to Public. Use - to read the link from
+    /// standard input. ${VAR} references to environment variables are expanded, also in paths
+    #[arg(required_unless_present = "manifest", value_parser = try_parse_share_link)]
+    pub share_link: Option<Url>,
+
+    /// Path to a JSON or CSV file listing local photos to display instead of a shared album
     ///
-    /// Note that the album's privacy settings must be set to Public
-    pub share_link: Url,
+    /// Each entry has a `path` (relative to the manifest's directory), and optionally a `date`
+    /// (e.g. 2024-05-01 or 2024-05-01T14:30:00) and a `location`
+    #[arg(long, value_parser = try_parse_path)]
+    pub manifest: Option<PathBuf>,
+
+    /// Source of dates of local photos, in a local directory or listed in --manifest without a
+    /// `date`
+    #[arg(long, value_enum, default_value_t = LocalDateSource::Exif)]
+    pub local_date_source: LocalDateSource,
+
+    /// Name of the HTTP header carrying the Synology Photos sharing id. For troubleshooting
+    /// proxies which rewrite headers
+    #[arg(long, hide = true, default_value = DEFAULT_SHARING_HEADER)]
+    pub syno_sharing_header: String,
 
     /// Link protection password if set in the album sharing settings
     #[arg(short = 'p', long = "password")]
     pub password: Option<String>,
 
+    /// Name of the album to display when the Synology Photos share link exposes multiple albums,
+    /// or its position in the share (starting at 1) when no album has that name. Ignored when
+    /// using Immich backend.
+    #[arg(long)]
+    pub album_name: Option<String>,
+
     /// Photo change interval in seconds
     ///
     /// Must be greater or equal to 5. Note that it is only guaranteed that the display time will
@@ -44,18 +92,121 @@ pub struct Cli {
     #[arg(short = 'o', long, value_enum, default_value_t = Order::ByDate)]
     pub order: Order,
 
+    /// With random order, move the last N photos displayed in a pass through the album to the end
+    /// of the next pass, so that photos are not displayed again shortly after the album starts over.
+    /// At most half of the album is moved
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub no_repeat_window: usize,
+
+    /// Handling of photos without a known date (which may be reported as taken on 1 January 1970)
+    #[arg(long, value_enum, default_value_t = UndatedPhotos::AsIs)]
+    pub undated_photos: UndatedPhotos,
+
     /// Start at randomly selected photo, then continue according to --order
     #[arg(long, default_value_t = false)]
     pub random_start: bool,
 
+    /// Exit after displaying every album photo once, instead of starting over. The last photo
+    /// stays on screen for the full --interval
+    #[arg(long, default_value_t = false)]
+    pub once: bool,
+
+    /// Hours of the day (local time) when the slideshow is displayed, e.g. 07:00-22:00. Outside of
+    /// them the screen is black and photos do not change, until the slideshow resumes where it
+    /// left off. May span midnight, e.g. 22:00-06:00
+    #[arg(long, value_name = "HH:MM-HH:MM", value_parser = try_parse_active_hours)]
+    pub active_hours: Option<ActiveHours>,
+
     /// Transition effect
     #[arg(short = 't', long, value_enum, default_value_t = Transition::Crossfade)]
     pub transition: Transition,
 
-    /// Background fill effect
-    #[arg(long, value_enum, default_value_t = Background::Blur)]
+    /// Effects picked from by --transition random. Include none to sometimes change photos
+    /// without an effect
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "crossfade,fade-to-black,zoom-blur,blur-bridge,slide",
+        value_parser = try_parse_random_transition)]
+    pub random_transitions: Vec<Transition>,
+
+    /// Direction in which photos move with --transition slide
+    #[arg(long, value_enum, default_value_t = SlideDirection::Left)]
+    pub slide_direction: SlideDirection,
+
+    /// Duration in seconds of the transition effect, e.g. 2.5 for a slower crossfade. The blur
+    /// bridge takes twice as long
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "1",
+        value_parser = try_parse_transition_duration)]
+    pub transition_duration: Duration,
+
+    /// What to display when a photo fails to load
+    #[arg(long, value_enum, default_value_t = OnError::ErrorScreen)]
+    pub on_error: OnError,
+
+    /// Duration in seconds of fading in from black at startup. Disabled when set to 0
+    #[arg(
+        long = "startup-fade-seconds",
+        default_value = "0",
+        value_parser = try_parse_fade_duration)]
+    pub startup_fade: Duration,
+
+    /// Minimum time in milliseconds between the welcome screen and the first photo transition.
+    /// Works around glitches of the first transition on displays which need time to warm up
+    #[arg(
+        long = "startup-settle-ms",
+        value_name = "MS",
+        default_value = "0",
+        value_parser = try_parse_millis)]
+    pub startup_settle: Duration,
+
+    /// Display the error screen when no photo arrives within SECONDS after startup (e.g. when the
+    /// server accepts connections but does not respond), instead of showing the welcome screen
+    /// indefinitely. Must be greater or equal to 5. The slideshow starts when the first photo
+    /// eventually arrives
+    #[arg(long, value_name = "SECONDS", value_parser = try_parse_duration)]
+    pub startup_timeout: Option<Duration>,
+
+    /// Background fill effect: blur, none (black), or a solid color given as #RRGGBB, e.g.
+    /// #808080 for a mid-gray matte
+    #[arg(long, default_value = "blur", value_parser = try_parse_background)]
     pub background: Background,
 
+    /// How photos are fitted to the screen
+    #[arg(long, value_enum, default_value_t = Fit::Contain)]
+    pub fit: Fit,
+
+    /// Maximum fraction of the screen area, between 0 and 1, that may be left to the background
+    /// fill. Photos whose aspect ratio differs from the screen so much that the background would
+    /// cover more are cropped to fill the whole screen instead. By default photos are never cropped
+    #[arg(long, value_name = "RATIO", value_parser = try_parse_ratio)]
+    pub max_letterbox_ratio: Option<f64>,
+
+    /// Crop photos to a WIDTH:HEIGHT aspect ratio (e.g. 4:3) before fitting them to the screen
+    ///
+    /// The center of the photo is kept, giving a uniform look to albums with photos of mixed
+    /// aspect ratios
+    #[arg(long, value_name = "W:H", value_parser = try_parse_aspect)]
+    pub crop_aspect: Option<(u32, u32)>,
+
+    /// Invert colors of displayed photos, including the background. Easier on the eyes at night
+    /// and helpful for some low-vision users
+    #[arg(long, default_value_t = false)]
+    pub invert: bool,
+
+    /// Gamma correction for panels displaying photos too dark (use a value above 1, e.g. 1.2) or
+    /// too bright (below 1). Applies to the photo together with its background
+    #[arg(long, value_name = "VALUE", default_value_t = 1.0, value_parser = try_parse_gamma)]
+    pub gamma: f64,
+
+    /// Path to an ICC profile of the display. Colors of photos are converted from sRGB to the
+    /// profile. Requires the app to be built with the `icc` feature
+    #[arg(long, value_parser = try_parse_path)]
+    pub icc_profile: Option<PathBuf>,
+
     /// Rotate display to match screen orientation
     #[arg(
         long = "rotate",
@@ -65,10 +216,65 @@ pub struct Cli {
     )]
     pub rotation: Rotation,
 
+    /// Screen resolution, e.g. 1920x1080, overriding the one detected automatically
+    ///
+    /// Use when the detected resolution is wrong, e.g. due to display driver or EDID issues
+    #[arg(long, value_parser = try_parse_resolution)]
+    pub resolution: Option<(u32, u32)>,
+
+    /// Process and render photos at a fraction of the screen resolution, between 0 and 1 (e.g.
+    /// 0.5), and let the GPU upscale them to the screen
+    ///
+    /// Reduces the load of weak devices (e.g. Raspberry Pi Zero) during transitions at the
+    /// expense of sharpness
+    #[arg(long, value_name = "SCALE", default_value_t = 1.0, value_parser = try_parse_render_scale)]
+    pub render_scale: f64,
+
+    /// Show an alignment grid (rule-of-thirds lines and a center crosshair) over photos, helpful
+    /// when mounting or leveling the frame. Press G on an attached keyboard to toggle the grid at
+    /// any time
+    #[arg(long, default_value_t = false)]
+    pub alignment_grid: bool,
+
+    /// Show a small preview of the next photo in the bottom-right corner of the screen, once the
+    /// next photo is ready
+    #[arg(long, default_value_t = false)]
+    pub show_preview: bool,
+
+    /// Show the current time (local time) in a corner of the screen, e.g. for a bedside frame
+    #[arg(long, default_value_t = false)]
+    pub show_clock: bool,
+
+    /// Screen corner of the clock, see --show-clock
+    #[arg(long, value_enum, default_value_t = Corner::TopRight)]
+    pub clock_corner: Corner,
+
+    /// Time format of the clock, see --show-clock
+    #[arg(long, value_enum, default_value_t = ClockFormat::H24)]
+    pub clock_format: ClockFormat,
+
+    /// Quit on any keyboard, mouse or touch input, e.g. when the slideshow is started as a
+    /// screensaver
+    #[arg(long, default_value_t = false)]
+    pub screensaver_mode: bool,
+
+    /// Path to an image file (e.g. a PNG with transparency) to overlay as a logo on every photo
+    #[arg(long, value_parser = try_parse_path)]
+    pub logo: Option<PathBuf>,
+
+    /// Screen corner to place the logo in
+    #[arg(long, value_enum, default_value_t = Corner::BottomRight)]
+    pub logo_corner: Corner,
+
     /// Path to a JPEG file to display during startup, replacing the default splash-screen
-    #[arg(long)]
+    #[arg(long, value_parser = try_parse_path)]
     pub splash: Option<PathBuf>,
 
+    /// Path to a JPEG file to display when a photo fails to load, replacing the default error
+    /// screen
+    #[arg(long, value_parser = try_parse_path)]
+    pub error_image: Option<PathBuf>,
+
     /// HTTP request timeout in seconds
     ///
     /// Must be greater or equal to 5. When server does not respond within the timeout, an
@@ -79,15 +285,399 @@ pub struct Cli {
         value_parser = clap::value_parser!(u16).range(5..))]
     pub timeout_seconds: u16,
 
-    /// Requested size of the photo as fetched from the Synology Photos. Can reduce network and CPU
-    /// utilization at the cost of image quality. Note: photos are still scaled to full-screen
-    /// size. Ignored when using Immich backend.
+    /// IP version used to connect to the server
+    ///
+    /// Forcing v4 can help on dual-stack networks where the server name resolves to an IPv6
+    /// address which is not reachable, e.g. blocked by a firewall, causing timeouts
+    #[arg(long, value_enum, default_value_t = IpVersion::Auto)]
+    pub ip_version: IpVersion,
+
+    /// Resolve HOST to IP address instead of using the system DNS resolver, e.g.
+    /// nas.local:192.168.1.10. Can be repeated for multiple hosts
+    #[arg(long, value_name = "HOST:IP", value_parser = try_parse_dns_override)]
+    pub resolve: Vec<(String, IpAddr)>,
+
+    /// Path to a PEM file with TLS client certificate, for servers requiring mutual TLS
+    /// authentication
+    #[arg(long, requires = "client_key", value_parser = try_parse_path)]
+    pub client_cert: Option<PathBuf>,
+
+    /// Path to a PEM file with PKCS #8 private key of the --client-cert
+    #[arg(long, requires = "client_cert", value_parser = try_parse_path)]
+    pub client_key: Option<PathBuf>,
+
+    /// Path to a PEM file with a certificate of a CA to trust, e.g. a private CA which signed the
+    /// server's certificate
+    #[arg(long, value_name = "PATH", value_parser = try_parse_path)]
+    pub cacert: Option<PathBuf>,
+
+    /// Accept any TLS certificate presented by the server, e.g. a self-signed one. DANGEROUS: the
+    /// connection is no longer protected against someone impersonating the server, use only on a
+    /// trusted home network
+    #[arg(long, default_value_t = false)]
+    pub danger_accept_invalid_certs: bool,
+
+    /// Requested size of the photo as fetched from Synology Photos or Immich. Can reduce network
+    /// and CPU utilization at the cost of image quality. Note: photos are still scaled to
+    /// full-screen size
+    ///
+    /// On Immich, S and M fetch the thumbnail and preview generated by the server, and L fetches
+    /// the original JPEG or PNG file (which can be large, consider M on low-memory devices).
+    /// Originals in other formats are fetched as the preview
     #[arg(long, value_enum, default_value_t = SourceSize::L)]
     pub source_size: SourceSize,
 
-    /// Disable checking for updates during startup
+    /// Log a warning when the album contains more photos than this, as keeping metadata of many
+    /// photos can put memory-constrained devices under pressure
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_LARGE_ALBUM_WARN)]
+    pub large_album_warn: usize,
+
+    /// Limit the amount of photo data downloaded per hour, e.g. on a metered connection. When the
+    /// limit is reached, the current photo stays on screen until the hour ends
+    #[arg(
+        long,
+        value_name = "MB",
+        value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_bandwidth_mb_per_hour: Option<u64>,
+
+    /// Delay in milliseconds before fetching the next photo when a photo has been removed from the
+    /// album. Protects the server from a flood of requests when many photos are removed
+    #[arg(
+        long = "skip-sleep-ms",
+        value_name = "MS",
+        default_value = "1000",
+        value_parser = try_parse_millis)]
+    pub skip_sleep: Duration,
+
+    /// Directory caching photos fitted to the screen between runs, so that they are not fetched
+    /// again on every pass through the album. Cached photos are removed when the screen size,
+    /// rotation or options changing how photos are fitted to the screen change
+    #[arg(long, value_name = "PATH", value_parser = try_parse_path)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Size limit of --cache-dir in megabytes. Least recently displayed photos are removed when
+    /// the cache grows over the limit
+    #[arg(
+        long,
+        value_name = "MB",
+        default_value_t = 1024,
+        requires = "cache_dir",
+        value_parser = clap::value_parser!(u64).range(1..))]
+    pub cache_max_mb: u64,
+
+    /// Number of times a photo request is retried when the server responds with an error or
+    /// cannot be reached, waiting 1s, 2s, 4s and so on before the retries. Requests for photos
+    /// removed from the album and login failures are not retried
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_RETRIES)]
+    pub max_retries: u32,
+
+    /// Like --max-retries, but for the request listing album photos. The response can be large
+    /// for big albums, making it more prone to time out
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_RETRIES)]
+    pub list_retries: u32,
+
+    /// Path to a file caching album metadata between runs, which speeds up startup with large
+    /// albums. The cache is not used when the share link changes
+    #[arg(long, value_name = "PATH", value_parser = try_parse_path)]
+    pub metadata_cache: Option<PathBuf>,
+
+    /// Time in seconds after which metadata in --metadata-cache is fetched from the server again
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value = "86400",
+        value_parser = try_parse_duration)]
+    pub metadata_ttl: Duration,
+
+    /// Number of photos fetched from the server at the same time. Values above 1 help on
+    /// high-latency connections, photos are still displayed in the selected order
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub fetch_concurrency: u8,
+
+    /// Disable checking for updates
     #[arg(long, default_value_t = false)]
     pub disable_update_check: bool,
+
+    /// Check for updates again every HOURS after startup, so that frames running for a long time
+    /// notice new versions
+    #[arg(
+        long,
+        value_name = "HOURS",
+        default_value_t = 24,
+        value_parser = clap::value_parser!(u64).range(1..))]
+    pub update_check_interval_hours: u64,
+
+    /// Listen for HTTP requests controlling the slideshow on PORT, e.g. `POST /next` to display
+    /// the next photo. Requires the app to be built with the `remote` feature
+    #[arg(long, value_name = "PORT")]
+    pub control_port: Option<u16>,
+
+    /// Address the --control-port listens on. Only local requests are accepted by default. Use
+    /// e.g. 0.0.0.0 to accept requests from the network, but note that they are not
+    /// authenticated, so use only on a trusted network
+    #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+    pub control_bind: IpAddr,
+
+    /// Connect to an MQTT broker at HOST[:PORT] (1883 by default) to publish the state of the
+    /// slideshow and receive commands, e.g. from Home Assistant. Requires the app to be built with
+    /// the `mqtt` feature
+    #[arg(long, value_name = "HOST[:PORT]", value_parser = try_parse_mqtt_broker)]
+    pub mqtt_broker: Option<(String, u16)>,
+
+    /// Prefix of MQTT topics of the --mqtt-broker
+    #[arg(long, value_name = "TOPIC", default_value = "syno-photo-frame")]
+    pub mqtt_topic: String,
+
+    /// Pause the slideshow while the display is off (e.g. TV in standby via HDMI-CEC, or monitor
+    /// turned off by DPMS), saving network bandwidth and CPU. Linux only
+    #[arg(long, default_value_t = false)]
+    pub pause_when_display_off: bool,
+
+    /// Every N photos, display a QR code linking to the shared album for one interval, so viewers
+    /// can open the album on their phones
+    #[arg(
+        long,
+        value_name = "N",
+        requires = "share_link",
+        value_parser = clap::value_parser!(u32).range(1..))]
+    pub show_album_qr: Option<u32>,
+
+    /// Write the displayed image to a JPEG or PNG file at PATH (format given by the extension),
+    /// e.g. for a "now showing" web page. The file is replaced each time the displayed photo
+    /// changes, so at most once per --interval
+    #[arg(long, value_name = "PATH", value_parser = try_parse_export_path)]
+    pub export_current: Option<PathBuf>,
+
+    /// Shell command to run each time a new photo is displayed
+    ///
+    /// The command runs in the background with PHOTO_ID, PHOTO_DATE, PHOTO_LOCATION and
+    /// PHOTO_ANNIVERSARY (e.g. "5 years ago today") environment variables set (all but the first
+    /// only when known). It is skipped while the previous
+    /// one is still running. WARNING: the command runs with the same privileges as the app, do not
+    /// pass commands from untrusted sources
+    #[arg(long)]
+    pub on_photo: Option<String>,
+
+    /// Template of a caption describing the displayed photo, passed to the --on-photo command as
+    /// PHOTO_INFO, e.g. "{date} {time} — {location}"
+    ///
+    /// Supported tokens are {date}, {time}, {location}, {filename}, {camera} and {caption}. Text
+    /// next to a token without a value is left out. PHOTO_INFO is not set when no token has a value
+    #[arg(long, value_name = "TEMPLATE")]
+    pub info_format: Option<String>,
+
+    /// Display only photos whose file names match PATTERN, e.g. "*.jpg" (case insensitive). Can
+    /// be repeated, photos matching any of the patterns are displayed
+    #[arg(long, value_name = "PATTERN", value_parser = try_parse_glob)]
+    pub include_glob: Vec<GlobMatcher>,
+
+    /// Hide photos whose file names match PATTERN, e.g. "scan_*" (case insensitive). Can be
+    /// repeated. Takes precedence over --include-glob
+    #[arg(long, value_name = "PATTERN", value_parser = try_parse_glob)]
+    pub exclude_glob: Vec<GlobMatcher>,
+
+    /// Shell command selecting which album photos are displayed
+    ///
+    /// The command receives metadata of all photos on standard input as a JSON array (in the same
+    /// format as --json-metadata-dump), and prints a JSON array of ids of the photos to display,
+    /// e.g. ["1", "42"]. It runs each time the album is (re)loaded. When it fails, all photos are
+    /// displayed
+    #[arg(long, value_name = "COMMAND")]
+    pub photo_filter_script: Option<String>,
+
+    /// Write metadata of all album photos to a JSON file at PATH and exit, without starting the
+    /// slideshow
+    #[arg(long, value_name = "PATH", value_parser = try_parse_path)]
+    pub json_metadata_dump: Option<PathBuf>,
+
+    /// Pin a photo with given id on screen instead of running the slideshow
+    #[arg(long, value_name = "ID")]
+    pub pin: Option<String>,
+
+    /// Display a photo before the slideshow starts, e.g. a title slide of an exhibit. Either a
+    /// path to a local file, or an id of an album photo. Can be repeated, photos are displayed
+    /// in the given order once per run
+    #[arg(long, value_name = "ID_OR_PATH")]
+    pub intro: Vec<String>,
+
+    /// TOML file with default values of options, e.g. `interval = 60`. Keys are long option
+    /// names, and `share-link` sets the album link. Options given on the command line take
+    /// precedence. Read from ~/.config/syno-photo-frame/config.toml by default, when it exists
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Parses command line arguments like [Parser::parse], taking defaults of options not given on
+    /// the command line from the config file (see [Cli::config]). Exits on error
+    pub fn parse_with_config() -> Self {
+        let args = std::env::args_os().collect::<Vec<_>>();
+        Self::try_parse_with_config(&args, default_config_path())
+            .unwrap_or_else(|error| error.exit())
+    }
+
+    fn try_parse_with_config(
+        args: &[OsString],
+        default_config_path: Option<PathBuf>,
+    ) -> Result<Self, clap::Error> {
+        let mut command = Self::command();
+        let config_path = config_arg(args).or(default_config_path.filter(|path| path.exists()));
+        if let Some(path) = config_path {
+            let config = read_config(&path).map_err(|error| {
+                command.error(
+                    ErrorKind::Io,
+                    format!("config file {}: {error:#}", path.display()),
+                )
+            })?;
+            command = with_config_defaults(command, &config).map_err(|error| {
+                Self::command().error(
+                    ErrorKind::InvalidValue,
+                    format!("config file {}: {error}", path.display()),
+                )
+            })?;
+        }
+        Self::from_arg_matches(&command.try_get_matches_from(args)?)
+    }
+}
+
+/// $XDG_CONFIG_HOME/syno-photo-frame/config.toml, falling back to ~/.config
+fn default_config_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("syno-photo-frame").join("config.toml"))
+}
+
+/// Finds the value of --config among raw command line arguments, as it's needed before parsing
+fn config_arg(args: &[OsString]) -> Option<PathBuf> {
+    let mut args = args.iter().skip(1).map(|arg| arg.to_string_lossy());
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == "--config" {
+            return args.next().map(|path| PathBuf::from(path.as_ref()));
+        } else if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+fn read_config(path: &Path) -> Result<toml::Table> {
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Sets config values as defaults of the matching arguments, so that they're overridden by the
+/// command line, and override built-in defaults
+fn with_config_defaults(mut command: Command, config: &toml::Table) -> Result<Command> {
+    for (key, value) in config {
+        let name = key.replace('_', "-");
+        let Some(id) = command
+            .get_arguments()
+            .find(|arg| {
+                arg.get_long() == Some(&name)
+                    || (arg.is_positional() && arg.get_id() == &name.replace('-', "_"))
+            })
+            .filter(|arg| arg.get_id() != "config")
+            .map(|arg| arg.get_id().clone())
+        else {
+            bail!("unknown option {key}")
+        };
+        let values = match value {
+            toml::Value::Array(array) => array
+                .iter()
+                .map(config_value)
+                .collect::<Result<Vec<_>>>()
+                .map_err(|error| anyhow!("{key}: {error}"))?,
+            value => vec![config_value(value).map_err(|error| anyhow!("{key}: {error}"))?],
+        };
+        /* A value from the config satisfies the requirement of share-link */
+        command = command.mut_arg(id, |arg| {
+            arg.default_values(values)
+                .required_unless_present(Resettable::Reset)
+        });
+    }
+    Ok(command)
+}
+
+fn config_value(value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(string) => Ok(string.clone()),
+        toml::Value::Integer(integer) => Ok(integer.to_string()),
+        toml::Value::Float(float) => Ok(float.to_string()),
+        toml::Value::Boolean(boolean) => Ok(boolean.to_string()),
+        _ => bail!("expected a string, a number or a boolean"),
+    }
+}
+
+fn try_parse_share_link(arg: &str) -> Result<Url> {
+    expand_share_link(arg, &EnvImpl, std::io::stdin().lock())
+}
+
+/// Expands environment variables in the share link given as `arg`, or read from `stdin` when `arg`
+/// is -, and parses it
+fn expand_share_link(arg: &str, env: &impl Env, stdin: impl BufRead) -> Result<Url> {
+    let (link, source) = match arg {
+        "-" => (read_share_link(stdin)?, " in standard input"),
+        _ => (arg.to_string(), ""),
+    };
+    env::expand_vars(&link, env)
+        .and_then(|link| parse_share_link(&link))
+        .map_err(|error| anyhow!("invalid share link '{link}'{source}: {error}"))
+}
+
+/// Parses a share link, or a path of an existing local directory which is converted to a
+/// `file://` URL
+fn parse_share_link(link: &str) -> Result<Url> {
+    let url_error = match Url::parse(link) {
+        Ok(url) => return Ok(url),
+        Err(error) => error,
+    };
+    match Path::new(link).canonicalize() {
+        Ok(dir) if dir.is_dir() => {
+            Url::from_directory_path(&dir).map_err(|_| anyhow!("invalid directory '{link}'"))
+        }
+        _ => Err(url_error.into()),
+    }
+}
+
+fn try_parse_path(arg: &str) -> Result<PathBuf> {
+    expand_path(arg, &EnvImpl)
+}
+
+fn expand_path(arg: &str, env: &impl Env) -> Result<PathBuf> {
+    Ok(PathBuf::from(env::expand_vars(arg, env)?))
+}
+
+fn try_parse_export_path(arg: &str) -> Result<PathBuf> {
+    let path = try_parse_path(arg)?;
+    match ImageFormat::from_path(&path) {
+        Ok(ImageFormat::Jpeg | ImageFormat::Png) => Ok(path),
+        _ => bail!("file extension must be .jpg, .jpeg or .png"),
+    }
+}
+
+fn read_share_link(input: impl BufRead) -> Result<String> {
+    let mut links = vec![];
+    for line in input.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            links.push(line.trim().to_string());
+        }
+    }
+    match links.as_slice() {
+        [] => bail!("no share link in standard input"),
+        [link] => Ok(link.clone()),
+        _ => bail!(
+            "standard input contains {} share links, but only one album can be displayed",
+            links.len()
+        ),
+    }
 }
 
 fn try_parse_duration(arg: &str) -> Result<Duration> {
@@ -99,6 +689,147 @@ fn try_parse_duration(arg: &str) -> Result<Duration> {
     }
 }
 
+fn try_parse_fade_duration(arg: &str) -> Result<Duration> {
+    let seconds: f64 = arg.parse()?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        bail!("must not be less than 0")
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+fn try_parse_random_transition(arg: &str) -> Result<Transition> {
+    match Transition::from_str(arg, false) {
+        Ok(Transition::Random) => bail!("random cannot be picked from"),
+        Ok(transition) => Ok(transition),
+        Err(error) => bail!(error),
+    }
+}
+
+fn try_parse_transition_duration(arg: &str) -> Result<Duration> {
+    let seconds: f64 = arg.parse()?;
+    if !seconds.is_finite() || seconds <= 0.0 {
+        bail!("must be greater than 0")
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+fn try_parse_millis(arg: &str) -> Result<Duration> {
+    Ok(Duration::from_millis(arg.parse()?))
+}
+
+/// File names like "IMG_0001.JPG" are matched regardless of case
+pub(crate) fn try_parse_glob(arg: &str) -> Result<GlobMatcher> {
+    Ok(GlobBuilder::new(arg)
+        .case_insensitive(true)
+        .build()?
+        .compile_matcher())
+}
+
+fn try_parse_mqtt_broker(arg: &str) -> Result<(String, u16)> {
+    const DEFAULT_PORT: u16 = 1883;
+    let (host, port) = match arg.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse()?),
+        None => (arg, DEFAULT_PORT),
+    };
+    if host.is_empty() {
+        bail!("host must not be empty")
+    }
+    Ok((host.to_string(), port))
+}
+
+fn try_parse_ratio(arg: &str) -> Result<f64> {
+    let ratio: f64 = arg.parse()?;
+    if !(0.0..=1.0).contains(&ratio) {
+        bail!("must be between 0 and 1")
+    }
+    Ok(ratio)
+}
+
+fn try_parse_gamma(arg: &str) -> Result<f64> {
+    let gamma: f64 = arg.parse()?;
+    if !(gamma.is_finite() && gamma > 0.0) {
+        bail!("must be greater than 0")
+    }
+    Ok(gamma)
+}
+
+fn try_parse_render_scale(arg: &str) -> Result<f64> {
+    let scale: f64 = arg.parse()?;
+    if !(scale > 0.0 && scale <= 1.0) {
+        bail!("must be greater than 0 and not greater than 1")
+    }
+    Ok(scale)
+}
+
+fn try_parse_resolution(arg: &str) -> Result<(u32, u32)> {
+    let Some((w, h)) = arg.split_once('x') else {
+        bail!("expected WIDTHxHEIGHT")
+    };
+    let (w, h) = (w.trim().parse()?, h.trim().parse()?);
+    if w == 0 || h == 0 {
+        bail!("width and height must be greater than 0")
+    }
+    Ok((w, h))
+}
+
+fn try_parse_aspect(arg: &str) -> Result<(u32, u32)> {
+    let Some((w, h)) = arg.split_once(':') else {
+        bail!("expected WIDTH:HEIGHT")
+    };
+    let (w, h) = (w.trim().parse()?, h.trim().parse()?);
+    if w == 0 || h == 0 {
+        bail!("width and height must be greater than 0")
+    }
+    Ok((w, h))
+}
+
+fn try_parse_background(arg: &str) -> Result<Background> {
+    match arg {
+        "blur" => Ok(Background::Blur),
+        "none" => Ok(Background::None),
+        _ => {
+            /* The # is optional, as it starts a comment in shell scripts unless quoted */
+            let hex = arg.strip_prefix('#').unwrap_or(arg);
+            if hex.len() != 6 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                bail!("expected blur, none, or a color as #RRGGBB")
+            }
+            let channel = |index| u8::from_str_radix(&hex[index..index + 2], 16);
+            Ok(Background::Color(Rgb([
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+            ])))
+        }
+    }
+}
+
+fn try_parse_active_hours(arg: &str) -> Result<ActiveHours> {
+    let Some((start, end)) = arg.split_once('-') else {
+        bail!("expected start and end times, e.g. 07:00-22:00")
+    };
+    let start = NaiveTime::parse_from_str(start, "%H:%M")?;
+    let end = NaiveTime::parse_from_str(end, "%H:%M")?;
+    if start == end {
+        bail!("start and end times must differ")
+    }
+    Ok(ActiveHours { start, end })
+}
+
+fn try_parse_dns_override(arg: &str) -> Result<(String, IpAddr)> {
+    let Some((host, ip)) = arg.split_once(':') else {
+        bail!("expected HOST:IP")
+    };
+    if host.is_empty() || host.contains(|c: char| c.is_whitespace() || c == '/') {
+        bail!("invalid host name '{host}'")
+    }
+    /* IPv6 address may be enclosed in brackets, like in URLs */
+    let ip = ip
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(ip);
+    Ok((host.to_lowercase(), ip.parse()?))
+}
+
 /// Backend service hosting a shared photo album
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum Backend {
@@ -108,6 +839,31 @@ pub enum Backend {
     Synology,
     /// Immich
     Immich,
+    /// local photos listed in a --manifest file
+    Manifest,
+    /// HTTP directory listing (e.g. nginx autoindex or a WebDAV share) at the share link
+    #[value(name = "webdav")]
+    WebDav,
+    /// photos in a local directory given instead of the share link
+    Local,
+}
+
+/// Daily time window, see [Cli::active_hours]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveHours {
+    start: NaiveTime,
+    /// Exclusive, earlier than `start` when the window spans midnight
+    end: NaiveTime,
+}
+
+impl ActiveHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
 }
 
 /// Slideshow ordering
@@ -119,6 +875,40 @@ pub enum Order {
     ByName,
     /// randomly
     Random,
+    /// by photo shooting date within a day, with days in random order
+    RandomDays,
+}
+
+/// Source of dates of local photos
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LocalDateSource {
+    /// time the photo was taken stored in its EXIF data, or the file's modification time when
+    /// missing
+    Exif,
+    /// file's modification time, which may be misleading for copied files
+    Mtime,
+}
+
+/// Handling of photos without a known date
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum UndatedPhotos {
+    /// report the date as received from the server
+    AsIs,
+    /// do not report any date, e.g. to the --on-photo command
+    HideDate,
+    /// display undated photos after all dated ones when ordering by date
+    SortLast,
+}
+
+/// Behavior when a photo fails to load
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum OnError {
+    /// display an error screen in place of the photo
+    ErrorScreen,
+    /// keep displaying the previous photo and load the next one right away
+    Hold,
+    /// keep displaying the previous photo until it's time for the next one
+    Skip,
 }
 
 /// Transition to next photo effect
@@ -128,18 +918,87 @@ pub enum Transition {
     Crossfade,
     /// Fade out to black and in to next photo
     FadeToBlack,
+    /// Zoom into the photo while blurring it, with next photo zooming out into focus
+    ZoomBlur,
+    /// Fade into a blurred version of the photo, then from it into next photo. Smooths changes
+    /// between photos of very different colors
+    BlurBridge,
+    /// Push the photo off the screen with next photo sliding in, see --slide-direction
+    Slide,
     /// Disable transition effect
     None,
+    /// Effect picked randomly for every photo from --random-transitions
+    Random,
 }
 
-/// Background fill effect
+/// Time format of the clock overlay
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum ClockFormat {
+    /// 24-hour clock, e.g. 19:05
+    #[value(name = "24h")]
+    H24,
+    /// 12-hour clock without AM/PM, e.g. 7:05
+    #[value(name = "12h")]
+    H12,
+}
+
+/// Direction of the slide transition
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum SlideDirection {
+    /// Next photo slides in from the right
+    Left,
+    /// Next photo slides in from the left
+    Right,
+    /// Left and right in turns
+    Alternate,
+}
+
+/// Background fill effect
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Background {
     /// Blur the photo
     Blur,
     /// Disable background (black)
     None,
+    /// Solid color
+    Color(Rgb<u8>),
+}
+
+/// Fitting of photos to the screen
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Fit {
+    /// show the whole photo, filling the space around it with the --background
+    Contain,
+    /// fill the screen, cropping the parts of the photo that do not fit
+    Cover,
+    /// fill the screen, ignoring the aspect ratio of the photo
+    Stretch,
+}
+
+/// IP version of server connections
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum IpVersion {
+    /// use any address the server name resolves to
+    Auto,
+    /// IPv4 only
+    V4,
+    /// IPv6 only
+    V6,
 }
+
+/// Screen corner
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Corner {
+    /// top-left corner
+    TopLeft,
+    /// top-right corner
+    TopRight,
+    /// bottom-right corner
+    BottomRight,
+    /// bottom-left corner
+    BottomLeft,
+}
+
 const ROTATIONS: [&str; 4] = ["0", "90", "180", "270"];
 
 /// Screen rotation in degrees
@@ -180,6 +1039,358 @@ pub enum SourceSize {
 
 #[test]
 fn verify_cli() {
-    use clap::CommandFactory;
     Cli::command().debug_assert()
 }
+
+#[test]
+fn try_parse_resolution_accepts_only_positive_width_and_height() {
+    assert_eq!(try_parse_resolution("1920x1080").unwrap(), (1920, 1080));
+    assert!(try_parse_resolution("0x1080").is_err());
+    assert!(try_parse_resolution("1920x0").is_err());
+    assert!(try_parse_resolution("-1920x1080").is_err());
+    assert!(try_parse_resolution("1920").is_err());
+}
+
+#[test]
+fn try_parse_aspect_accepts_only_positive_width_and_height() {
+    assert_eq!(try_parse_aspect("4:3").unwrap(), (4, 3));
+    assert!(try_parse_aspect("0:3").is_err());
+    assert!(try_parse_aspect("4:-3").is_err());
+    assert!(try_parse_aspect("4x3").is_err());
+}
+
+#[test]
+fn try_parse_random_transition_accepts_effects_except_random() {
+    assert_eq!(
+        try_parse_random_transition("fade-to-black").unwrap(),
+        Transition::FadeToBlack
+    );
+    assert_eq!(
+        try_parse_random_transition("none").unwrap(),
+        Transition::None
+    );
+    assert!(try_parse_random_transition("random").is_err());
+    assert!(try_parse_random_transition("push").is_err());
+    let cli = Cli::parse_from([
+        "syno-photo-frame",
+        "http://fake.dsm.addr/aa/sharing/FakeSharingId",
+    ]);
+    assert_eq!(
+        cli.random_transitions,
+        [
+            Transition::Crossfade,
+            Transition::FadeToBlack,
+            Transition::ZoomBlur,
+            Transition::BlurBridge,
+            Transition::Slide
+        ]
+    );
+}
+
+#[test]
+fn try_parse_transition_duration_accepts_only_positive_values() {
+    assert_eq!(
+        try_parse_transition_duration("2.5").unwrap(),
+        Duration::from_millis(2500)
+    );
+    assert!(try_parse_transition_duration("0").is_err());
+    assert!(try_parse_transition_duration("-1").is_err());
+    assert!(try_parse_transition_duration("inf").is_err());
+}
+
+#[test]
+fn try_parse_background_accepts_effects_and_colors() {
+    assert_eq!(try_parse_background("blur").unwrap(), Background::Blur);
+    assert_eq!(try_parse_background("none").unwrap(), Background::None);
+    assert_eq!(
+        try_parse_background("#80aF00").unwrap(),
+        Background::Color(Rgb([0x80, 0xaf, 0x00]))
+    );
+    assert_eq!(
+        try_parse_background("ffffff").unwrap(),
+        Background::Color(Rgb([255, 255, 255]))
+    );
+    assert!(try_parse_background("#fff").is_err());
+    assert!(try_parse_background("#+fffff").is_err());
+    assert!(try_parse_background("gray").is_err());
+}
+
+#[test]
+fn try_parse_active_hours_accepts_start_and_end_times() {
+    let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+    assert_eq!(
+        try_parse_active_hours("07:00-22:30").unwrap(),
+        ActiveHours {
+            start: time(7, 0),
+            end: time(22, 30)
+        }
+    );
+    assert!(try_parse_active_hours("07:00").is_err());
+    assert!(try_parse_active_hours("07:00-24:00").is_err());
+    assert!(try_parse_active_hours("7am-10pm").is_err());
+    assert!(try_parse_active_hours("07:00-07:00").is_err());
+}
+
+#[test]
+fn active_hours_contain_times_from_start_until_end() {
+    let time = |hour, min| NaiveTime::from_hms_opt(hour, min, 0).unwrap();
+    let daytime = try_parse_active_hours("07:00-22:00").unwrap();
+    assert!(!daytime.contains(time(6, 59)));
+    assert!(daytime.contains(time(7, 0)));
+    assert!(daytime.contains(time(21, 59)));
+    assert!(!daytime.contains(time(22, 0)));
+    let overnight = try_parse_active_hours("22:00-06:00").unwrap();
+    assert!(overnight.contains(time(23, 0)));
+    assert!(overnight.contains(time(1, 0)));
+    assert!(!overnight.contains(time(6, 0)));
+    assert!(!overnight.contains(time(12, 0)));
+}
+
+#[test]
+fn try_parse_dns_override_accepts_host_and_ip_address() {
+    assert_eq!(
+        try_parse_dns_override("NAS.local:192.168.1.10").unwrap(),
+        ("nas.local".to_string(), "192.168.1.10".parse().unwrap())
+    );
+    assert_eq!(
+        try_parse_dns_override("nas.local:fd00::10").unwrap(),
+        ("nas.local".to_string(), "fd00::10".parse().unwrap())
+    );
+    assert_eq!(
+        try_parse_dns_override("nas.local:[fd00::10]").unwrap(),
+        ("nas.local".to_string(), "fd00::10".parse().unwrap())
+    );
+    assert!(try_parse_dns_override("nas.local").is_err());
+    assert!(try_parse_dns_override(":192.168.1.10").is_err());
+    assert!(try_parse_dns_override("nas.local:192.168.1").is_err());
+    assert!(try_parse_dns_override("nas.local:").is_err());
+}
+
+#[test]
+fn try_parse_glob_accepts_valid_patterns() {
+    assert!(try_parse_glob("*.jpg").unwrap().is_match("IMG_0001.JPG"));
+    assert!(!try_parse_glob("scan_*").unwrap().is_match("IMG_0001.JPG"));
+    assert!(try_parse_glob("[a-").is_err());
+}
+
+#[test]
+fn try_parse_mqtt_broker_accepts_host_with_optional_port() {
+    assert_eq!(
+        try_parse_mqtt_broker("broker.local").unwrap(),
+        ("broker.local".to_string(), 1883)
+    );
+    assert_eq!(
+        try_parse_mqtt_broker("192.168.1.2:8883").unwrap(),
+        ("192.168.1.2".to_string(), 8883)
+    );
+    assert!(try_parse_mqtt_broker("broker.local:port").is_err());
+    assert!(try_parse_mqtt_broker(":1883").is_err());
+}
+
+#[test]
+fn try_parse_ratio_accepts_only_values_between_0_and_1() {
+    assert_eq!(try_parse_ratio("0.25").unwrap(), 0.25);
+    assert_eq!(try_parse_ratio("0").unwrap(), 0.0);
+    assert_eq!(try_parse_ratio("1").unwrap(), 1.0);
+    assert!(try_parse_ratio("-0.1").is_err());
+    assert!(try_parse_ratio("1.5").is_err());
+    assert!(try_parse_ratio("NaN").is_err());
+}
+
+#[test]
+fn try_parse_gamma_accepts_only_positive_values() {
+    assert_eq!(try_parse_gamma("1.2").unwrap(), 1.2);
+    assert!(try_parse_gamma("0").is_err());
+    assert!(try_parse_gamma("-1").is_err());
+    assert!(try_parse_gamma("inf").is_err());
+}
+
+#[test]
+fn try_parse_render_scale_accepts_only_values_above_0_up_to_1() {
+    assert_eq!(try_parse_render_scale("0.5").unwrap(), 0.5);
+    assert_eq!(try_parse_render_scale("1").unwrap(), 1.0);
+    assert!(try_parse_render_scale("0").is_err());
+    assert!(try_parse_render_scale("1.5").is_err());
+    assert!(try_parse_render_scale("NaN").is_err());
+}
+
+#[test]
+fn try_parse_export_path_accepts_only_jpeg_and_png_files() {
+    assert_eq!(
+        try_parse_export_path("/tmp/current.jpg").unwrap(),
+        PathBuf::from("/tmp/current.jpg")
+    );
+    assert!(try_parse_export_path("/tmp/current.PNG").is_ok());
+    assert!(try_parse_export_path("/tmp/current.gif").is_err());
+    assert!(try_parse_export_path("/tmp/current").is_err());
+}
+
+#[test]
+fn parse_share_link_accepts_links_and_local_directories() {
+    assert_eq!(
+        parse_share_link("http://fake.dsm.addr/aa/sharing/FakeSharingId")
+            .unwrap()
+            .as_str(),
+        "http://fake.dsm.addr/aa/sharing/FakeSharingId"
+    );
+    let assets_dir = Path::new("assets").canonicalize().unwrap();
+    assert_eq!(
+        parse_share_link("assets").unwrap(),
+        Url::from_directory_path(assets_dir).unwrap()
+    );
+    assert!(parse_share_link("assets/test_loading.jpeg").is_err());
+    assert!(parse_share_link("no/such/dir").is_err());
+}
+
+#[test]
+fn read_share_link_accepts_single_link() {
+    assert_eq!(
+        read_share_link("\n  http://fake.dsm.addr/aa/sharing/FakeSharingId  \n".as_bytes())
+            .unwrap(),
+        "http://fake.dsm.addr/aa/sharing/FakeSharingId"
+    );
+    assert!(read_share_link("".as_bytes()).is_err());
+    assert!(read_share_link("http://a.addr/share/A\nhttp://b.addr/share/B\n".as_bytes()).is_err());
+}
+
+#[test]
+fn expand_share_link_expands_variables_in_argument_and_standard_input() {
+    let mut env_stub = MockEnv::new();
+    env_stub
+        .expect_var()
+        .with(eq("NAS"))
+        .return_const(Some("nas.local:5001".to_string()));
+    env_stub
+        .expect_var()
+        .with(eq("SHARING_ID"))
+        .return_const(Some("FakeSharingId".to_string()));
+
+    let from_argument = expand_share_link(
+        "https://${NAS}/mo/sharing/${SHARING_ID}",
+        &env_stub,
+        std::io::empty(),
+    );
+    let from_stdin = expand_share_link(
+        "-",
+        &env_stub,
+        "https://${NAS}/mo/sharing/${SHARING_ID}\n".as_bytes(),
+    );
+
+    assert_eq!(
+        from_argument.unwrap().as_str(),
+        "https://nas.local:5001/mo/sharing/FakeSharingId"
+    );
+    assert_eq!(
+        from_stdin.unwrap().as_str(),
+        "https://nas.local:5001/mo/sharing/FakeSharingId"
+    );
+}
+
+#[test]
+fn when_share_link_is_invalid_or_variable_is_not_set_then_expand_share_link_fails() {
+    let mut env_stub = MockEnv::new();
+    env_stub.expect_var().return_const(None);
+
+    assert!(expand_share_link(
+        "https://${NAS}/mo/sharing/FakeSharingId",
+        &env_stub,
+        std::io::empty()
+    )
+    .is_err());
+    assert!(expand_share_link(
+        "-",
+        &env_stub,
+        "https://${NAS}/mo/sharing/FakeSharingId\n".as_bytes()
+    )
+    .is_err());
+    assert!(expand_share_link("-", &env_stub, "not a link\n".as_bytes()).is_err());
+}
+
+#[test]
+fn expand_path_expands_variables() {
+    let mut env_stub = MockEnv::new();
+    env_stub
+        .expect_var()
+        .with(eq("CACHE"))
+        .return_const(Some("/var/cache".to_string()));
+
+    let result = expand_path("${CACHE}/syno-photo-frame", &env_stub);
+
+    assert_eq!(
+        result.unwrap(),
+        PathBuf::from("/var/cache/syno-photo-frame")
+    );
+}
+
+#[test]
+fn when_config_file_is_given_then_command_line_takes_precedence_over_file_over_defaults() {
+    let config_path = std::env::temp_dir().join(format!(
+        "syno-photo-frame-config-{}.toml",
+        std::process::id()
+    ));
+    fs::write(
+        &config_path,
+        "share-link = \"http://fake.dsm.addr/aa/sharing/FileSharingId\"\n\
+         interval = 60\n\
+         order = \"random\"\n\
+         random_start = true\n\
+         intro = [\"1\", \"2\"]\n",
+    )
+    .unwrap();
+    let parse = |args: &str| {
+        let args = args.split(' ').map(OsString::from).collect::<Vec<_>>();
+        Cli::try_parse_with_config(&args, Some(config_path.clone()))
+    };
+
+    let from_file = parse("syno-photo-frame").unwrap();
+    let from_command_line = parse(
+        "syno-photo-frame http://fake.dsm.addr/aa/sharing/FakeSharingId --interval 90 --intro 3",
+    )
+    .unwrap();
+
+    fs::remove_file(&config_path).unwrap();
+    assert_eq!(
+        from_file.share_link.unwrap().as_str(),
+        "http://fake.dsm.addr/aa/sharing/FileSharingId"
+    );
+    assert_eq!(from_file.photo_change_interval, Duration::from_secs(60));
+    assert_eq!(from_file.order, Order::Random);
+    assert!(from_file.random_start);
+    assert_eq!(from_file.intro, ["1", "2"]);
+    assert_eq!(from_file.transition, Transition::Crossfade);
+    assert_eq!(
+        from_command_line.share_link.unwrap().as_str(),
+        "http://fake.dsm.addr/aa/sharing/FakeSharingId"
+    );
+    assert_eq!(
+        from_command_line.photo_change_interval,
+        Duration::from_secs(90)
+    );
+    assert_eq!(from_command_line.order, Order::Random);
+    assert_eq!(from_command_line.intro, ["3"]);
+}
+
+#[test]
+fn when_config_file_has_unknown_option_then_parsing_fails() {
+    let config = toml::from_str("no-such-option = 1").unwrap();
+    assert!(with_config_defaults(Cli::command(), &config).is_err());
+    let config = toml::from_str("config = \"other.toml\"").unwrap();
+    assert!(with_config_defaults(Cli::command(), &config).is_err());
+    let config = toml::from_str("interval = { seconds = 60 }").unwrap();
+    assert!(with_config_defaults(Cli::command(), &config).is_err());
+}
+
+#[test]
+fn config_arg_finds_path_given_on_command_line() {
+    let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+    assert_eq!(
+        config_arg(&args(&["app", "--config", "a.toml"])),
+        Some(PathBuf::from("a.toml"))
+    );
+    assert_eq!(
+        config_arg(&args(&["app", "-i", "60", "--config=b.toml"])),
+        Some(PathBuf::from("b.toml"))
+    );
+    assert_eq!(config_arg(&args(&["app", "--", "--config"])), None);
+    assert_eq!(config_arg(&args(&["--config"])), None);
+}
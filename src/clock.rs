@@ -0,0 +1,243 @@
+//! Clock overlay, see `--show-clock`
+//!
+//! The app has no font rendering, so digits are drawn as seven-segment displays out of
+//! rectangles.
+
+use anyhow::Result;
+use chrono::{NaiveTime, Timelike};
+
+use crate::{
+    cli::{ClockFormat, Corner, Rotation},
+    render::{Color, Rect, Renderer},
+};
+
+const DIGIT_COLOR: Color = Color::rgba(255, 255, 255, 220);
+/// Darkens the photo behind the clock, so that it stays readable on bright photos
+const BACKDROP_COLOR: Color = Color::rgba(0, 0, 0, 96);
+/// Height of digits as a fraction of the shorter side of the screen
+const DIGIT_HEIGHT_DIVISOR: u32 = 10;
+
+/// Segments lit for each digit, in the order: top, upper right, lower right, bottom, lower left,
+/// upper left, middle
+const DIGIT_SEGMENTS: [[bool; 7]; 10] = [
+    [true, true, true, true, true, true, false],
+    [false, true, true, false, false, false, false],
+    [true, true, false, true, true, false, true],
+    [true, true, true, true, false, false, true],
+    [false, true, true, false, false, true, true],
+    [true, false, true, true, false, true, true],
+    [true, false, true, true, true, true, true],
+    [true, true, true, false, false, false, false],
+    [true, true, true, true, true, true, true],
+    [true, true, true, true, false, true, true],
+];
+
+/// Current time drawn over the displayed photo
+pub struct ClockOverlay {
+    corner: Corner,
+    format: ClockFormat,
+    rotation: Rotation,
+    /// Text on screen, `None` when the clock needs to be drawn again
+    drawn_text: Option<String>,
+}
+
+impl ClockOverlay {
+    pub fn new(corner: Corner, format: ClockFormat, rotation: Rotation) -> Self {
+        ClockOverlay {
+            corner,
+            format,
+            rotation,
+            drawn_text: None,
+        }
+    }
+
+    /// Whether the clock needs to be redrawn over the current photo with
+    /// [ClockOverlay::draw], i.e. the displayed time has changed, or the photo has been redrawn
+    /// without the clock
+    pub fn needs_redraw(&self, time: NaiveTime) -> bool {
+        self.drawn_text.as_deref() != Some(self.text(time).as_str())
+    }
+
+    /// Called when the canvas has been drawn over, e.g. by a transition
+    pub fn invalidate(&mut self) {
+        self.drawn_text = None;
+    }
+
+    /// Draws the clock over the canvas
+    pub fn draw(&mut self, renderer: &mut impl Renderer, time: NaiveTime) -> Result<()> {
+        let text = self.text(time);
+        for (rect, color) in clock_rects(&text, renderer.size(), self.corner, self.rotation) {
+            renderer.fill_canvas_rect(rect, color)?;
+        }
+        self.drawn_text = Some(text);
+        Ok(())
+    }
+
+    fn text(&self, time: NaiveTime) -> String {
+        match self.format {
+            ClockFormat::H24 => time.format("%H:%M").to_string(),
+            /* Without AM/PM, which could not be drawn with seven segments anyway */
+            ClockFormat::H12 => format!("{}:{:02}", time.hour12().1, time.minute()),
+        }
+    }
+}
+
+/// Returns rectangles of the canvas to fill to draw `text` (digits and colons) in a corner of the
+/// (rotated) screen, starting with the backdrop
+fn clock_rects(
+    text: &str,
+    screen_size: (u32, u32),
+    corner: Corner,
+    rotation: Rotation,
+) -> Vec<(Rect, Color)> {
+    /* Laid out on the upright screen, then mapped to the canvas */
+    let upright_size = match rotation {
+        Rotation::D0 | Rotation::D180 => screen_size,
+        Rotation::D90 | Rotation::D270 => (screen_size.1, screen_size.0),
+    };
+    let digit_h = (upright_size.0.min(upright_size.1) / DIGIT_HEIGHT_DIVISOR).max(5);
+    let digit_w = digit_h / 2;
+    let thickness = (digit_h / 8).max(1);
+    let gap = thickness * 2;
+    let char_w = |c: char| if c == ':' { thickness } else { digit_w };
+    let text_w = text.chars().map(|c| char_w(c) + gap).sum::<u32>() - gap;
+    let padding = thickness * 2;
+    let (box_w, box_h) = (text_w + padding * 2, digit_h + padding * 2);
+    /* Small margin separating the clock from the screen edges */
+    let margin = upright_size.0.min(upright_size.1) / 50;
+    let right = upright_size.0.saturating_sub(box_w + margin);
+    let bottom = upright_size.1.saturating_sub(box_h + margin);
+    let (box_x, box_y) = match corner {
+        Corner::TopLeft => (margin, margin),
+        Corner::TopRight => (right, margin),
+        Corner::BottomRight => (right, bottom),
+        Corner::BottomLeft => (margin, bottom),
+    };
+
+    let mut rects = vec![(Rect::new(0, 0, box_w, box_h), BACKDROP_COLOR)];
+    let mut x = padding;
+    for c in text.chars() {
+        match c.to_digit(10) {
+            Some(digit) => {
+                let half_h = digit_h / 2;
+                let segments = [
+                    (0, 0, digit_w, thickness),
+                    (digit_w - thickness, 0, thickness, half_h),
+                    (digit_w - thickness, half_h, thickness, digit_h - half_h),
+                    (0, digit_h - thickness, digit_w, thickness),
+                    (0, half_h, thickness, digit_h - half_h),
+                    (0, 0, thickness, half_h),
+                    (0, half_h - thickness / 2, digit_w, thickness),
+                ];
+                rects.extend(
+                    segments
+                        .into_iter()
+                        .zip(DIGIT_SEGMENTS[digit as usize])
+                        .filter(|(_, is_lit)| *is_lit)
+                        .map(|((sx, sy, w, h), _)| {
+                            (
+                                Rect::new((x + sx) as i32, (padding + sy) as i32, w, h),
+                                DIGIT_COLOR,
+                            )
+                        }),
+                );
+            }
+            None if c == ':' => {
+                for dot_y in [digit_h / 3, digit_h * 2 / 3] {
+                    let y = padding + dot_y - thickness / 2;
+                    rects.push((
+                        Rect::new(x as i32, y as i32, thickness, thickness),
+                        DIGIT_COLOR,
+                    ));
+                }
+            }
+            None => {}
+        }
+        x += char_w(c) + gap;
+    }
+    rects
+        .into_iter()
+        .map(|(rect, color)| {
+            let rect = Rect::new(rect.x + box_x as i32, rect.y + box_y as i32, rect.w, rect.h);
+            (rect.rotate(upright_size, rotation), color)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::MockRenderer;
+
+    use super::*;
+
+    const SCREEN_SIZE: (u32, u32) = (1920, 1080);
+
+    #[test]
+    fn clock_text_follows_format() {
+        let time = NaiveTime::from_hms_opt(19, 5, 42).unwrap();
+        let midnight = NaiveTime::from_hms_opt(0, 30, 0).unwrap();
+
+        let clock_24h = ClockOverlay::new(Corner::TopRight, ClockFormat::H24, Rotation::D0);
+        let clock_12h = ClockOverlay::new(Corner::TopRight, ClockFormat::H12, Rotation::D0);
+
+        assert_eq!(clock_24h.text(time), "19:05");
+        assert_eq!(clock_24h.text(midnight), "00:30");
+        assert_eq!(clock_12h.text(time), "7:05");
+        assert_eq!(clock_12h.text(midnight), "12:30");
+    }
+
+    #[test]
+    fn clock_needs_redraw_when_minute_changes_or_when_invalidated() {
+        let mut renderer_mock = MockRenderer::new();
+        renderer_mock.expect_size().return_const(SCREEN_SIZE);
+        /* Backdrop, 2 + 5 segments of "12", 2 dots of the colon, 4 + 5 segments of "45" */
+        renderer_mock
+            .expect_fill_canvas_rect()
+            .times(19)
+            .returning(|_, _| Ok(()));
+        let mut clock = ClockOverlay::new(Corner::TopRight, ClockFormat::H24, Rotation::D0);
+        let time = NaiveTime::from_hms_opt(12, 45, 0).unwrap();
+
+        assert!(clock.needs_redraw(time));
+        clock.draw(&mut renderer_mock, time).unwrap();
+
+        assert!(!clock.needs_redraw(NaiveTime::from_hms_opt(12, 45, 59).unwrap()));
+        assert!(clock.needs_redraw(NaiveTime::from_hms_opt(12, 46, 0).unwrap()));
+        clock.invalidate();
+        assert!(clock.needs_redraw(time));
+    }
+
+    #[test]
+    fn clock_fits_in_corner_of_screen() {
+        for rotation in [Rotation::D0, Rotation::D90, Rotation::D180, Rotation::D270] {
+            for corner in [
+                Corner::TopLeft,
+                Corner::TopRight,
+                Corner::BottomRight,
+                Corner::BottomLeft,
+            ] {
+                let result = clock_rects("88:88", SCREEN_SIZE, corner, rotation);
+
+                let (backdrop, _) = result[0];
+                for (Rect { x, y, w, h }, _) in &result {
+                    assert!(*x >= backdrop.x && *y >= backdrop.y);
+                    assert!(x + *w as i32 <= backdrop.x + backdrop.w as i32);
+                    assert!(y + *h as i32 <= backdrop.y + backdrop.h as i32);
+                }
+                /* Backdrop is in the corner, rotated like photos */
+                let is_left = backdrop.x < (SCREEN_SIZE.0 / 2) as i32;
+                let is_top = backdrop.y < (SCREEN_SIZE.1 / 2) as i32;
+                let expected_corner = match (is_left, is_top) {
+                    (true, true) => Corner::TopLeft,
+                    (false, true) => Corner::TopRight,
+                    (false, false) => Corner::BottomRight,
+                    (true, false) => Corner::BottomLeft,
+                };
+                assert_eq!(corner.rotate(rotation), expected_corner);
+                assert!(backdrop.x >= 0 && backdrop.y >= 0);
+                assert!(backdrop.x as u32 + backdrop.w <= SCREEN_SIZE.0);
+                assert!(backdrop.y as u32 + backdrop.h <= SCREEN_SIZE.1);
+            }
+        }
+    }
+}
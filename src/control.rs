@@ -0,0 +1,165 @@
+//! State shared between the slideshow and control surfaces, e.g. remote control or queries about
+//! the displayed photo
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex,
+};
+
+use serde::Serialize;
+
+use crate::metadata::CurrentPhoto;
+
+/// Handles to the state shared with the slideshow, and to the channel of [Command]s handled by the
+/// slideshow loop. Clones share the same state and channel
+#[derive(Debug, Clone)]
+pub struct Control {
+    pub current_photo: Arc<Mutex<CurrentPhoto>>,
+    pub pinned_photo: Arc<Mutex<PinnedPhoto>>,
+    /// While set, the displayed photo stays on screen and no further photos are fetched (beyond
+    /// the one already prepared). Set by the slideshow loop when handling [Command::Pause] and
+    /// [Command::Resume]
+    pub paused: Arc<AtomicBool>,
+    /// Set while the display is powered off, see `--pause-when-display-off`. Pauses the slideshow
+    /// like [Control::paused], but is kept apart so that a pause set by the user outlasts the
+    /// display being turned off and on
+    pub display_off: Arc<AtomicBool>,
+    command_sender: Sender<Command>,
+    command_receiver: Arc<Mutex<Receiver<Command>>>,
+}
+
+impl Default for Control {
+    fn default() -> Self {
+        let (command_sender, command_receiver) = mpsc::channel();
+        Self {
+            current_photo: Arc::default(),
+            pinned_photo: Arc::default(),
+            paused: Arc::default(),
+            display_off: Arc::default(),
+            command_sender,
+            command_receiver: Arc::new(Mutex::new(command_receiver)),
+        }
+    }
+}
+
+impl Control {
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_display_off(&self) -> bool {
+        self.display_off.load(Ordering::Relaxed)
+    }
+
+    /// Sends `command` to the slideshow loop, which handles it before displaying the next frame
+    pub fn send(&self, command: Command) {
+        /* The receiver is kept by self, so the channel is never disconnected */
+        let _ = self.command_sender.send(command);
+    }
+
+    /// Returns commands sent since the last call, in the order they were sent
+    pub(crate) fn take_commands(&self) -> Vec<Command> {
+        match self.command_receiver.lock() {
+            Ok(command_receiver) => command_receiver.try_iter().collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// State of the slideshow, as reported to remote control surfaces
+    pub fn status(&self) -> Status {
+        let info = match self.current_photo.lock() {
+            Ok(current_photo) => current_photo.info.clone(),
+            Err(_) => None,
+        };
+        let position = info.as_ref().and_then(|info| info.position);
+        Status {
+            paused: self.is_paused(),
+            position: position.map(|(position, _)| position),
+            count: position.map(|(_, count)| count),
+            caption: info.as_ref().and_then(|info| info.caption.clone()),
+            id: info.map(|info| info.id),
+        }
+    }
+}
+
+/// Command sent to the slideshow by the keyboard or a remote control surface, e.g.
+/// `--control-port`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// Display the next photo without waiting for the interval to pass
+    Next,
+    Pause,
+    Resume,
+    /// Pause when running, resume when paused
+    TogglePause,
+    /// Fetch album metadata again before the next photo, so that photos added to the album are
+    /// displayed without waiting for the next pass
+    Reload,
+    /// End the slideshow
+    Quit,
+}
+
+impl Command {
+    /// Parses a lowercase command name, e.g. "next"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "next" => Some(Command::Next),
+            "pause" => Some(Command::Pause),
+            "resume" => Some(Command::Resume),
+            "toggle-pause" => Some(Command::TogglePause),
+            "reload" => Some(Command::Reload),
+            "quit" => Some(Command::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// State of the slideshow reported to remote control surfaces
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Status {
+    pub paused: bool,
+    /// Id of the displayed photo, `None` e.g. while the welcome screen is displayed
+    pub id: Option<String>,
+    /// Position of the displayed photo in the current pass through the album, starting at 1
+    pub position: Option<usize>,
+    /// Number of photos in the current pass
+    pub count: Option<usize>,
+    /// Caption (description) of the displayed photo, when it has one
+    pub caption: Option<String>,
+}
+
+/// Photo pinned on screen. While a photo is pinned it is displayed instead of the slideshow, which
+/// resumes where it left off once the photo is unpinned
+#[derive(Debug, Default)]
+pub struct PinnedPhoto {
+    id: Option<String>,
+    /// Incremented on every change, so that a photo fetched for an outdated pin can be recognized
+    version: u64,
+}
+
+impl PinnedPhoto {
+    /// Pins the photo with given id (as reported in [crate::metadata::PhotoInfo::id])
+    pub fn pin(&mut self, id: &str) {
+        self.id = Some(id.to_string());
+        self.version += 1;
+    }
+
+    pub fn unpin(&mut self) {
+        if self.id.take().is_some() {
+            self.version += 1;
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub(crate) fn version(&self) -> u64 {
+        self.version
+    }
+}
@@ -0,0 +1,183 @@
+//! Detection of the display being powered off, e.g. a TV put to standby over HDMI-CEC or a monitor
+//! turned off by DPMS, so that the slideshow can pause instead of fetching photos nobody sees
+
+#[cfg(target_os = "linux")]
+use std::{fs, path::PathBuf};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+    time::Duration,
+};
+
+#[cfg(target_os = "linux")]
+use anyhow::bail;
+use anyhow::Result;
+
+/// Power state of the display
+#[cfg_attr(test, mockall::automock)]
+pub trait DisplayPower {
+    /// Returns false when the display is off or disconnected
+    fn is_on(&self) -> Result<bool>;
+}
+
+/// Reads power state of display connectors exposed by the Linux DRM subsystem in sysfs
+#[cfg(target_os = "linux")]
+pub struct DrmDisplayPower {
+    drm_path: PathBuf,
+}
+
+#[cfg(target_os = "linux")]
+impl DrmDisplayPower {
+    pub fn new() -> Self {
+        Self::with_drm_path("/sys/class/drm")
+    }
+
+    fn with_drm_path(drm_path: impl Into<PathBuf>) -> Self {
+        Self {
+            drm_path: drm_path.into(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Default for DrmDisplayPower {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl DisplayPower for DrmDisplayPower {
+    fn is_on(&self) -> Result<bool> {
+        let mut connector_found = false;
+        for entry in fs::read_dir(&self.drm_path)? {
+            let connector_path = entry?.path();
+            /* Only connectors (e.g. card0-HDMI-A-1) have status, not the cards themselves */
+            let Ok(status) = fs::read_to_string(connector_path.join("status")) else {
+                continue;
+            };
+            connector_found = true;
+            if status.trim() != "connected" {
+                continue;
+            }
+            let dpms = fs::read_to_string(connector_path.join("dpms"))?;
+            if dpms.trim() == "On" {
+                return Ok(true);
+            }
+        }
+        if !connector_found {
+            bail!(
+                "No display connectors found in {}",
+                self.drm_path.to_string_lossy()
+            );
+        }
+        Ok(false)
+    }
+}
+
+/// Polls the display power state, setting `display_off` (see
+/// [crate::control::Control::display_off]) to pause the slideshow while the display is off
+pub fn watch(display_power: impl DisplayPower, display_off: &AtomicBool) -> ! {
+    const POLL_INTERVAL: Duration = Duration::from_secs(5);
+    loop {
+        update_display_off(&display_power, display_off);
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn update_display_off(display_power: &impl DisplayPower, display_off: &AtomicBool) {
+    let is_on = display_power.is_on().unwrap_or_else(|error| {
+        /* Rather keep the slideshow going than pause it for good */
+        log::debug!("Failed to read display power state: {error}");
+        true
+    });
+    let was_off = display_off.swap(!is_on, Ordering::Relaxed);
+    match (was_off, is_on) {
+        (true, true) => log::info!("Display is on, resuming slideshow"),
+        (false, false) => log::info!("Display is off, pausing slideshow"),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use mockall::Sequence;
+
+    use crate::control::Control;
+
+    use super::*;
+
+    #[test]
+    fn update_display_off_follows_display_power_state() {
+        let mut display_power_stub = MockDisplayPower::new();
+        let mut seq = Sequence::new();
+        for is_on in [true, false, false, true] {
+            display_power_stub
+                .expect_is_on()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_once(move || Ok(is_on));
+        }
+        let display_off = AtomicBool::new(false);
+
+        let mut states = vec![];
+        for _ in 0..4 {
+            update_display_off(&display_power_stub, &display_off);
+            states.push(display_off.load(Ordering::Relaxed));
+        }
+
+        assert_eq!(states, [false, true, true, false]);
+    }
+
+    #[test]
+    fn when_display_power_state_is_unknown_then_slideshow_is_not_paused() {
+        let mut display_power_stub = MockDisplayPower::new();
+        display_power_stub
+            .expect_is_on()
+            .return_once(|| Err(anyhow!("no DRM")));
+        let display_off = AtomicBool::new(true);
+
+        update_display_off(&display_power_stub, &display_off);
+
+        assert!(!display_off.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn when_display_is_on_then_pause_set_by_user_is_kept() {
+        let mut display_power_stub = MockDisplayPower::new();
+        display_power_stub.expect_is_on().returning(|| Ok(true));
+        let control = Control::default();
+        control.set_paused(true);
+
+        update_display_off(&display_power_stub, &control.display_off);
+
+        assert!(control.is_paused());
+        assert!(!control.is_display_off());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn drm_display_power_reads_dpms_state_of_connected_connectors() {
+        let drm_path =
+            std::env::temp_dir().join(format!("syno-photo-frame-drm-{}", std::process::id()));
+        let create_connector = |name: &str, status: &str, dpms: &str| {
+            let connector_path = drm_path.join(name);
+            fs::create_dir_all(&connector_path).unwrap();
+            fs::write(connector_path.join("status"), format!("{status}\n")).unwrap();
+            fs::write(connector_path.join("dpms"), format!("{dpms}\n")).unwrap();
+        };
+        fs::create_dir_all(drm_path.join("card0")).unwrap();
+        create_connector("card0-HDMI-A-1", "connected", "Off");
+        create_connector("card0-HDMI-A-2", "disconnected", "On");
+        let display_power = DrmDisplayPower::with_drm_path(&drm_path);
+
+        let off_result = display_power.is_on();
+        create_connector("card0-HDMI-A-1", "connected", "On");
+        let on_result = display_power.is_on();
+        fs::remove_dir_all(&drm_path).unwrap();
+
+        assert!(off_result.is_ok_and(|is_on| !is_on));
+        assert!(on_result.is_ok_and(|is_on| is_on));
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::OnceLock;
+
+use anyhow::{bail, Result};
+use regex::{Captures, Regex};
+
+/// Access to environment variables
+#[cfg_attr(test, mockall::automock)]
+pub trait Env {
+    /// Returns value of the variable, or `None` when it's not set (or not valid unicode)
+    fn var(&self, name: &str) -> Option<String>;
+}
+
+pub struct EnvImpl;
+
+impl Env for EnvImpl {
+    fn var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// Replaces `${VAR}` references in `text` with values of environment variables. Fails when any of
+/// the referenced variables is not set
+pub fn expand_vars(text: &str, env: &impl Env) -> Result<String> {
+    static VAR_RE: OnceLock<Regex> = OnceLock::new();
+    let var_re = VAR_RE.get_or_init(|| Regex::new(r"\$\{([[:word:]]+)\}").unwrap());
+    let mut unset = vec![];
+    let expanded = var_re.replace_all(text, |captures: &Captures| {
+        let name = &captures[1];
+        env.var(name).unwrap_or_else(|| {
+            unset.push(name.to_string());
+            String::new()
+        })
+    });
+    if !unset.is_empty() {
+        bail!("environment variable {} is not set", unset.join(", "))
+    }
+    Ok(expanded.into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::eq;
+
+    use super::*;
+
+    #[test]
+    fn expand_vars_replaces_variables_in_templated_link() {
+        let mut env_stub = MockEnv::new();
+        env_stub
+            .expect_var()
+            .with(eq("NAS"))
+            .return_const(Some("nas.local:5001".to_string()));
+        env_stub
+            .expect_var()
+            .with(eq("SHARING_ID"))
+            .return_const(Some("FakeSharingId".to_string()));
+
+        let result = expand_vars("https://${NAS}/mo/sharing/${SHARING_ID}", &env_stub);
+
+        assert_eq!(
+            result.unwrap(),
+            "https://nas.local:5001/mo/sharing/FakeSharingId"
+        );
+    }
+
+    #[test]
+    fn expand_vars_keeps_text_without_variables() {
+        let env_stub = MockEnv::new();
+
+        let result = expand_vars("/home/pi/$HOME/splash.jpeg", &env_stub);
+
+        assert_eq!(result.unwrap(), "/home/pi/$HOME/splash.jpeg");
+    }
+
+    #[test]
+    fn when_variable_is_not_set_then_expand_vars_fails() {
+        let mut env_stub = MockEnv::new();
+        env_stub.expect_var().return_const(None);
+
+        let result = expand_vars("https://${NAS}/mo/sharing/FakeSharingId", &env_stub);
+
+        assert!(result.is_err_and(|e| e.to_string().contains("NAS")));
+    }
+}
@@ -0,0 +1,146 @@
+//! Alignment grid overlay, helpful when mounting or leveling the frame
+
+use anyhow::Result;
+
+use crate::render::{Color, Key, Rect, Renderer};
+
+/// Key toggling the grid
+const TOGGLE_KEY: Key = Key::Char('g');
+const LINE_COLOR: Color = Color::rgba(255, 255, 255, 160);
+
+/// Rule-of-thirds lines and a center crosshair drawn over the displayed photo
+pub struct AlignmentGrid {
+    is_visible: bool,
+    /// Set when the grid gets hidden, so that the photo is redrawn without it once
+    needs_clear: bool,
+}
+
+impl AlignmentGrid {
+    pub fn new(is_visible: bool) -> Self {
+        AlignmentGrid {
+            is_visible,
+            needs_clear: false,
+        }
+    }
+
+    /// Toggles the grid for every press of the toggle key
+    pub fn handle_keys(&mut self, keys: &[Key]) {
+        for _ in keys.iter().filter(|&&key| key == TOGGLE_KEY) {
+            self.is_visible = !self.is_visible;
+            self.needs_clear = !self.is_visible;
+        }
+    }
+
+    /// Whether the current photo needs to be redrawn with [AlignmentGrid::draw_lines] over it.
+    /// True on every slideshow loop iteration while the grid is visible, as transitions draw over
+    /// the grid
+    pub fn needs_redraw(&self) -> bool {
+        self.is_visible || self.needs_clear
+    }
+
+    /// Draws the grid over the canvas when visible
+    pub fn draw_lines(&mut self, renderer: &mut impl Renderer) -> Result<()> {
+        if self.is_visible {
+            for line in grid_lines(renderer.size()) {
+                renderer.fill_canvas_rect(line, LINE_COLOR)?;
+            }
+        }
+        self.needs_clear = false;
+        Ok(())
+    }
+}
+
+fn grid_lines((w, h): (u32, u32)) -> [Rect; 6] {
+    let thickness = (w.min(h) / 400).max(1);
+    let offset = (thickness / 2) as i32;
+    /* Half of the crosshair's length */
+    let arm = w.min(h) / 20;
+    let (center_x, center_y) = ((w / 2) as i32, (h / 2) as i32);
+    [
+        Rect::new((w / 3) as i32 - offset, 0, thickness, h),
+        Rect::new((w * 2 / 3) as i32 - offset, 0, thickness, h),
+        Rect::new(0, (h / 3) as i32 - offset, w, thickness),
+        Rect::new(0, (h * 2 / 3) as i32 - offset, w, thickness),
+        Rect::new(center_x - arm as i32, center_y - offset, arm * 2, thickness),
+        Rect::new(center_x - offset, center_y - arm as i32, thickness, arm * 2),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::render::MockRenderer;
+
+    use super::*;
+
+    #[test]
+    fn when_grid_is_hidden_then_it_does_not_need_redraw() {
+        let mut grid = AlignmentGrid::new(false);
+
+        grid.handle_keys(&[Key::Char('x'), Key::Other]);
+
+        assert!(!grid.needs_redraw());
+    }
+
+    #[test]
+    fn when_grid_is_visible_then_draw_lines_draws_lines_on_every_redraw() {
+        let mut renderer_mock = new_renderer_mock();
+        renderer_mock
+            .expect_fill_canvas_rect()
+            .times(12)
+            .returning(|_, _| Ok(()));
+        let mut grid = AlignmentGrid::new(true);
+
+        for _ in 0..2 {
+            assert!(grid.needs_redraw());
+            grid.draw_lines(&mut renderer_mock).unwrap();
+        }
+        assert!(grid.needs_redraw());
+    }
+
+    #[test]
+    fn when_toggled_off_then_photo_is_redrawn_without_grid_once() {
+        let mut renderer_mock = new_renderer_mock();
+        renderer_mock.expect_fill_canvas_rect().never();
+        let mut grid = AlignmentGrid::new(true);
+
+        grid.handle_keys(&[TOGGLE_KEY]);
+        assert!(grid.needs_redraw());
+        grid.draw_lines(&mut renderer_mock).unwrap();
+
+        assert!(!grid.needs_redraw());
+    }
+
+    #[test]
+    fn handle_keys_toggles_grid_on_every_toggle_key_press() {
+        let mut grid = AlignmentGrid::new(false);
+
+        grid.handle_keys(&[TOGGLE_KEY]);
+        assert!(grid.is_visible);
+
+        grid.handle_keys(&[TOGGLE_KEY, Key::Other, TOGGLE_KEY]);
+        assert!(grid.is_visible);
+        /* Hidden and shown again before being drawn, nothing to clear */
+        assert!(!grid.needs_clear);
+    }
+
+    #[test]
+    fn grid_lines_fit_on_screen() {
+        const SCREEN_SIZE: (u32, u32) = (1920, 1080);
+
+        let result = grid_lines(SCREEN_SIZE);
+
+        for line in result {
+            assert!(line.x >= 0 && line.y >= 0);
+            assert!(line.x as u32 + line.w <= SCREEN_SIZE.0);
+            assert!(line.y as u32 + line.h <= SCREEN_SIZE.1);
+        }
+        assert_eq!(result[0], Rect::new(639, 0, 2, 1080));
+        assert_eq!(result[4], Rect::new(906, 539, 108, 2));
+    }
+
+    fn new_renderer_mock() -> MockRenderer {
+        let mut renderer_mock = MockRenderer::new();
+        renderer_mock.expect_size().return_const((1920, 1080));
+        renderer_mock
+    }
+}
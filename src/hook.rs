@@ -0,0 +1,216 @@
+//! Command executed when a photo is displayed
+
+use std::process::{Child, Command};
+
+use chrono::NaiveDate;
+
+use crate::metadata::PhotoInfo;
+
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Runs a shell command in the background each time a new photo is displayed
+pub struct PhotoHook {
+    command: String,
+    /// Template of PHOTO_INFO, see [PhotoInfo::format]
+    info_format: Option<String>,
+    running: Option<Child>,
+}
+
+impl PhotoHook {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            info_format: None,
+            running: None,
+        }
+    }
+
+    pub fn with_info_format(mut self, info_format: Option<&str>) -> Self {
+        self.info_format = info_format.map(str::to_string);
+        self
+    }
+
+    /// Spawns the command without waiting for it to finish. Failures are only logged, so they do
+    /// not interrupt the slideshow. `today` is used for PHOTO_ANNIVERSARY, see
+    /// [PhotoInfo::anniversary]
+    pub fn run(&mut self, info: &PhotoInfo, today: NaiveDate) {
+        if let Some(child) = &mut self.running {
+            match child.try_wait() {
+                Ok(None) => {
+                    log::warn!("Previous --on-photo command is still running, skipping");
+                    return;
+                }
+                Ok(Some(status)) if !status.success() => {
+                    log::warn!("Previous --on-photo command failed: {status}")
+                }
+                Ok(Some(_)) => {}
+                Err(error) => log::error!("--on-photo: {error}"),
+            }
+        }
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.command)
+            .env("PHOTO_ID", &info.id)
+            /* Do not leak values inherited from the app's environment */
+            .env_remove("PHOTO_DATE")
+            .env_remove("PHOTO_LOCATION")
+            .env_remove("PHOTO_POSITION")
+            .env_remove("PHOTO_INFO")
+            .env_remove("PHOTO_ANNIVERSARY");
+        if let Some(date) = info.date {
+            command.env("PHOTO_DATE", date.format(DATE_FORMAT).to_string());
+        }
+        if let Some(location) = &info.location {
+            command.env("PHOTO_LOCATION", location);
+        }
+        if let Some((position, count)) = info.position {
+            command.env("PHOTO_POSITION", format!("{position}/{count}"));
+        }
+        if let Some(caption) = self
+            .info_format
+            .as_deref()
+            .and_then(|template| info.format(template))
+        {
+            command.env("PHOTO_INFO", caption);
+        }
+        if let Some(anniversary) = info.anniversary(today) {
+            command.env("PHOTO_ANNIVERSARY", anniversary);
+        }
+        self.running = match command.spawn() {
+            Ok(child) => Some(child),
+            Err(error) => {
+                log::error!("--on-photo: {error}");
+                None
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process};
+
+    use super::*;
+
+    #[test]
+    fn run_passes_photo_info_in_environment_variables() {
+        let output = temp_file_path("env");
+        let mut hook = PhotoHook::new(&format!(
+            "echo \"$PHOTO_ID|$PHOTO_DATE|${{PHOTO_LOCATION-unset}}\" > {}",
+            output.to_string_lossy()
+        ));
+        let info = PhotoInfo {
+            id: "42".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 5, 1)
+                .unwrap()
+                .and_hms_opt(14, 30, 0),
+            ..Default::default()
+        };
+
+        hook.run(&info, today());
+
+        hook.running.take().unwrap().wait().unwrap();
+        let result = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert_eq!(result, "42|2024-05-01T14:30:00|unset\n");
+    }
+
+    #[test]
+    fn run_passes_photo_info_formatted_with_info_format() {
+        let output = temp_file_path("info");
+        let mut hook = PhotoHook::new(&format!(
+            "echo \"${{PHOTO_INFO-unset}}\" >> {}",
+            output.to_string_lossy()
+        ))
+        .with_info_format(Some("{filename} ({location})"));
+        let info = PhotoInfo {
+            id: "42".to_string(),
+            file_name: Some("IMG_0042.jpg".to_string()),
+            location: Some("Gdansk".to_string()),
+            ..Default::default()
+        };
+
+        hook.run(&info, today());
+        hook.running.take().unwrap().wait().unwrap();
+        hook.run(
+            &PhotoInfo {
+                id: "43".to_string(),
+                ..Default::default()
+            },
+            today(),
+        );
+        hook.running.take().unwrap().wait().unwrap();
+
+        let result = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert_eq!(result, "IMG_0042.jpg (Gdansk)\nunset\n");
+    }
+
+    #[test]
+    fn run_passes_anniversary_when_photo_was_taken_on_this_day() {
+        let output = temp_file_path("anniversary");
+        let mut hook = PhotoHook::new(&format!(
+            "echo \"${{PHOTO_ANNIVERSARY-unset}}\" >> {}",
+            output.to_string_lossy()
+        ));
+        let taken_on = |month, day| PhotoInfo {
+            id: "42".to_string(),
+            date: NaiveDate::from_ymd_opt(2019, month, day)
+                .unwrap()
+                .and_hms_opt(14, 30, 0),
+            ..Default::default()
+        };
+
+        hook.run(&taken_on(5, 1), today());
+        hook.running.take().unwrap().wait().unwrap();
+        hook.run(&taken_on(5, 2), today());
+        hook.running.take().unwrap().wait().unwrap();
+
+        let result = fs::read_to_string(&output).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert_eq!(result, "5 years ago today\nunset\n");
+    }
+
+    #[test]
+    fn when_previous_command_is_running_then_run_skips_command() {
+        let mut hook = PhotoHook::new("sleep 5");
+        let info = PhotoInfo {
+            id: "1".to_string(),
+            ..Default::default()
+        };
+        hook.run(&info, today());
+        let first_pid = hook.running.as_ref().unwrap().id();
+
+        hook.run(&info, today());
+
+        let mut child = hook.running.take().unwrap();
+        assert_eq!(child.id(), first_pid);
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn when_command_fails_then_run_keeps_going() {
+        let mut hook = PhotoHook::new("exit 1");
+        let info = PhotoInfo {
+            id: "1".to_string(),
+            ..Default::default()
+        };
+        hook.run(&info, today());
+        hook.running.as_mut().unwrap().wait().unwrap();
+
+        hook.run(&info, today());
+
+        assert!(hook.running.take().unwrap().wait().is_ok());
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, 1).unwrap()
+    }
+
+    fn temp_file_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("syno-photo-frame-hook-{}-{name}", process::id()))
+    }
+}
@@ -1,15 +1,22 @@
 //! HTTP request-response handling
 
-use std::fmt::Formatter;
+use std::{
+    fmt::Formatter,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::Path,
+    time::Duration,
+};
 
 pub(crate) use bytes::Bytes;
-pub use reqwest::{blocking::ClientBuilder, cookie::CookieStore};
+pub use reqwest::{blocking::ClientBuilder, cookie::CookieStore, Certificate, Identity};
 pub(crate) use reqwest::{StatusCode, Url};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use reqwest::blocking::{Client as ReqwestClient, Response as ReqwestResponse};
 use serde::de::DeserializeOwned;
 
+use crate::cli::IpVersion;
+
 #[cfg(test)]
 pub(crate) use reqwest::cookie::Jar;
 
@@ -25,6 +32,14 @@ pub trait HttpClient {
     ) -> Result<Self::Response>;
 
     fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<Self::Response>;
+
+    /// Like [HttpClient::get], but overrides the client's default timeout
+    fn get_with_timeout(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<Self::Response>;
 }
 
 /// Isolates [reqwest::blocking::Response] for testing
@@ -40,6 +55,72 @@ pub trait HttpResponse {
     fn text(self) -> Result<String>;
 }
 
+/// Restricts connections made by the built client to the selected IP version
+pub fn with_ip_version(client_builder: ClientBuilder, ip_version: IpVersion) -> ClientBuilder {
+    /* Binding to an unspecified local address of one family makes the connector skip server
+     * addresses of the other family */
+    let local_address = match ip_version {
+        IpVersion::Auto => None,
+        IpVersion::V4 => Some(IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        IpVersion::V6 => Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    };
+    client_builder.local_address(local_address)
+}
+
+/// Overrides system DNS resolution of the given hosts
+pub fn with_dns_overrides(
+    client_builder: ClientBuilder,
+    overrides: &[(String, IpAddr)],
+) -> ClientBuilder {
+    overrides
+        .iter()
+        .fold(client_builder, |client_builder, (host, ip)| {
+            /* Port 0 means the port from request URL is used */
+            client_builder.resolve(host, SocketAddr::new(*ip, 0))
+        })
+}
+
+/// Loads TLS client identity from PEM files when both certificate and private key are provided
+pub fn client_identity(
+    cert_path: Option<&Path>,
+    key_path: Option<&Path>,
+) -> Result<Option<Identity>> {
+    let (cert_path, key_path) = match (cert_path, key_path) {
+        (Some(cert_path), Some(key_path)) => (cert_path, key_path),
+        (None, None) => return Ok(None),
+        _ => bail!("Both client certificate and private key are required"),
+    };
+    let read = |path: &Path| {
+        std::fs::read(path).map_err(|error| anyhow!("{}: {error}", path.to_string_lossy()))
+    };
+    let identity =
+        Identity::from_pkcs8_pem(&read(cert_path)?, &read(key_path)?).map_err(|error| {
+            anyhow!(
+                "Failed to load client certificate {} with private key {}: {error}",
+                cert_path.to_string_lossy(),
+                key_path.to_string_lossy()
+            )
+        })?;
+    Ok(Some(identity))
+}
+
+/// Loads a PEM file with a certificate of a CA to trust in addition to the system's root
+/// certificates
+pub fn root_certificate(path: Option<&Path>) -> Result<Option<Certificate>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let pem =
+        std::fs::read(path).map_err(|error| anyhow!("{}: {error}", path.to_string_lossy()))?;
+    let certificate = Certificate::from_pem(&pem).map_err(|error| {
+        anyhow!(
+            "Failed to load CA certificate {}: {error}",
+            path.to_string_lossy()
+        )
+    })?;
+    Ok(Some(certificate))
+}
+
 impl HttpClient for ReqwestClient {
     type Response = ReqwestResponse;
 
@@ -59,6 +140,18 @@ impl HttpClient for ReqwestClient {
     fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<ReqwestResponse> {
         Ok(ReqwestClient::get(self, url).query(query).send()?)
     }
+
+    fn get_with_timeout(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<ReqwestResponse> {
+        Ok(ReqwestClient::get(self, url)
+            .query(query)
+            .timeout(timeout)
+            .send()?)
+    }
 }
 
 impl HttpResponse for ReqwestResponse {
@@ -92,6 +185,17 @@ where
     }
 }
 
+/// Whether a request failed for a reason likely to go away on its own, e.g. the server waking up
+/// from sleep or a Wi-Fi hiccup, so that the request is worth retrying
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    if let Some(InvalidHttpResponse(status)) = error.downcast_ref() {
+        return status.is_server_error();
+    }
+    error
+        .downcast_ref::<reqwest::Error>()
+        .is_some_and(|error| error.is_connect() || error.is_timeout())
+}
+
 #[derive(Debug)]
 pub struct InvalidHttpResponse(pub StatusCode);
 
@@ -100,3 +204,111 @@ impl std::fmt::Display for InvalidHttpResponse {
         write!(f, "Invalid HTTP response code: {}", self.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_server_errors_are_transient() {
+        let is_transient_status = |status| is_transient(&anyhow!(InvalidHttpResponse(status)));
+
+        assert!(is_transient_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient_status(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient_status(StatusCode::NOT_FOUND));
+        assert!(!is_transient_status(StatusCode::UNAUTHORIZED));
+        assert!(!is_transient(&anyhow!("Album is empty")));
+    }
+
+    #[test]
+    fn when_cert_and_key_are_provided_then_client_identity_is_some() {
+        let result = client_identity(
+            Some(Path::new("assets/test_client_cert.pem")),
+            Some(Path::new("assets/test_client_key.pem")),
+        );
+
+        assert!(matches!(result, Ok(Some(_))));
+    }
+
+    #[test]
+    fn when_cert_and_key_are_not_provided_then_client_identity_is_none() {
+        let result = client_identity(None, None);
+
+        assert!(matches!(result, Ok(None)));
+    }
+
+    #[test]
+    fn when_key_does_not_match_cert_format_then_client_identity_returns_error() {
+        let result = client_identity(
+            Some(Path::new("assets/test_client_key.pem")),
+            Some(Path::new("assets/test_client_cert.pem")),
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn when_cacert_is_provided_then_root_certificate_is_some() {
+        let result = root_certificate(Some(Path::new("assets/test_client_cert.pem")));
+
+        assert!(matches!(result, Ok(Some(_))));
+        assert!(matches!(root_certificate(None), Ok(None)));
+    }
+
+    #[test]
+    fn when_cacert_is_not_certificate_or_missing_then_root_certificate_returns_error() {
+        let not_certificate = root_certificate(Some(Path::new("assets/test_client_key.pem")));
+        let missing = root_certificate(Some(Path::new("assets/missing.pem")));
+
+        assert!(not_certificate.is_err());
+        assert!(missing
+            .unwrap_err()
+            .to_string()
+            .starts_with("assets/missing.pem: "));
+    }
+
+    #[test]
+    fn with_ip_version_sets_local_address_of_selected_ip_version() {
+        test_case(IpVersion::Auto, None);
+        test_case(IpVersion::V4, Some("local_address: 0.0.0.0"));
+        test_case(IpVersion::V6, Some("local_address: ::"));
+
+        fn test_case(ip_version: IpVersion, expected: Option<&str>) {
+            let result = with_ip_version(ClientBuilder::new(), ip_version);
+
+            /* ClientBuilder does not expose its configuration other than through Debug */
+            let debug = format!("{result:?}");
+            match expected {
+                Some(expected) => assert!(debug.contains(expected), "{debug}"),
+                None => assert!(!debug.contains("local_address"), "{debug}"),
+            }
+        }
+    }
+
+    #[test]
+    fn with_dns_overrides_sets_resolved_addresses() {
+        let overrides = [
+            ("nas.local".to_string(), "192.168.1.10".parse().unwrap()),
+            ("immich.local".to_string(), "fd00::10".parse().unwrap()),
+        ];
+
+        let result = with_dns_overrides(ClientBuilder::new(), &overrides);
+
+        let debug = format!("{result:?}");
+        assert!(
+            debug.contains(r#""nas.local": [192.168.1.10:0]"#),
+            "{debug}"
+        );
+        assert!(
+            debug.contains(r#""immich.local": [[fd00::10]:0]"#),
+            "{debug}"
+        );
+    }
+
+    #[test]
+    fn when_no_overrides_then_with_dns_overrides_keeps_system_resolution() {
+        let result = with_dns_overrides(ClientBuilder::new(), &[]);
+
+        assert!(!format!("{result:?}").contains("dns_overrides"));
+    }
+}
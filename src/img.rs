@@ -1,15 +1,21 @@
 pub use image::{open, DynamicImage};
 
-use std::thread::{self, JoinHandle};
+use std::{
+    fs,
+    io::Cursor,
+    path::Path,
+    thread::{self, JoinHandle},
+};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use image::{
     self,
     imageops::{self, FilterType},
-    GenericImageView,
+    metadata::Orientation,
+    GenericImageView, ImageDecoder, ImageFormat, ImageReader, RgbImage,
 };
 
-use crate::cli::{Background, Rotation};
+use crate::cli::{Background, Corner, Fit, Rotation};
 
 pub trait Framed {
     /// Resizes an image while preserving the aspect ratio, and centers it on screen. Returns a new
@@ -23,11 +29,39 @@ pub trait Framed {
         screen_size: (u32, u32),
         rotation: Rotation,
         background: Background,
-    ) -> Self;
+    ) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Works like [Framed::fit_to_screen_and_add_background] for [Fit::Contain], unless the
+    /// background would cover more than `max_letterbox_ratio` of the screen area. The image is
+    /// then resized to cover the whole screen, cropping the parts that do not fit, like for
+    /// [Fit::Cover]. [Fit::Stretch] resizes the image to the screen size ignoring its aspect ratio.
+    /// No background is created for modes filling the whole screen
+    fn fit_or_fill_screen(
+        &self,
+        screen_size: (u32, u32),
+        rotation: Rotation,
+        fit: Fit,
+        background: Background,
+        max_letterbox_ratio: Option<f64>,
+    ) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Crops the center of an image to match the aspect ratio `(width, height)`
+    fn crop_to_aspect(&self, aspect: (u32, u32)) -> Self;
+
+    /// Inverts colors of an image. Applied to the final image, i.e. the photo together with its
+    /// background
+    fn invert_colors(&mut self);
 
     /// Adds update icon to an image
     fn overlay_update_icon(&mut self, update_icon: &Self, rotation: Rotation);
 
+    /// Adds a logo to an image, in a corner of the (rotated) screen
+    fn overlay_logo(&mut self, logo: &Self, corner: Corner, rotation: Rotation);
+
     fn resize(&self, new_width: u32, new_height: u32) -> Self;
 
     fn rotate(&self, degrees: Rotation) -> Self;
@@ -45,7 +79,7 @@ impl Framed for DynamicImage {
         screen_size: (u32, u32),
         rotate: Rotation,
         background: Background,
-    ) -> Self {
+    ) -> Result<Self> {
         internal_fit_to_screen_and_add_background(
             self,
             screen_size,
@@ -55,6 +89,65 @@ impl Framed for DynamicImage {
         )
     }
 
+    fn fit_or_fill_screen(
+        &self,
+        screen_size: (u32, u32),
+        rotation: Rotation,
+        fit: Fit,
+        background: Background,
+        max_letterbox_ratio: Option<f64>,
+    ) -> Result<Self> {
+        let (w, h) = self.dimensions();
+        let rotated_size = match rotation {
+            Rotation::D0 | Rotation::D180 => (w, h),
+            Rotation::D90 | Rotation::D270 => (h, w),
+        };
+        let (x_res, y_res) = screen_size;
+        match (fit, max_letterbox_ratio) {
+            (Fit::Stretch, _) => {
+                Ok(self
+                    .rotate(rotation)
+                    .resize_exact(x_res, y_res, FilterType::Lanczos3))
+            }
+            (Fit::Cover, _) => {
+                Ok(self
+                    .rotate(rotation)
+                    .resize_to_fill(x_res, y_res, FilterType::Lanczos3))
+            }
+            (Fit::Contain, Some(max_ratio))
+                if letterbox_ratio(rotated_size, screen_size) > max_ratio =>
+            {
+                Ok(self
+                    .rotate(rotation)
+                    .resize_to_fill(x_res, y_res, FilterType::Lanczos3))
+            }
+            (Fit::Contain, _) => {
+                self.fit_to_screen_and_add_background(screen_size, rotation, background)
+            }
+        }
+    }
+
+    fn crop_to_aspect(&self, (aspect_w, aspect_h): (u32, u32)) -> Self {
+        let (w, h) = self.dimensions();
+        let (w, h, aspect_w, aspect_h) = (w as u64, h as u64, aspect_w as u64, aspect_h as u64);
+        let (crop_w, crop_h) = if w * aspect_h > h * aspect_w {
+            /* Too wide */
+            ((h * aspect_w / aspect_h).max(1), h)
+        } else {
+            (w, (w * aspect_h / aspect_w).max(1))
+        };
+        self.crop_imm(
+            ((w - crop_w) / 2) as u32,
+            ((h - crop_h) / 2) as u32,
+            crop_w as u32,
+            crop_h as u32,
+        )
+    }
+
+    fn invert_colors(&mut self) {
+        self.invert();
+    }
+
     fn overlay_update_icon(&mut self, update_icon: &Self, rotation: Rotation) {
         let (width, height) = (self.width(), self.height());
         let (x_offset, y_offset) = match rotation {
@@ -66,6 +159,21 @@ impl Framed for DynamicImage {
         imageops::overlay(self, update_icon, x_offset as i64, y_offset as i64);
     }
 
+    fn overlay_logo(&mut self, logo: &Self, corner: Corner, rotation: Rotation) {
+        let (width, height) = (self.width(), self.height());
+        /* Small margin separating the logo from the screen edges */
+        let margin = u32::min(width, height) / 50;
+        let right = width.saturating_sub(logo.width() + margin);
+        let bottom = height.saturating_sub(logo.height() + margin);
+        let (x_offset, y_offset) = match corner.rotate(rotation) {
+            Corner::TopLeft => (margin, margin),
+            Corner::TopRight => (right, margin),
+            Corner::BottomRight => (right, bottom),
+            Corner::BottomLeft => (margin, bottom),
+        };
+        imageops::overlay(self, logo, x_offset as i64, y_offset as i64);
+    }
+
     fn resize(&self, new_width: u32, new_height: u32) -> Self {
         self.resize(new_width, new_height, FilterType::Lanczos3)
     }
@@ -80,8 +188,189 @@ impl Framed for DynamicImage {
     }
 }
 
+impl Corner {
+    /// Maps a corner of a rotated screen to the corresponding corner of a non-rotated image
+    pub(crate) fn rotate(self, rotation: Rotation) -> Corner {
+        let clockwise = |corner| match corner {
+            Corner::TopLeft => Corner::TopRight,
+            Corner::TopRight => Corner::BottomRight,
+            Corner::BottomRight => Corner::BottomLeft,
+            Corner::BottomLeft => Corner::TopLeft,
+        };
+        let quarter_turns = match rotation {
+            Rotation::D0 => 0,
+            Rotation::D90 => 1,
+            Rotation::D180 => 2,
+            Rotation::D270 => 3,
+        };
+        (0..quarter_turns).fold(self, |corner, _| clockwise(corner))
+    }
+}
+
+/// Converts colors of photos before display
+#[cfg_attr(test, mockall::automock)]
+pub trait ColorTransform {
+    fn transform(&self, image: &mut DynamicImage);
+}
+
+/// Transforms colors from sRGB, which photos are assumed to use, to a display's ICC profile
+#[cfg(feature = "icc")]
+pub struct IccTransform(lcms2::Transform<u8, u8>);
+
+#[cfg(feature = "icc")]
+impl IccTransform {
+    pub fn load(display_profile_path: &Path) -> Result<Self> {
+        use lcms2::{Intent, PixelFormat, Profile, Transform};
+
+        let display_profile = Profile::new_file(display_profile_path)?;
+        let transform = Transform::new(
+            &Profile::new_srgb(),
+            PixelFormat::RGB_8,
+            &display_profile,
+            PixelFormat::RGB_8,
+            Intent::Perceptual,
+        )?;
+        Ok(Self(transform))
+    }
+}
+
+#[cfg(feature = "icc")]
+impl ColorTransform for IccTransform {
+    fn transform(&self, image: &mut DynamicImage) {
+        let mut rgb = std::mem::take(image).into_rgb8();
+        self.0.transform_in_place(&mut rgb);
+        *image = DynamicImage::ImageRgb8(rgb);
+    }
+}
+
+/// Gamma correction for panels displaying photos too dark or too bright. Values above 1 brighten
+/// mid-tones, values below 1 darken them, black and white are kept
+pub struct GammaCorrection {
+    /// Corrected value of every 8-bit channel value
+    lut: [u8; 256],
+}
+
+impl GammaCorrection {
+    pub fn new(gamma: f64) -> Self {
+        let mut lut = [0; 256];
+        for (value, corrected) in lut.iter_mut().enumerate() {
+            *corrected = (255.0 * (value as f64 / 255.0).powf(1.0 / gamma)).round() as u8;
+        }
+        Self { lut }
+    }
+
+    /// Corrects every pixel of an image. Applied to the final image, i.e. the photo together with
+    /// its background
+    pub fn apply(&self, image: &mut DynamicImage) {
+        let mut rgb = std::mem::take(image).into_rgb8();
+        for value in rgb.iter_mut() {
+            *value = self.lut[*value as usize];
+        }
+        *image = DynamicImage::ImageRgb8(rgb);
+    }
+}
+
+/// Loads color transform to the display's ICC profile. Failing to load it is not fatal, photos are
+/// then displayed without color management
+pub fn load_color_transform(display_profile_path: &Path) -> Option<Box<dyn ColorTransform + Send>> {
+    #[cfg(feature = "icc")]
+    let result = IccTransform::load(display_profile_path)
+        .map(|transform| Box::new(transform) as Box<dyn ColorTransform + Send>);
+    #[cfg(not(feature = "icc"))]
+    let result = Err(anyhow!("the app was built without the icc feature"));
+    match result {
+        Ok(transform) => Some(transform),
+        Err(error) => {
+            log::warn!(
+                "ICC profile {} not applied: {error}",
+                display_profile_path.to_string_lossy()
+            );
+            None
+        }
+    }
+}
+
+/// Decodes an image, rotating it upright according to its EXIF orientation. Malformed data results
+/// in an error rather than a panic further down the processing pipeline.
 pub fn load_from_memory(buffer: &[u8]) -> Result<DynamicImage> {
-    Ok(image::load_from_memory(buffer)?)
+    if is_raw(buffer) {
+        bail!("Camera RAW photos (DNG, CR2, NEF) cannot be decoded, only JPEG and PNG (and HEIC with the heif feature)");
+    }
+    if is_heif(buffer) {
+        return load_heif_from_memory(buffer);
+    }
+    let mut decoder = ImageReader::new(Cursor::new(buffer))
+        .with_guessed_format()?
+        .into_decoder()?;
+    /* Malformed EXIF should not prevent displaying the photo */
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    let (w, h) = image.dimensions();
+    if w == 0 || h == 0 {
+        bail!("Invalid image dimensions: {w}x{h}");
+    }
+    Ok(image)
+}
+
+/// Writes an image to a file, in a format (JPEG or PNG) given by the file extension. The image is
+/// written to a temporary file first and then renamed, so that readers of the file never see a
+/// partially written image
+pub fn export(image: &DynamicImage, path: &Path) -> Result<()> {
+    let format = ImageFormat::from_path(path)?;
+    let Some(file_name) = path.file_name() else {
+        bail!("Not a file path");
+    };
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    image.save_with_format(&temp_path, format)?;
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Detects TIFF-based camera RAW formats, so that they are reported as such rather than with a
+/// generic decoder error. RAW decoding is not supported. Neither JPEG nor PNG starts with a TIFF
+/// header, so anything that does cannot be decoded
+fn is_raw(buffer: &[u8]) -> bool {
+    buffer.starts_with(b"II*\0") || buffer.starts_with(b"MM\0*")
+}
+
+/// Detects HEIC/HEIF photos by the brand of their ISO base media file
+fn is_heif(buffer: &[u8]) -> bool {
+    const BRANDS: [&[u8]; 8] = [
+        b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1",
+    ];
+    buffer.len() >= 12 && &buffer[4..8] == b"ftyp" && BRANDS.contains(&&buffer[8..12])
+}
+
+/// Decodes a HEIC/HEIF photo. Transformations stored in the file (e.g. rotation) are applied by
+/// libheif
+#[cfg(feature = "heif")]
+fn load_heif_from_memory(buffer: &[u8]) -> Result<DynamicImage> {
+    use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+
+    let context = HeifContext::read_from_bytes(buffer)?;
+    let handle = context.primary_image_handle()?;
+    let image = LibHeif::new().decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)?;
+    let Some(plane) = image.planes().interleaved else {
+        bail!("HEIF image has no RGB data");
+    };
+    let row_len = plane.width as usize * 3;
+    let pixels = plane
+        .data
+        .chunks(plane.stride)
+        .take(plane.height as usize)
+        .flat_map(|row| &row[..row_len])
+        .copied()
+        .collect();
+    let Some(rgb) = RgbImage::from_raw(plane.width, plane.height, pixels) else {
+        bail!("Invalid HEIF image data");
+    };
+    Ok(DynamicImage::ImageRgb8(rgb))
+}
+
+#[cfg(not(feature = "heif"))]
+fn load_heif_from_memory(_buffer: &[u8]) -> Result<DynamicImage> {
+    bail!("HEIC/HEIF photos are not supported, the app was built without the heif feature")
 }
 
 /// Testable version of [Framed::fit_to_screen_and_add_background]
@@ -91,33 +380,40 @@ fn internal_fit_to_screen_and_add_background(
     rotate: Rotation,
     background: Background,
     brighten_and_blur: fn(&DynamicImage) -> DynamicImage,
-) -> DynamicImage {
+) -> Result<DynamicImage> {
     let rotated = original.rotate(rotate);
     if rotated.dimensions() == screen_size {
-        return rotated;
+        return Ok(rotated);
     }
 
     let foreground = resize_to_fit_screen(&rotated, screen_size);
     if foreground.dimensions() == screen_size {
-        return foreground;
+        return Ok(foreground);
     }
 
     let (x_res, y_res) = screen_size;
     let mut final_image = DynamicImage::new_rgb8(x_res, y_res);
 
-    if background == Background::Blur {
-        let (bg_thread1, bg_thread2) =
-            background_fill_threads(&rotated, screen_size, brighten_and_blur);
-        let bg_fill_1 = bg_thread1.join().unwrap();
-        imageops::overlay(&mut final_image, &bg_fill_1, 0, 0);
-
-        let bg_fill_2 = bg_thread2.join().unwrap();
-        imageops::overlay(
-            &mut final_image,
-            &bg_fill_2,
-            (x_res - bg_fill_2.width()) as i64,
-            (y_res - bg_fill_2.height()) as i64,
-        );
+    match background {
+        Background::Blur => {
+            let (bg_thread1, bg_thread2) =
+                background_fill_threads(&rotated, screen_size, brighten_and_blur);
+            /* A panic in a background thread would otherwise take down the photo fetcher thread */
+            let bg_fill_1 = join_background_thread(bg_thread1)?;
+            imageops::overlay(&mut final_image, &bg_fill_1, 0, 0);
+
+            let bg_fill_2 = join_background_thread(bg_thread2)?;
+            imageops::overlay(
+                &mut final_image,
+                &bg_fill_2,
+                (x_res - bg_fill_2.width()) as i64,
+                (y_res - bg_fill_2.height()) as i64,
+            );
+        }
+        Background::Color(color) => {
+            final_image = RgbImage::from_pixel(x_res, y_res, color).into();
+        }
+        Background::None => {}
     }
 
     let (w_diff, h_diff) = Dimensions::from(screen_size).diff(foreground.dimensions().into());
@@ -128,7 +424,20 @@ fn internal_fit_to_screen_and_add_background(
         (h_diff / 2.0).round() as i64,
     );
 
-    final_image
+    Ok(final_image)
+}
+
+/// Fraction of the screen area not covered by an image of `image_size` fitted to the screen
+fn letterbox_ratio(image_size: (u32, u32), screen_size: (u32, u32)) -> f64 {
+    let screen = Dimensions::from(screen_size);
+    let foreground = Dimensions::from(image_size).resize(screen);
+    1.0 - (foreground.w * foreground.h) / (screen.w * screen.h)
+}
+
+fn join_background_thread(handle: JoinHandle<DynamicImage>) -> Result<DynamicImage> {
+    handle
+        .join()
+        .map_err(|_| anyhow!("Failed to create background fill"))
 }
 
 fn resize_to_fit_screen(original: &DynamicImage, (x_res, y_res): (u32, u32)) -> DynamicImage {
@@ -210,10 +519,30 @@ fn background_fill_threads(
     (bg_thread1, bg_thread2)
 }
 
+const BACKGROUND_BRIGHTNESS_OFFSET: i32 = -20;
+const BACKGROUND_BLUR_SIGMA: f32 = 45.0;
+
 fn brighten_and_blur_background(background: &DynamicImage) -> DynamicImage {
-    const BRIGHTNESS_OFFSET: i32 = -20;
-    const BLUR_SIGMA: f32 = 45.0;
-    background.brighten(BRIGHTNESS_OFFSET).blur(BLUR_SIGMA)
+    background
+        .brighten(BACKGROUND_BRIGHTNESS_OFFSET)
+        .blur(BACKGROUND_BLUR_SIGMA)
+}
+
+/// Creates a blurred version of a screen image, looking like the background fill, for the blur
+/// bridge transition. A downscaled copy is blurred, which is much cheaper than blurring at the
+/// screen resolution and hardly distinguishable after such a strong blur
+pub fn blur_bridge(image: &DynamicImage) -> DynamicImage {
+    const DOWNSCALE: u32 = 8;
+    let (w, h) = image.dimensions();
+    image
+        .resize_exact(
+            (w / DOWNSCALE).max(1),
+            (h / DOWNSCALE).max(1),
+            FilterType::Triangle,
+        )
+        .brighten(BACKGROUND_BRIGHTNESS_OFFSET)
+        .blur(BACKGROUND_BLUR_SIGMA / DOWNSCALE as f32)
+        .resize_exact(w, h, FilterType::Triangle)
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -328,7 +657,7 @@ struct Coords {
 #[cfg(test)]
 mod tests {
     use crate::cli::Rotation;
-    use image::{GenericImage, GenericImageView, Rgba};
+    use image::{GenericImage, GenericImageView, Rgb, Rgba};
 
     use super::*;
 
@@ -348,7 +677,8 @@ mod tests {
             Rotation::D0,
             Background::Blur,
             panicking_brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), 120 * 80);
         assert!(result.pixels().all(|(_, _, p)| p == pixel));
@@ -366,7 +696,8 @@ mod tests {
             Rotation::D0,
             Background::Blur,
             panicking_brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), 640 * 360);
         assert!(result.pixels().all(|(_, _, p)| p == pixel));
@@ -386,7 +717,8 @@ mod tests {
             Rotation::D0,
             Background::None,
             brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), (x_res * y_res) as usize);
         let expected_bg_w = 10;
@@ -402,6 +734,35 @@ mod tests {
         }
     }
 
+    #[test]
+    fn when_background_is_color_then_margins_are_filled_with_color() {
+        let original = create_test_image((50, 40), RED);
+        let (x_res, y_res) = (120, 80); /* screen resolution */
+        fn panicking_brighten_and_blur_stub(_: &DynamicImage) -> DynamicImage {
+            panic!("background should not be blurred")
+        }
+
+        let result = internal_fit_to_screen_and_add_background(
+            &original,
+            (x_res, y_res),
+            Rotation::D0,
+            Background::Color(Rgb([128, 64, 32])),
+            panicking_brighten_and_blur_stub,
+        )
+        .unwrap();
+
+        assert_eq!(result.pixels().count(), (x_res * y_res) as usize);
+        let expected_bg_w = 10;
+        for y in 0..y_res {
+            for x in (0..expected_bg_w).chain(x_res - expected_bg_w..x_res) {
+                assert_eq!(result.get_pixel(x, y), Rgba([128, 64, 32, 255]));
+            }
+            for x in expected_bg_w..x_res - expected_bg_w {
+                assert_eq!(result.get_pixel(x, y), RED);
+            }
+        }
+    }
+
     #[test]
     fn when_smaller_image_fits_vertically_then_background_fills_left_and_right_space() {
         let mut original = create_test_image((50, 40), RED);
@@ -427,7 +788,8 @@ mod tests {
             Rotation::D0,
             Background::Blur,
             brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), (x_res * y_res) as usize);
         let expected_bg_w = 10;
@@ -468,7 +830,8 @@ mod tests {
             Rotation::D0,
             Background::Blur,
             brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), (x_res * y_res) as usize);
         let expected_bg_w = 5;
@@ -509,7 +872,8 @@ mod tests {
             Rotation::D0,
             Background::Blur,
             brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), (x_res * y_res) as usize);
         let expected_bg_h = 10;
@@ -550,7 +914,8 @@ mod tests {
             Rotation::D0,
             Background::Blur,
             brighten_and_blur_stub,
-        );
+        )
+        .unwrap();
 
         assert_eq!(result.pixels().count(), (x_res * y_res) as usize);
         let expected_bg_h = 5;
@@ -566,6 +931,167 @@ mod tests {
         }
     }
 
+    #[test]
+    fn letterbox_ratio_is_fraction_of_screen_not_covered_by_image() {
+        test_case((160, 90), (1920, 1080), 0.0);
+        test_case(
+            (90, 160),
+            (1600, 900),
+            1.0 - (506.25 * 900.0) / (1600.0 * 900.0),
+        );
+        test_case((400, 100), (800, 800), 0.75);
+
+        fn test_case(image_size: (u32, u32), screen_size: (u32, u32), expected: f64) {
+            let result = letterbox_ratio(image_size, screen_size);
+
+            assert!((result - expected).abs() < 1e-9, "{result} != {expected}");
+        }
+    }
+
+    #[test]
+    fn fit_or_fill_screen_crops_image_only_when_letterbox_exceeds_max_ratio() {
+        /* Panorama on a square screen leaves 75% of the screen to the background */
+        let original = create_test_image((400, 100), RED);
+        let screen = (80, 80);
+        test_case(&original, screen, None, false);
+        test_case(&original, screen, Some(0.8), false);
+        test_case(&original, screen, Some(0.75), false);
+        test_case(&original, screen, Some(0.7), true);
+
+        fn test_case(
+            original: &DynamicImage,
+            screen: (u32, u32),
+            max_letterbox_ratio: Option<f64>,
+            expect_filled: bool,
+        ) {
+            let result = original.fit_or_fill_screen(
+                screen,
+                Rotation::D0,
+                Fit::Contain,
+                Background::None,
+                max_letterbox_ratio,
+            );
+
+            assert!(result.is_ok());
+            let result = result.unwrap();
+            assert_eq!(result.dimensions(), screen);
+            /* Top-left corner is black background when letterboxed */
+            assert_eq!(
+                result.get_pixel(0, 0) == RED,
+                expect_filled,
+                "{max_letterbox_ratio:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn fit_or_fill_screen_with_cover_crops_center_of_image() {
+        /* Red center with blue sides, which do not fit on the square screen */
+        let mut original = create_test_image((400, 100), BLUE);
+        imageops::overlay(&mut original, &create_test_image((100, 100), RED), 150, 0);
+        let screen = (80, 80);
+
+        let result = original
+            .fit_or_fill_screen(screen, Rotation::D0, Fit::Cover, Background::Blur, None)
+            .unwrap();
+
+        assert_eq!(result.dimensions(), screen);
+        /* Away from the edges, which get blended with the sides when resizing */
+        assert_eq!(result.get_pixel(10, 40), RED);
+        assert_eq!(result.get_pixel(70, 40), RED);
+    }
+
+    #[test]
+    fn fit_or_fill_screen_with_stretch_fills_screen_with_whole_image() {
+        /* Red left half and blue right half */
+        let mut original = create_test_image((400, 100), RED);
+        imageops::overlay(&mut original, &create_test_image((200, 100), BLUE), 200, 0);
+        let screen = (80, 80);
+
+        let result = original
+            .fit_or_fill_screen(screen, Rotation::D0, Fit::Stretch, Background::Blur, None)
+            .unwrap();
+
+        assert_eq!(result.dimensions(), screen);
+        assert_eq!(result.get_pixel(0, 0), RED);
+        assert_eq!(result.get_pixel(79, 79), BLUE);
+    }
+
+    #[test]
+    fn fit_or_fill_screen_with_cover_or_stretch_rotates_image() {
+        let original = create_test_image((100, 400), RED);
+        let screen = (80, 60);
+
+        for fit in [Fit::Cover, Fit::Stretch] {
+            let result = original
+                .fit_or_fill_screen(screen, Rotation::D90, fit, Background::None, None)
+                .unwrap();
+
+            assert_eq!(result.dimensions(), screen);
+        }
+    }
+
+    #[test]
+    fn crop_to_aspect_crops_center_of_image() {
+        test_case((400, 200), (4, 3), (266, 200));
+        test_case((300, 400), (4, 3), (300, 225));
+        test_case((300, 400), (3, 4), (300, 400));
+        test_case((100, 100), (16, 9), (100, 56));
+
+        fn test_case(original: (u32, u32), aspect: (u32, u32), expected: (u32, u32)) {
+            let (w, h) = original;
+            /* Red frame marks the center area which should be left after cropping */
+            let mut image = create_test_image(original, GREEN);
+            let (expected_w, expected_h) = expected;
+            let (x_offset, y_offset) = ((w - expected_w) / 2, (h - expected_h) / 2);
+            image.put_pixel(x_offset, y_offset, RED);
+            image.put_pixel(x_offset + expected_w - 1, y_offset + expected_h - 1, RED);
+
+            let result = image.crop_to_aspect(aspect);
+
+            assert_eq!(result.dimensions(), expected);
+            assert_eq!(result.get_pixel(0, 0), RED);
+            assert_eq!(result.get_pixel(expected_w - 1, expected_h - 1), RED);
+        }
+    }
+
+    #[test]
+    fn invert_colors_inverts_photo_and_background() {
+        let image = create_test_image((100, 50), RED);
+        let mut result = image
+            .fit_to_screen_and_add_background((100, 100), Rotation::D0, Background::None)
+            .unwrap();
+
+        result.invert_colors();
+
+        /* Photo is centered with black background above and below */
+        assert_eq!(result.get_pixel(50, 50), Rgba([0, 255, 255, 255]));
+        assert_eq!(result.get_pixel(50, 0), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn gamma_correction_corrects_mid_gray_and_keeps_black_and_white() {
+        test_case(2.2, 186);
+        test_case(0.5, 64);
+        test_case(1.0, 128);
+
+        fn test_case(gamma: f64, expected_gray: u8) {
+            let mut image = create_test_image((3, 1), Rgba([128, 128, 128, 255]));
+            image.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+            image.put_pixel(2, 0, Rgba([255, 255, 255, 255]));
+
+            GammaCorrection::new(gamma).apply(&mut image);
+
+            assert_eq!(
+                image.get_pixel(0, 0),
+                Rgba([expected_gray, expected_gray, expected_gray, 255]),
+                "gamma {gamma}"
+            );
+            assert_eq!(image.get_pixel(1, 0), Rgba([0, 0, 0, 255]));
+            assert_eq!(image.get_pixel(2, 0), Rgba([255, 255, 255, 255]));
+        }
+    }
+
     fn create_test_image((w, h): (u32, u32), pixel: Rgba<u8>) -> DynamicImage {
         let mut image = DynamicImage::new_rgb8(w, h);
         for y in 0..h {
@@ -579,4 +1105,181 @@ mod tests {
     fn panicking_brighten_and_blur_stub(_: &DynamicImage) -> DynamicImage {
         panic!("Unexpected creation of background when image fits perfectly");
     }
+
+    #[test]
+    fn overlay_logo_places_logo_in_requested_corner_of_rotated_screen() {
+        /* Screen is 100x50, so the margin is 1 pixel. Expected coordinates are the top-left
+         * pixel of the logo in the non-rotated image. */
+        test_case(Corner::TopLeft, Rotation::D0, (1, 1));
+        test_case(Corner::TopRight, Rotation::D0, (89, 1));
+        test_case(Corner::BottomRight, Rotation::D0, (89, 44));
+        test_case(Corner::BottomLeft, Rotation::D0, (1, 44));
+        test_case(Corner::TopLeft, Rotation::D90, (89, 1));
+        test_case(Corner::BottomRight, Rotation::D90, (1, 44));
+        test_case(Corner::TopLeft, Rotation::D180, (89, 44));
+        test_case(Corner::BottomRight, Rotation::D180, (1, 1));
+        test_case(Corner::TopLeft, Rotation::D270, (1, 44));
+        test_case(Corner::BottomRight, Rotation::D270, (89, 1));
+
+        fn test_case(corner: Corner, rotation: Rotation, (x, y): (u32, u32)) {
+            let mut image = create_test_image((100, 50), BLUE);
+            let logo = create_test_image((10, 5), RED);
+
+            image.overlay_logo(&logo, corner, rotation);
+
+            assert_eq!(image.get_pixel(x, y), RED);
+            assert_eq!(image.get_pixel(x + 9, y + 4), RED);
+            assert_eq!(image.pixels().filter(|(_, _, p)| *p == RED).count(), 50);
+        }
+    }
+
+    #[test]
+    fn when_background_fill_panics_then_error_is_returned() {
+        let original = create_test_image((50, 40), RED);
+        fn panicking_stub(_: &DynamicImage) -> DynamicImage {
+            panic!("Simulated failure to create background")
+        }
+
+        let result = internal_fit_to_screen_and_add_background(
+            &original,
+            (120, 80),
+            Rotation::D0,
+            Background::Blur,
+            panicking_stub,
+        );
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn load_from_memory_does_not_panic_on_truncated_or_corrupted_bytes() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let valid = std::fs::read("assets/test_loading.jpeg").unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let screen = (64, 48);
+
+        for len in (0..valid.len()).step_by(valid.len() / 50) {
+            let truncated = &valid[..len];
+            if let Ok(image) = load_from_memory(truncated) {
+                let _ =
+                    image.fit_to_screen_and_add_background(screen, Rotation::D0, Background::Blur);
+            }
+        }
+        for _ in 0..50 {
+            let mut corrupted = valid.clone();
+            for _ in 0..10 {
+                let i = rng.gen_range(0..corrupted.len());
+                corrupted[i] = rng.gen();
+            }
+            if let Ok(image) = load_from_memory(&corrupted) {
+                let _ =
+                    image.fit_to_screen_and_add_background(screen, Rotation::D90, Background::Blur);
+            }
+        }
+        for len in [0, 1, 2, 16, 1024] {
+            let random_bytes = (0..len).map(|_| rng.gen()).collect::<Vec<u8>>();
+            assert!(load_from_memory(&random_bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn load_from_memory_rotates_photo_according_to_exif_orientation() {
+        /* APP1 segment with a little-endian TIFF structure containing only the Orientation tag
+         * (0x0112) set to 6, i.e. the photo needs to be rotated 90° clockwise */
+        const EXIF_ORIENTATION_6: &[u8] = b"\xff\xe1\x00\x22Exif\0\0II*\0\x08\0\0\0\x01\0\
+            \x12\x01\x03\0\x01\0\0\0\x06\0\0\0\0\0\0\0";
+        let mut jpeg = vec![];
+        DynamicImage::new_rgb8(4, 2)
+            .write_to(&mut Cursor::new(&mut jpeg), ImageFormat::Jpeg)
+            .unwrap();
+        /* Right after the start of image marker */
+        jpeg.splice(2..2, EXIF_ORIENTATION_6.iter().copied());
+
+        let result = load_from_memory(&jpeg).unwrap();
+
+        assert_eq!(result.dimensions(), (2, 4));
+    }
+
+    #[test]
+    fn when_photo_is_camera_raw_then_load_from_memory_returns_descriptive_error() {
+        /* Little-endian TIFF header followed by "CR" marker, as found in Canon CR2 files */
+        const CR2_HEADER: &[u8] = b"II*\0\x10\0\0\0CR\x02\0";
+        /* Big-endian TIFF header, as found in Nikon NEF files */
+        const NEF_HEADER: &[u8] = b"MM\0*\0\0\0\x08";
+
+        for header in [CR2_HEADER, NEF_HEADER] {
+            let result = load_from_memory(header);
+
+            assert!(result.is_err_and(|e| e.to_string().contains("RAW")));
+        }
+    }
+
+    #[test]
+    fn is_heif_detects_heif_brands() {
+        assert!(is_heif(b"\0\0\0\x18ftypheic\0\0\0\0"));
+        assert!(is_heif(b"\0\0\0\x18ftypmif1\0\0\0\0"));
+        /* MP4 video */
+        assert!(!is_heif(b"\0\0\0\x18ftypisom\0\0\0\0"));
+        assert!(!is_heif(b"\0\0\0\x18ftyp"));
+    }
+
+    #[cfg(not(feature = "heif"))]
+    #[test]
+    fn when_heif_feature_is_disabled_then_load_from_memory_returns_descriptive_error() {
+        let result = load_from_memory(b"\0\0\0\x18ftypheic\0\0\0\0");
+
+        assert!(result.is_err_and(|e| e.to_string().contains("heif feature")));
+    }
+
+    #[test]
+    fn when_icc_profile_cannot_be_loaded_then_color_transform_is_skipped() {
+        let result = load_color_transform(Path::new("/nonexistent/display.icc"));
+
+        assert!(result.is_none());
+    }
+
+    #[cfg(feature = "icc")]
+    #[test]
+    fn icc_transform_converts_photo_to_display_profile() {
+        let profile_path =
+            std::env::temp_dir().join(format!("syno-photo-frame-srgb-{}.icc", std::process::id()));
+        std::fs::write(&profile_path, lcms2::Profile::new_srgb().icc().unwrap()).unwrap();
+        let transform = load_color_transform(&profile_path);
+        std::fs::remove_file(&profile_path).unwrap();
+        let mut image = create_test_image((4, 2), RED);
+
+        transform.unwrap().transform(&mut image);
+
+        /* sRGB to sRGB, colors stay (almost) the same */
+        assert_eq!(image.dimensions(), (4, 2));
+        let Rgba([r, g, b, _]) = image.get_pixel(1, 1);
+        assert!(r >= 250 && g <= 5 && b <= 5);
+    }
+
+    #[test]
+    fn blur_bridge_matches_image_size_and_darkens_it() {
+        let original = create_test_image((120, 80), Rgba([100, 100, 100, 255]));
+
+        let result = blur_bridge(&original);
+
+        assert_eq!(result.dimensions(), (120, 80));
+        assert_eq!(result.as_bytes().len(), original.as_bytes().len());
+        assert!(result
+            .pixels()
+            .all(|(_, _, p)| p == Rgba([80, 80, 80, 255])));
+    }
+
+    #[test]
+    fn fit_to_screen_and_add_background_does_not_panic_on_extreme_dimensions() {
+        let screen = (120, 80);
+        for dimensions in [(1, 1), (1, 500), (500, 1), (2, 1000), (1000, 2), (119, 80)] {
+            let original = create_test_image(dimensions, RED);
+            for rotation in [Rotation::D0, Rotation::D90, Rotation::D180, Rotation::D270] {
+                let result =
+                    original.fit_to_screen_and_add_background(screen, rotation, Background::Blur);
+                assert!(result.is_ok_and(|image| image.dimensions() == screen));
+            }
+        }
+    }
 }
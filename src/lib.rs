@@ -2,12 +2,26 @@
 //!
 //! syno_photo_frame is a full-screen slideshow app for Synology Photos and Immich albums
 
-pub use {api_client::LoginError, rand::RandomImpl};
+pub use {
+    api_client::{LoginError, ShareLinkExpired},
+    control::{Command, Control, PinnedPhoto},
+    metadata::CurrentPhoto,
+    rand::RandomImpl,
+};
 
 use std::{
+    collections::VecDeque,
     error::Error,
     fmt::{Display, Formatter},
-    sync::mpsc::{self, Receiver, SyncSender},
+    fs::File,
+    io::BufWriter,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+        Arc, Mutex,
+    },
     thread::{self, Scope, ScopedJoinHandle},
     time::Duration,
 };
@@ -15,30 +29,60 @@ use std::{
 #[cfg(not(test))]
 use std::{thread::sleep as thread_sleep, time::Instant};
 #[cfg(test)]
-use {mock_instant::Instant, test_helpers::fake_sleep as thread_sleep};
+use {
+    mock_instant::Instant,
+    test_helpers::{fake_local_now as local_now, fake_sleep as thread_sleep},
+};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
+use bytes::Bytes;
 
 use crate::{
-    api_client::{immich_client::ImmichApiClient, syno_client::SynoApiClient, ApiClient},
-    cli::{Backend, Cli},
-    http::{CookieStore, HttpClient},
-    img::{DynamicImage, Framed},
+    api_client::{
+        immich_client::ImmichApiClient, local_client::LocalApiClient,
+        manifest_client::ManifestApiClient, syno_client::SynoApiClient,
+        webdav_client::WebDavApiClient, ApiClient,
+    },
+    cli::{Backend, Cli, OnError, Transition},
+    clock::ClockOverlay,
+    grid::AlignmentGrid,
+    hook::PhotoHook,
+    http::{CookieStore, HttpClient, Url},
+    img::{ColorTransform, DynamicImage, Framed, GammaCorrection},
+    metadata::{Metadata, PhotoInfo},
+    metadata_cache::MetadataCache,
+    photo_cache::{CacheSettings, PhotoCache},
+    photo_filter::{FileNameFilter, PhotoFilter},
     rand::Random,
-    sdl::{Sdl, TextureIndex},
-    slideshow::Slideshow,
+    render::{Color, Key, Rect, Renderer, TextureIndex},
+    slideshow::{retry_transient, SelectedPhoto, Slideshow},
     update::UpdateNotification,
 };
 
 pub mod cli;
+pub mod control;
+pub mod display_power;
 pub mod http;
 pub mod logging;
+pub mod metadata;
+pub mod mqtt;
+pub mod remote;
+pub mod render;
+#[cfg(feature = "sdl")]
 pub mod sdl;
 
 mod api_client;
 mod api_crates;
 mod asset;
+mod clock;
+mod env;
+mod grid;
+mod hook;
 mod img;
+mod metadata_cache;
+mod photo_cache;
+mod photo_filter;
+mod preview;
 mod rand;
 mod slideshow;
 mod transition;
@@ -51,168 +95,574 @@ mod test_helpers;
 pub fn run<H, R>(
     cli: &Cli,
     (http_client, cookie_store): (&H, &impl CookieStore),
-    sdl: &mut impl Sdl,
+    renderer: &mut impl Renderer,
     random: R,
+    control: Control,
     installed_version: &str,
 ) -> Result<()>
 where
     H: HttpClient + Sync,
-    R: Random + Send,
+    R: Random + Send + Clone,
 {
-    let current_image = show_welcome_screen(cli, sdl)?;
+    let current_image = show_welcome_screen(cli, renderer)?;
+    if let (Some(id), Ok(mut pinned_photo)) = (&cli.pin, control.pinned_photo.lock()) {
+        pinned_photo.pin(id);
+    }
 
     thread::scope::<'_, _, Result<()>>(|thread_scope| {
         let (update_check_sender, update_check_receiver) = mpsc::sync_channel(1);
+        let (stop_update_check_sender, stop_update_check_receiver) = mpsc::channel();
         if !cli.disable_update_check {
             update::check_for_updates_thread(
                 http_client,
                 installed_version,
+                Duration::from_secs(cli.update_check_interval_hours.saturating_mul(60 * 60)),
                 thread_scope,
                 update_check_sender,
+                stop_update_check_receiver,
             );
         }
 
-        select_backend_and_start_slideshow(
+        let result = select_backend_and_start_slideshow(
             cli,
             (http_client, cookie_store),
-            sdl,
+            renderer,
             random,
+            control,
             update_check_receiver,
             current_image,
-        )
+        );
+        /* Wakes the update check thread up, so that the scope does not wait for the next check */
+        drop(stop_update_check_sender);
+        result
     })
 }
 
-fn show_welcome_screen(cli: &Cli, sdl: &mut impl Sdl) -> Result<DynamicImage> {
+fn show_welcome_screen(cli: &Cli, renderer: &mut impl Renderer) -> Result<DynamicImage> {
     let welcome_img = match &cli.splash {
-        None => asset::welcome_screen(sdl.size(), cli.rotation)?,
+        None => asset::welcome_screen(renderer.size(), cli.rotation)?,
         Some(path) => {
-            let (w, h) = sdl.size();
+            let (w, h) = renderer.size();
             match img::open(path) {
                 Ok(image) => image.resize_exact(w, h, image::imageops::FilterType::Nearest),
                 Err(error) => {
                     log::error!("Splashscreen {}: {error}", path.to_string_lossy());
-                    asset::welcome_screen(sdl.size(), cli.rotation)?
+                    asset::welcome_screen(renderer.size(), cli.rotation)?
                 }
             }
         }
     };
-    sdl.update_texture(welcome_img.as_bytes(), TextureIndex::Current)?;
-    sdl.copy_texture_to_canvas(TextureIndex::Current)?;
-    sdl.present_canvas();
+    renderer.update_texture(welcome_img.as_bytes(), TextureIndex::Current)?;
+    if cli.startup_fade.is_zero() {
+        renderer.copy_texture_to_canvas(TextureIndex::Current)?;
+        renderer.present_canvas();
+    } else {
+        transition::fade_in_from_black(renderer, cli.startup_fade)?;
+    }
     Ok(welcome_img)
 }
 
 fn select_backend_and_start_slideshow<H, R>(
     cli: &Cli,
     (http_client, cookie_store): (&H, &impl CookieStore),
-    sdl: &mut impl Sdl,
+    renderer: &mut impl Renderer,
     random: R,
+    control: Control,
     update_check_receiver: Receiver<bool>,
     current_image: DynamicImage,
 ) -> Result<()>
 where
     H: HttpClient + Sync,
-    R: Random + Send,
+    R: Random + Send + Clone,
 {
-    let backend = if matches!(cli.backend, Backend::Auto) {
-        api_client::detect_backend(&cli.share_link)?
-    } else {
-        cli.backend
-    };
+    let backend = selected_backend(cli)?;
     match backend {
         Backend::Synology => slideshow_loop(
             cli,
-            SynoApiClient::build(http_client, cookie_store, &cli.share_link)?
-                .with_password(&cli.password),
-            sdl,
+            SynoApiClient::build(http_client, cookie_store, required_share_link(cli)?)?
+                .with_password(&cli.password)
+                .with_album_name(&cli.album_name)
+                .with_sharing_header(&cli.syno_sharing_header),
+            renderer,
             random,
+            control,
             update_check_receiver,
             current_image,
         ),
         Backend::Immich => slideshow_loop(
             cli,
-            ImmichApiClient::build(http_client, &cli.share_link)?.with_password(&cli.password),
-            sdl,
+            ImmichApiClient::build(http_client, required_share_link(cli)?)?
+                .with_password(&cli.password),
+            renderer,
+            random,
+            control,
+            update_check_receiver,
+            current_image,
+        ),
+        Backend::Manifest => slideshow_loop(
+            cli,
+            ManifestApiClient::build(required_manifest(cli)?)?
+                .with_date_source(cli.local_date_source),
+            renderer,
+            random,
+            control,
+            update_check_receiver,
+            current_image,
+        ),
+        Backend::WebDav => slideshow_loop(
+            cli,
+            WebDavApiClient::build(http_client, required_share_link(cli)?)?,
+            renderer,
+            random,
+            control,
+            update_check_receiver,
+            current_image,
+        ),
+        Backend::Local => slideshow_loop(
+            cli,
+            LocalApiClient::build(required_share_link(cli)?)?
+                .with_date_source(cli.local_date_source),
+            renderer,
             random,
+            control,
             update_check_receiver,
             current_image,
         ),
-        Backend::Auto => unreachable!(),
+        Backend::Auto => bail!("Backend could not be detected"),
+    }
+}
+
+/// Writes metadata of all album photos to a JSON file at `path`, without displaying the slideshow
+pub fn dump_metadata<H: HttpClient>(
+    cli: &Cli,
+    (http_client, cookie_store): (&H, &impl CookieStore),
+    path: &Path,
+) -> Result<()> {
+    let file =
+        File::create(path).map_err(|error| anyhow!("{}: {error}", path.to_string_lossy()))?;
+    let writer = BufWriter::new(file);
+    let sort_by = cli.order.into();
+    match selected_backend(cli)? {
+        Backend::Synology => metadata::write_json(
+            &SynoApiClient::build(http_client, cookie_store, required_share_link(cli)?)?
+                .with_password(&cli.password)
+                .with_album_name(&cli.album_name)
+                .with_sharing_header(&cli.syno_sharing_header),
+            sort_by,
+            writer,
+        ),
+        Backend::Immich => metadata::write_json(
+            &ImmichApiClient::build(http_client, required_share_link(cli)?)?
+                .with_password(&cli.password),
+            sort_by,
+            writer,
+        ),
+        Backend::Manifest => metadata::write_json(
+            &ManifestApiClient::build(required_manifest(cli)?)?
+                .with_date_source(cli.local_date_source),
+            sort_by,
+            writer,
+        ),
+        Backend::WebDav => metadata::write_json(
+            &WebDavApiClient::build(http_client, required_share_link(cli)?)?,
+            sort_by,
+            writer,
+        ),
+        Backend::Local => metadata::write_json(
+            &LocalApiClient::build(required_share_link(cli)?)?
+                .with_date_source(cli.local_date_source),
+            sort_by,
+            writer,
+        ),
+        Backend::Auto => bail!("Backend could not be detected"),
+    }
+}
+
+/// Resolves [Backend::Auto] to the actual backend
+fn selected_backend(cli: &Cli) -> Result<Backend> {
+    match (cli.backend, &cli.manifest, &cli.share_link) {
+        (Backend::Auto, Some(_), _) => Ok(Backend::Manifest),
+        (Backend::Auto, None, Some(share_link)) => api_client::detect_backend(share_link),
+        (Backend::Auto, None, None) => bail!("either a share link or --manifest is required"),
+        (backend, _, _) => Ok(backend),
+    }
+}
+
+fn required_manifest(cli: &Cli) -> Result<&Path> {
+    match &cli.manifest {
+        Some(manifest) => Ok(manifest),
+        None => bail!("Manifest backend requires the --manifest option"),
+    }
+}
+
+fn required_share_link(cli: &Cli) -> Result<&Url> {
+    match &cli.share_link {
+        Some(share_link) => Ok(share_link),
+        None => bail!(
+            "Share link is required when using {:?} backend",
+            cli.backend
+        ),
     }
 }
 
 fn slideshow_loop<A, R>(
     cli: &Cli,
     api_client: A,
-    sdl: &mut impl Sdl,
+    renderer: &mut impl Renderer,
     random: R,
+    control: Control,
     update_check_receiver: Receiver<bool>,
     mut current_image: DynamicImage,
 ) -> Result<()>
 where
-    A: ApiClient + Send,
-    R: Random + Send,
+    A: ApiClient + Send + Sync,
+    R: Random + Send + Clone,
 {
-    /* Load the first photo as soon as it's ready. */
-    let mut last_change = Instant::now() - cli.photo_change_interval;
-    let screen_size = sdl.size();
+    /* Load the first photo as soon as it's ready, but not before --startup-settle-ms passes */
+    let mut last_change = Instant::now() - cli.photo_change_interval + cli.startup_settle;
+    /* Cleared once anything replaces the welcome screen */
+    let mut startup_deadline = cli.startup_timeout.map(|timeout| Instant::now() + timeout);
+    let screen_size = renderer.size();
     let mut update_notification = UpdateNotification::new(screen_size, cli.rotation)?;
+    /* Loaded only when needed, to not keep another full screen image in memory */
+    let error_screen =
+        || asset::error_screen(cli.error_image.as_deref(), screen_size, cli.rotation);
+    let mut alignment_grid = AlignmentGrid::new(cli.alignment_grid);
+    let mut clock = cli
+        .show_clock
+        .then(|| ClockOverlay::new(cli.clock_corner, cli.clock_format, cli.rotation));
+    let preview = cli
+        .show_preview
+        .then(|| preview::preview_rect(screen_size, cli.rotation));
+    /* With --show-preview, the next photo is received as soon as it's ready so that it can be
+     * previewed from the next texture */
+    let mut upcoming_photo = None;
+    let mut is_preview_ready = false;
     let (photo_sender, photo_receiver) = mpsc::sync_channel(1);
+    let mut photo_hook = cli
+        .on_photo
+        .as_deref()
+        .map(|command| PhotoHook::new(command).with_info_format(cli.info_format.as_deref()));
+    /* QR code linking to the album is displayed in place of a photo every --show-album-qr photos */
+    let album_qr = match (cli.show_album_qr, &cli.share_link) {
+        (Some(every), Some(share_link)) => Some((
+            every,
+            asset::album_qr(share_link)?.fit_to_screen_and_add_background(
+                screen_size,
+                cli.rotation,
+                cli.background,
+            )?,
+        )),
+        _ => None,
+    };
+    let mut photos_since_album_qr = 0;
+    /* Version of the pin whose photo is on screen, see PinnedPhoto */
+    let mut displayed_pin = None;
+    /* Slideshow photos received while waiting for a pinned photo, displayed after unpinning so
+     * that the slideshow resumes where it left off */
+    let mut held_back_photos = VecDeque::new();
+    /* Recently displayed photos, oldest first, displayed again when going back with the left
+     * arrow key */
+    let mut history = VecDeque::new();
+    let mut is_going_back = false;
+    /* Set by arrow keys or remote control to change the photo without waiting for the interval to
+     * pass */
+    let mut change_requested = false;
+    let mut paused_since = None;
+    /* Counts slide transitions, so that --slide-direction alternate can alternate */
+    let mut slides_played = 0;
+    /* Set while the screen is black outside of --active-hours */
+    let mut is_blanked = false;
     const LOOP_SLEEP_DURATION: Duration = Duration::from_millis(100);
+    /* Each one is a full screen image, so keep it short */
+    const HISTORY_LEN: usize = 3;
+
+    /* Dropped when the loop ends, so that photo_fetcher_thread does not keep the app from quitting
+     * while waiting for --max-bandwidth-mb-per-hour */
+    let (stop_sender, stop_receiver) = mpsc::channel();
+    /* Forwards Command::Reload to photo_fetcher_thread, which owns the slideshow */
+    let (reload_sender, reload_receiver) = mpsc::channel();
 
     thread::scope::<'_, _, Result<()>>(|thread_scope| {
+        let _stop_sender = stop_sender;
         photo_fetcher_thread(
             cli,
             api_client,
             screen_size,
-            random,
+            /* The other one draws random transitions */
+            random.clone(),
+            control.clone(),
             thread_scope,
-            photo_sender,
+            (photo_sender, stop_receiver, reload_receiver),
         )?;
 
         let loop_result = loop {
-            sdl.handle_quit_event()?;
+            renderer.handle_quit_event()?;
+            for command in control.take_commands() {
+                match command {
+                    Command::Next => change_requested = true,
+                    Command::Pause => control.set_paused(true),
+                    Command::Resume => control.set_paused(false),
+                    Command::TogglePause => control.set_paused(!control.is_paused()),
+                    Command::Reload => {
+                        /* Fails only once the fetcher thread has ended */
+                        let _ = reload_sender.send(());
+                    }
+                    Command::Quit => return Err(QuitEvent.into()),
+                }
+            }
+            let pressed_keys = renderer.pressed_keys();
+            alignment_grid.handle_keys(&pressed_keys);
+            for key in pressed_keys {
+                match key {
+                    Key::Right => change_requested = true,
+                    Key::Left if !is_going_back => {
+                        if let Some(previous_photo) = history.pop_back() {
+                            /* The current photo is displayed again after the previous one, then
+                             * the slideshow continues where it left off */
+                            let current_info = match control.current_photo.lock() {
+                                Ok(current_photo) => current_photo.info.clone(),
+                                Err(_) => None,
+                            };
+                            if let Some(info) = current_info {
+                                held_back_photos.push_front(Ok((current_image.clone(), info)));
+                            }
+                            held_back_photos.push_front(Ok(previous_photo));
+                            is_going_back = true;
+                            change_requested = true;
+                            is_preview_ready = false;
+                        }
+                    }
+                    Key::Char(' ') => control.send(Command::TogglePause),
+                    Key::Char('r') => control.send(Command::Reload),
+                    _ => {}
+                }
+            }
+
+            if cli
+                .active_hours
+                .is_some_and(|active_hours| !active_hours.contains(local_now().time()))
+            {
+                if !is_blanked {
+                    renderer.fill_canvas(Color::rgba(0, 0, 0, u8::MAX))?;
+                    renderer.present_canvas();
+                    is_blanked = true;
+                }
+                /* Like pausing, so that the slideshow resumes where it left off */
+                paused_since.get_or_insert_with(Instant::now);
+                thread_sleep(LOOP_SLEEP_DURATION);
+                continue;
+            }
+            if mem::take(&mut is_blanked) {
+                renderer.copy_texture_to_canvas(TextureIndex::Current)?;
+                renderer.present_canvas();
+                invalidate_clock(&mut clock);
+            }
 
             if let Ok(true) = update_check_receiver.try_recv() {
                 /* Overlay a notification on the currently displayed image when an update was
                  * detected */
                 update_notification.is_visible = true;
-                update_notification.show_on_current_image(&mut current_image, sdl)?;
+                update_notification.show_on_current_image(&mut current_image, renderer)?;
+                invalidate_clock(&mut clock);
             }
 
-            let elapsed_display_duration = Instant::now() - last_change;
-            if elapsed_display_duration < cli.photo_change_interval {
+            draw_overlays(
+                renderer,
+                &mut alignment_grid,
+                preview.filter(|_| is_preview_ready),
+                clock.as_mut(),
+            )?;
+
+            if (control.is_paused() || control.is_display_off()) && !change_requested {
+                /* Photo fetcher thread blocks once the next photo is ready, and the slideshow
+                 * continues with it when resumed */
+                paused_since.get_or_insert_with(Instant::now);
                 thread_sleep(LOOP_SLEEP_DURATION);
                 continue;
             }
+            if let Some(paused_at) = paused_since.take() {
+                /* Time spent paused does not count towards the display interval */
+                last_change += Instant::now() - paused_at;
+            }
+
+            let pin = pinned_photo_version(&control.pinned_photo);
+            match pin {
+                Some(_) if pin == displayed_pin => {
+                    /* Pinned photo stays on screen until unpinned */
+                    thread_sleep(LOOP_SLEEP_DURATION);
+                    continue;
+                }
+                /* Newly pinned photo is displayed as soon as it's ready */
+                Some(_) => {}
+                None => {
+                    if displayed_pin.take().is_some() {
+                        /* Resume the slideshow right after unpinning */
+                        last_change = Instant::now() - cli.photo_change_interval;
+                    }
+                    let elapsed_display_duration = Instant::now() - last_change;
+                    if elapsed_display_duration < cli.photo_change_interval && !change_requested {
+                        let is_album_qr_due = album_qr
+                            .as_ref()
+                            .is_some_and(|(every, _)| photos_since_album_qr >= *every);
+                        if preview.is_some()
+                            && upcoming_photo.is_none()
+                            && held_back_photos.is_empty()
+                            && !is_album_qr_due
+                        {
+                            if let Ok(fetched_photo) = photo_receiver.try_recv() {
+                                if let FetchedPhoto::Next(Ok((image, _))) = &fetched_photo {
+                                    renderer
+                                        .update_texture(image.as_bytes(), TextureIndex::Next)?;
+                                    is_preview_ready = true;
+                                }
+                                upcoming_photo = Some(fetched_photo);
+                            }
+                        }
+                        thread_sleep(LOOP_SLEEP_DURATION);
+                        continue;
+                    }
+                }
+            }
 
-            if let Ok(next_photo_result) = photo_receiver.try_recv() {
-                let mut next_image = match next_photo_result {
-                    Ok(photo) => photo,
-                    Err(error) if error.is::<LoginError>() => {
-                        /* Login error terminates the main thread loop */
-                        break Err(error);
+            let next_photo_result = match &album_qr {
+                Some((every, qr_image)) if pin.is_none() && photos_since_album_qr >= *every => {
+                    photos_since_album_qr = 0;
+                    Ok((qr_image.clone(), None))
+                }
+                _ => {
+                    let fetched_photo = match held_back_photos.pop_front() {
+                        Some(photo_result) if pin.is_none() => Ok(FetchedPhoto::Next(photo_result)),
+                        held_back => {
+                            if let Some(photo_result) = held_back {
+                                held_back_photos.push_front(photo_result);
+                            }
+                            upcoming_photo
+                                .take()
+                                .map_or_else(|| photo_receiver.try_recv(), Ok)
+                        }
+                    };
+                    match fetched_photo {
+                        Ok(FetchedPhoto::Next(photo_result)) if pin.is_some() => {
+                            held_back_photos.push_back(photo_result);
+                            continue;
+                        }
+                        Ok(FetchedPhoto::Next(photo_result)) => {
+                            photos_since_album_qr += 1;
+                            photo_result.map(|(photo, info)| (photo, Some(info)))
+                        }
+                        Ok(FetchedPhoto::Pinned(version, photo_result)) if pin == Some(version) => {
+                            displayed_pin = pin;
+                            photo_result.map(|(photo, info)| (photo, Some(info)))
+                        }
+                        /* Fetched for a pin which has changed since */
+                        Ok(FetchedPhoto::Pinned(..)) => continue,
+                        /* The last photo has been displayed for the full interval */
+                        Ok(FetchedPhoto::AlbumFinished) => break Ok(()),
+                        Err(TryRecvError::Disconnected) => {
+                            break Err(anyhow!("Photo fetcher thread terminated unexpectedly"));
+                        }
+                        Err(TryRecvError::Empty) => match startup_deadline {
+                            Some(deadline) if Instant::now() >= deadline => {
+                                log::error!(
+                                    "No photo received before --startup-timeout elapsed. Make \
+                                     sure the server is reachable and responding"
+                                );
+                                Ok((error_screen()?, None))
+                            }
+                            _ => {
+                                /* next photo is still being fetched and processed, we have to
+                                 * wait for it */
+                                thread_sleep(LOOP_SLEEP_DURATION);
+                                continue;
+                            }
+                        },
                     }
-                    Err(error) => {
-                        /* Any non-login error gets logged and an error screen is displayed. */
-                        log::error!("{error}");
-                        asset::error_screen(screen_size, cli.rotation)?
+                }
+            };
+            let (mut next_image, next_photo_info) = match next_photo_result {
+                Ok(next) => next,
+                Err(error) if error.is::<LoginError>() => {
+                    /* Login error terminates the main thread loop */
+                    break Err(error);
+                }
+                Err(error) => {
+                    /* Any non-login error gets logged and handled according to --on-error */
+                    log::error!("{error}");
+                    match cli.on_error {
+                        OnError::ErrorScreen => (error_screen()?, None),
+                        OnError::Hold => continue,
+                        OnError::Skip => {
+                            /* The failed photo's time slot passes with the previous photo on
+                             * screen */
+                            last_change = Instant::now();
+                            continue;
+                        }
                     }
-                };
-                if update_notification.is_visible {
-                    update_notification.overlay(&mut next_image);
                 }
-                sdl.update_texture(next_image.as_bytes(), TextureIndex::Next)?;
-                cli.transition.play(sdl)?;
+            };
+            if update_notification.is_visible {
+                update_notification.overlay(&mut next_image);
+            }
+            renderer.update_texture(next_image.as_bytes(), TextureIndex::Next)?;
+            let is_repeated = match (&next_photo_info, control.current_photo.lock()) {
+                (Some(next), Ok(current_photo)) => current_photo
+                    .info
+                    .as_ref()
+                    .is_some_and(|current| current.id == next.id),
+                _ => false,
+            };
+            let transition = cli.transition.pick(&cli.random_transitions, &random);
+            if is_repeated {
+                /* Same photo displayed again (e.g. album contains a single photo), fading it
+                 * into itself would be pointless */
+                Transition::None.play(renderer, cli.transition_duration)?;
+            } else if transition == Transition::Slide {
+                let to_left = cli.slide_direction.is_left(slides_played);
+                slides_played += 1;
+                transition::slide(renderer, to_left, cli.rotation, cli.transition_duration)?;
+            } else if transition == Transition::BlurBridge {
+                let bridge = img::blur_bridge(&current_image);
+                transition::blur_bridge(
+                    renderer,
+                    bridge.as_bytes(),
+                    next_image.as_bytes(),
+                    cli.transition_duration,
+                )?;
+            } else {
+                transition.play(renderer, cli.transition_duration)?;
+            }
 
-                last_change = Instant::now();
+            last_change = Instant::now();
+            startup_deadline = None;
+            is_preview_ready = false;
+            change_requested = false;
+            invalidate_clock(&mut clock);
 
-                sdl.swap_textures();
-                current_image = next_image;
-            } else {
-                /* next photo is still being fetched and processed, we have to wait for it */
-                thread_sleep(LOOP_SLEEP_DURATION);
+            renderer.swap_textures();
+            let previous_image = mem::replace(&mut current_image, next_image);
+            let previous_info = match control.current_photo.lock() {
+                Ok(mut current_photo) => {
+                    mem::replace(&mut current_photo.info, next_photo_info.clone())
+                }
+                Err(_) => None,
+            };
+            /* When going back, the replaced photo is already queued to be displayed again, and
+             * welcome or error screens are not kept */
+            if let (Some(info), false) = (previous_info, mem::take(&mut is_going_back)) {
+                history.push_back((previous_image, info));
+                if history.len() > HISTORY_LEN {
+                    history.pop_front();
+                }
+            }
+
+            if let Some(path) = &cli.export_current {
+                if let Err(error) = img::export(&current_image, path) {
+                    log::warn!("Export to {}: {error}", path.to_string_lossy());
+                }
+            }
+            if let (Some(hook), Some(info)) = (&mut photo_hook, &next_photo_info) {
+                hook.run(info, local_now().date());
             }
         };
         if loop_result.is_err() {
@@ -223,40 +673,407 @@ where
     })
 }
 
+/// Local wall-clock time, e.g. for --active-hours
+#[cfg(not(test))]
+fn local_now() -> chrono::NaiveDateTime {
+    chrono::Local::now().naive_local()
+}
+
+/// Redraws the current photo when any overlay (alignment grid, preview of the next photo, clock)
+/// is displayed over it
+fn draw_overlays(
+    renderer: &mut impl Renderer,
+    alignment_grid: &mut AlignmentGrid,
+    preview: Option<Rect>,
+    clock: Option<&mut ClockOverlay>,
+) -> Result<()> {
+    let now = local_now().time();
+    let clock_needs_redraw = clock.as_ref().is_some_and(|clock| clock.needs_redraw(now));
+    if !alignment_grid.needs_redraw() && preview.is_none() && !clock_needs_redraw {
+        return Ok(());
+    }
+    renderer.copy_texture_to_canvas(TextureIndex::Current)?;
+    alignment_grid.draw_lines(renderer)?;
+    if let Some(rect) = preview {
+        renderer.set_texture_alpha(u8::MAX, TextureIndex::Next);
+        renderer.copy_texture_to_canvas_rect(TextureIndex::Next, rect)?;
+    }
+    if let Some(clock) = clock {
+        clock.draw(renderer, now)?;
+    }
+    renderer.present_canvas();
+    Ok(())
+}
+
+/// Makes the clock (when shown) be redrawn, after the canvas has been drawn over without it
+fn invalidate_clock(clock: &mut Option<ClockOverlay>) {
+    if let Some(clock) = clock {
+        clock.invalidate();
+    }
+}
+
+/// Photo prepared for display by [photo_fetcher_thread]
+enum FetchedPhoto {
+    /// Next photo of the slideshow
+    Next(Result<(DynamicImage, PhotoInfo)>),
+    /// Pinned photo, together with the version of the pin it was fetched for
+    Pinned(u64, Result<(DynamicImage, PhotoInfo)>),
+    /// Every album photo has been sent once, with --once
+    AlbumFinished,
+}
+
+/// Returns version of the pin when a photo is pinned
+fn pinned_photo_version(pinned_photo: &Mutex<PinnedPhoto>) -> Option<u64> {
+    let pinned_photo = pinned_photo.lock().ok()?;
+    pinned_photo.id().map(|_| pinned_photo.version())
+}
+
 fn photo_fetcher_thread<'a, A, R>(
     cli: &'a Cli,
     api_client: A,
     screen_size: (u32, u32),
     random: R,
+    control: Control,
     thread_scope: &'a Scope<'a, '_>,
-    photo_sender: SyncSender<Result<DynamicImage>>,
+    (photo_sender, stop_receiver, reload_receiver): (
+        SyncSender<FetchedPhoto>,
+        Receiver<()>,
+        Receiver<()>,
+    ),
 ) -> Result<ScopedJoinHandle<'a, ()>>
 where
-    A: ApiClient + Send + 'a,
+    A: ApiClient + Send + Sync + 'a,
     R: Random + Send + 'a,
 {
-    if !api_client.is_logged_in() {
-        api_client.login()?;
-    }
-    let mut slideshow = Slideshow::new(api_client, random)
-        .with_ordering(cli.order)
-        .with_random_start(cli.random_start)
-        .with_source_size(cli.source_size);
-    Ok(thread_scope.spawn(move || loop {
-        let photo_result = slideshow
-            .get_next_photo()
-            .and_then(|bytes| load_image_from_memory(&bytes))
-            .map(|image| {
-                image.fit_to_screen_and_add_background(screen_size, cli.rotation, cli.background)
-            });
-        /* Blocks until photo is received by the main thread */
-        let send_result = photo_sender.send(photo_result);
-        if send_result.is_err() {
-            break;
+    let logo =
+        cli.logo
+            .as_ref()
+            .and_then(|path| match asset::logo(path, screen_size, cli.rotation) {
+                Ok(logo) => Some(logo),
+                Err(error) => {
+                    log::error!("Logo {}: {error}", path.to_string_lossy());
+                    None
+                }
+            });
+    let color_transform = cli
+        .icc_profile
+        .as_deref()
+        .and_then(img::load_color_transform);
+    let gamma_correction = (cli.gamma != 1.0).then(|| GammaCorrection::new(cli.gamma));
+    let photo_cache = cli
+        .cache_dir
+        .as_deref()
+        .and_then(|dir| open_photo_cache(dir, cli, screen_size))
+        .map(Arc::new);
+    let prepare_photo = {
+        let photo_cache = photo_cache.clone();
+        move |photo_result: Result<(PhotoData, PhotoInfo)>| {
+            let (photo_data, info) = photo_result?;
+            let mut image = match photo_data {
+                PhotoData::Cached(image) => image,
+                PhotoData::Fetched(bytes, cache_key) => {
+                    let image = load_image_from_memory(&bytes)?;
+                    let image = match cli.crop_aspect {
+                        Some(aspect) => image.crop_to_aspect(aspect),
+                        None => image,
+                    };
+                    let image = image.fit_or_fill_screen(
+                        screen_size,
+                        cli.rotation,
+                        cli.fit,
+                        cli.background,
+                        cli.max_letterbox_ratio,
+                    )?;
+                    if let (Some(photo_cache), Some(cache_key)) = (&photo_cache, cache_key) {
+                        photo_cache.put(&cache_key, &image);
+                    }
+                    image
+                }
+            };
+            finish_photo(
+                &mut image,
+                cli,
+                logo.as_ref(),
+                gamma_correction.as_ref(),
+                color_transform.as_deref(),
+            );
+            Ok((image, info))
+        }
+    };
+    Ok(thread_scope.spawn(move || {
+        /* Login happens here rather than in the main thread, so that a server which does not
+         * respond does not prevent --startup-timeout from kicking in */
+        if !api_client.is_logged_in() {
+            if let Err(error) = api_client.login() {
+                /* Login error terminates the main thread loop */
+                let _ = photo_sender.send(FetchedPhoto::Next(Err(error.into())));
+                return;
+            }
         }
+        let photo_cache = photo_cache.as_deref();
+        thread::scope(|fetch_scope| {
+            let mut slideshow = Slideshow::new(&api_client, random)
+                .with_ordering(cli.order)
+                .with_random_start(cli.random_start)
+                .with_no_repeat_window(cli.no_repeat_window)
+                .with_source_size(cli.source_size)
+                .with_large_album_warn(cli.large_album_warn)
+                .with_max_bytes_per_hour(
+                    cli.max_bandwidth_mb_per_hour
+                        .map(|megabytes| megabytes.saturating_mul(1024 * 1024)),
+                )
+                .with_stop_receiver(stop_receiver)
+                .with_file_name_filter(FileNameFilter::new(&cli.include_glob, &cli.exclude_glob))
+                .with_photo_filter(cli.photo_filter_script.as_deref().map(PhotoFilter::new))
+                .with_undated_photos(cli.undated_photos)
+                .with_skip_sleep(cli.skip_sleep)
+                .with_max_retries(cli.max_retries)
+                .with_list_retries(cli.list_retries)
+                .with_metadata_cache(
+                    cli.metadata_cache.as_deref().map(|path| {
+                        MetadataCache::new(path, cli.metadata_ttl, &cached_album_id(cli))
+                    }),
+                );
+            for intro in &cli.intro {
+                let fetched_photo = FetchedPhoto::Next(catch_panic(|| {
+                    prepare_photo(slideshow.get_intro_photo(intro).map(not_cached))
+                }));
+                if photo_sender.send(fetched_photo).is_err() {
+                    return;
+                }
+            }
+            let mut fetched_pin = None;
+            /* Album metadata for the next pass is fetched in the background, so that starting the
+             * next pass does not delay the photo */
+            let mut metadata_refresh: Option<ScopedJoinHandle<_>> = None;
+            /* Photos being fetched when --fetch-concurrency is above 1 or --cache-dir is set, in
+             * display order */
+            let mut photo_fetches = VecDeque::new();
+            loop {
+                /* With --once there is no next pass */
+                if let Some(sort_by) = slideshow.metadata_refresh_due().filter(|_| !cli.once) {
+                    let api_client = &api_client;
+                    metadata_refresh = Some(fetch_scope.spawn(move || {
+                        retry_transient(cli.list_retries, || api_client.get_photo_metadata(sort_by))
+                    }));
+                }
+                if metadata_refresh.as_ref().is_some_and(|refresh| {
+                    refresh.is_finished() || slideshow.is_waiting_for_next_pass()
+                }) {
+                    let photos = metadata_refresh
+                        .take()
+                        .expect("metadata refresh should be running")
+                        .join()
+                        .unwrap_or_else(|_| Err(anyhow!("Fetching album metadata panicked")));
+                    slideshow.set_next_pass(photos);
+                }
+
+                if reload_receiver.try_iter().count() > 0 {
+                    slideshow.request_reload();
+                }
+                let pin = match control.pinned_photo.lock() {
+                    Ok(pinned_photo) => pinned_photo
+                        .id()
+                        .map(|id| (id.to_string(), pinned_photo.version())),
+                    Err(_) => None,
+                };
+                /* Once the pinned photo is fetched, the next slideshow photo is prepared as usual,
+                 * so it's ready when the photo gets unpinned */
+                let fetched_photo = match pin {
+                    Some((id, version)) if fetched_pin != Some(version) => {
+                        fetched_pin = Some(version);
+                        FetchedPhoto::Pinned(
+                            version,
+                            catch_panic(|| {
+                                prepare_photo(slideshow.get_photo_by_id(&id).map(not_cached))
+                            }),
+                        )
+                    }
+                    /* Photos of the pass still being fetched concurrently are sent first */
+                    _ if cli.once && slideshow.is_pass_finished() && photo_fetches.is_empty() => {
+                        FetchedPhoto::AlbumFinished
+                    }
+                    _ => FetchedPhoto::Next(catch_panic(|| {
+                        /* Only photos selected from the display sequence ahead of fetching can be
+                         * looked up in --cache-dir */
+                        let photo_result = if cli.fetch_concurrency > 1 || photo_cache.is_some() {
+                            get_next_photo_concurrently(
+                                &mut slideshow,
+                                (&api_client, cli),
+                                photo_cache,
+                                &mut photo_fetches,
+                                fetch_scope,
+                            )
+                        } else {
+                            slideshow.get_next_photo().map(not_cached)
+                        };
+                        prepare_photo(photo_result)
+                    })),
+                };
+                let is_album_finished = matches!(fetched_photo, FetchedPhoto::AlbumFinished);
+                /* Blocks until photo is received by the main thread */
+                let send_result = photo_sender.send(fetched_photo);
+                if send_result.is_err() || is_album_finished {
+                    break;
+                }
+            }
+        })
     }))
 }
 
+/// Identifies the album in --metadata-cache, so that cached metadata of another album is not used
+fn cached_album_id(cli: &Cli) -> String {
+    let share_link = cli.share_link.as_ref().map(Url::to_string);
+    let manifest = cli
+        .manifest
+        .as_ref()
+        .map(|path| path.to_string_lossy().into_owned());
+    [share_link, cli.album_name.clone(), manifest]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Photo of the display sequence being fetched in a background thread, see
+/// [get_next_photo_concurrently]
+type PhotoFetch<'scope, P> = ScopedJoinHandle<'scope, (SelectedPhoto<P>, Result<PhotoData>)>;
+
+/// Fetches the next photo while up to --fetch-concurrency photos following it in the display
+/// sequence are fetched in the background. Photos found in `photo_cache` are not fetched. Photos
+/// are returned in the display sequence order
+fn get_next_photo_concurrently<'scope, A, R>(
+    slideshow: &mut Slideshow<&'scope A, R>,
+    (api_client, cli): (&'scope A, &Cli),
+    photo_cache: Option<&'scope PhotoCache>,
+    photo_fetches: &mut VecDeque<PhotoFetch<'scope, A::Photo>>,
+    fetch_scope: &'scope Scope<'scope, '_>,
+) -> Result<(PhotoData, PhotoInfo)>
+where
+    A: ApiClient + Sync,
+    R: Random,
+{
+    loop {
+        /* Once the display sequence ends, the next pass waits for the photos still being fetched,
+         * so that the background metadata refresh can finish in the meantime */
+        while photo_fetches.len() < cli.fetch_concurrency as usize
+            && (photo_fetches.is_empty() || !slideshow.is_waiting_for_next_pass())
+        {
+            let selected = slideshow.select_next_photo()?;
+            let (source_size, max_retries) = (cli.source_size, cli.max_retries);
+            photo_fetches.push_back(fetch_scope.spawn(move || {
+                let cache_key = photo_cache.map(|_| selected.photo.cache_key());
+                if let Some(image) = photo_cache
+                    .zip(cache_key.as_deref())
+                    .and_then(|(photo_cache, cache_key)| photo_cache.get(cache_key))
+                {
+                    return (selected, Ok(PhotoData::Cached(image)));
+                }
+                let photo_bytes_result = retry_transient(max_retries, || {
+                    api_client.get_photo_bytes(&selected.photo, source_size)
+                });
+                (
+                    selected,
+                    photo_bytes_result.map(|bytes| PhotoData::Fetched(bytes, cache_key)),
+                )
+            }));
+        }
+        let (selected, photo_data_result) = photo_fetches
+            .pop_front()
+            .expect("photo fetches should not be empty")
+            .join()
+            .map_err(|_| anyhow!("Failed to fetch photo, fetching panicked"))?;
+        let (photo_bytes_result, cache_key) = match photo_data_result {
+            Ok(PhotoData::Cached(image)) => {
+                break Ok((
+                    PhotoData::Cached(image),
+                    slideshow.accept_cached_photo(selected),
+                ));
+            }
+            Ok(PhotoData::Fetched(bytes, cache_key)) => (Ok(bytes), cache_key),
+            Err(error) => (Err(error), None),
+        };
+        match slideshow.accept_fetched_photo(selected, photo_bytes_result)? {
+            Some((bytes, info)) => break Ok((PhotoData::Fetched(bytes, cache_key), info)),
+            None => {
+                /* Save on CPU and request flooding */
+                thread_sleep(cli.skip_sleep);
+            }
+        }
+    }
+}
+
+/// Photo data to be prepared for display by [photo_fetcher_thread]
+enum PhotoData {
+    /// Photo fetched from the server, together with the key to store it in --cache-dir under once
+    /// it is fitted to the screen
+    Fetched(Bytes, Option<String>),
+    /// Photo already fitted to the screen, loaded from --cache-dir
+    Cached(DynamicImage),
+}
+
+/// Photo fetched outside of --cache-dir
+fn not_cached((bytes, info): (Bytes, PhotoInfo)) -> (PhotoData, PhotoInfo) {
+    (PhotoData::Fetched(bytes, None), info)
+}
+
+/// Opens --cache-dir. The slideshow works without the cache when it cannot be opened
+fn open_photo_cache(dir: &Path, cli: &Cli, screen_size: (u32, u32)) -> Option<PhotoCache> {
+    let settings = CacheSettings {
+        screen_size,
+        rotation: format!("{:?}", cli.rotation),
+        processing: format!(
+            "{:?} {:?} {:?} {:?} {:?}",
+            cli.source_size, cli.crop_aspect, cli.fit, cli.background, cli.max_letterbox_ratio
+        ),
+    };
+    match PhotoCache::open(dir, cli.cache_max_mb.saturating_mul(1024 * 1024), &settings) {
+        Ok(photo_cache) => Some(photo_cache),
+        Err(error) => {
+            log::error!("Photo cache {}: {error}", dir.to_string_lossy());
+            None
+        }
+    }
+}
+
+/// Converts a panic (e.g. in a decoder choking on a malformed photo) to an error, so that the
+/// fetcher thread survives it and the main thread does not wait for a photo forever
+fn catch_panic<T>(f: impl FnOnce() -> Result<T>) -> Result<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow!(
+            "Failed to fetch photo, processing panicked: {message}"
+        ))
+    })
+}
+
+/// Applies effects and overlays to a photo fitted to the screen
+fn finish_photo(
+    image: &mut DynamicImage,
+    cli: &Cli,
+    logo: Option<&DynamicImage>,
+    gamma_correction: Option<&GammaCorrection>,
+    color_transform: Option<&(dyn ColorTransform + Send)>,
+) {
+    if cli.invert {
+        image.invert_colors();
+    }
+    if let Some(logo) = logo {
+        image.overlay_logo(logo, cli.logo_corner, cli.rotation);
+    }
+    if let Some(gamma_correction) = gamma_correction {
+        gamma_correction.apply(image);
+    }
+    /* Last, so that it applies to everything on screen */
+    if let Some(color_transform) = color_transform {
+        color_transform.transform(image);
+    }
+}
+
 fn load_image_from_memory(bytes: &[u8]) -> Result<DynamicImage> {
     img::load_from_memory(bytes)
         /* Synology Photos API may respond with a http OK code and a JSON containing an
@@ -285,7 +1102,10 @@ impl Error for QuitEvent {}
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
     use bytes::Bytes;
+    use chrono::NaiveDate;
     use mock_instant::MockClock;
     use syno_api::dto::{ApiResponse, Error, List};
 
@@ -294,7 +1114,8 @@ mod tests {
         api_client::syno_client::Login,
         cli::Parser,
         http::{Jar, MockHttpResponse, StatusCode},
-        sdl::MockSdl,
+        img::MockColorTransform,
+        render::MockRenderer,
         test_helpers::{rand::FakeRandom, MockHttpClient},
     };
 
@@ -325,12 +1146,16 @@ mod tests {
         /* Avoid overflow when setting initial last_change */
         const DISPLAY_INTERVAL: u64 = 30;
         MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
-        let mut sdl_stub = MockSdl::new().with_default_expectations();
-        /* Hack: Break the loop eventually in case of assertion failure */
-        sdl_stub
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        /* Hack: Break the loop eventually in case of assertion failure. Login runs in the
+         * fetcher thread, give it time to fail */
+        renderer_stub
             .expect_handle_quit_event()
             .times(..5000)
-            .returning(|| Ok(()));
+            .returning(|| {
+                std::thread::sleep(Duration::from_millis(1));
+                Ok(())
+            });
         let cli_command = format!(
             "syno-photo-frame {SHARE_LINK} \
              --interval {DISPLAY_INTERVAL} \
@@ -341,8 +1166,9 @@ mod tests {
         let result = run(
             &Cli::parse_from(cli_command.split(' ')),
             (&client_stub, &Jar::default()),
-            &mut sdl_stub,
+            &mut renderer_stub,
             FakeRandom::default(),
+            Control::default(),
             "1.2.3",
         );
 
@@ -363,12 +1189,16 @@ mod tests {
         /* Avoid overflow when setting initial last_change */
         const DISPLAY_INTERVAL: u64 = 30;
         MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
-        let mut sdl_stub = MockSdl::new().with_default_expectations();
-        /* Hack: Break the loop eventually in case of assertion failure */
-        sdl_stub
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        /* Hack: Break the loop eventually in case of assertion failure. Login runs in the
+         * fetcher thread, give it time to fail */
+        renderer_stub
             .expect_handle_quit_event()
             .times(..5000)
-            .returning(|| Ok(()));
+            .returning(|| {
+                std::thread::sleep(Duration::from_millis(1));
+                Ok(())
+            });
         let cli_command = format!(
             "syno-photo-frame http://fake.dsm.addr/aa/sharing/FakeSharingId \
             --interval {DISPLAY_INTERVAL} \
@@ -379,8 +1209,9 @@ mod tests {
         let result = run(
             &Cli::parse_from(cli_command.split(' ')),
             (&client_stub, &Jar::default()),
-            &mut sdl_stub,
+            &mut renderer_stub,
             FakeRandom::default(),
+            Control::default(),
             "1.2.3",
         );
 
@@ -437,20 +1268,23 @@ mod tests {
         /* Avoid overflow when setting initial last_change */
         const DISPLAY_INTERVAL: u64 = 30;
         MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
-        let mut sdl_stub = MockSdl::new();
+        let mut renderer_stub = MockRenderer::new();
         {
-            sdl_stub.expect_size().return_const((198, 102));
-            sdl_stub
+            renderer_stub.expect_pressed_keys().returning(Vec::new);
+            renderer_stub.expect_size().return_const((198, 102));
+            renderer_stub
                 .expect_copy_texture_to_canvas()
                 .returning(|_| Ok(()));
-            sdl_stub.expect_fill_canvas().returning(|_| Ok(()));
-            sdl_stub.expect_present_canvas().return_const(());
-            sdl_stub.expect_update_texture().returning(|_, _| Ok(()));
+            renderer_stub.expect_fill_canvas().returning(|_| Ok(()));
+            renderer_stub.expect_present_canvas().return_const(());
+            renderer_stub
+                .expect_update_texture()
+                .returning(|_, _| Ok(()));
         }
-        sdl_stub.expect_swap_textures().returning(|| {
+        renderer_stub.expect_swap_textures().returning(|| {
             MockClock::advance(Duration::from_secs(1));
         });
-        sdl_stub.expect_handle_quit_event().returning(|| {
+        renderer_stub.expect_handle_quit_event().returning(|| {
             /* Until swap_textures is called (with an error image) and advances the time, return
              * Ok. Afterward, break the loop with a simulated Quit event to finish the test */
             if MockClock::time() <= Duration::from_secs(DISPLAY_INTERVAL) {
@@ -471,8 +1305,9 @@ mod tests {
         let result = run(
             &Cli::parse_from(cli_command.split(' ')),
             (&client_stub, &Jar::default()),
-            &mut sdl_stub,
+            &mut renderer_stub,
             FakeRandom::default(),
+            Control::default(),
             "1.2.3",
         );
 
@@ -532,20 +1367,23 @@ mod tests {
         /* Avoid overflow when setting initial last_change */
         const DISPLAY_INTERVAL: u64 = 30;
         MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
-        let mut sdl_stub = MockSdl::new();
+        let mut renderer_stub = MockRenderer::new();
         {
-            sdl_stub.expect_size().return_const((198, 102));
-            sdl_stub
+            renderer_stub.expect_pressed_keys().returning(Vec::new);
+            renderer_stub.expect_size().return_const((198, 102));
+            renderer_stub
                 .expect_copy_texture_to_canvas()
                 .returning(|_| Ok(()));
-            sdl_stub.expect_fill_canvas().returning(|_| Ok(()));
-            sdl_stub.expect_present_canvas().return_const(());
-            sdl_stub.expect_update_texture().returning(|_, _| Ok(()));
+            renderer_stub.expect_fill_canvas().returning(|_| Ok(()));
+            renderer_stub.expect_present_canvas().return_const(());
+            renderer_stub
+                .expect_update_texture()
+                .returning(|_, _| Ok(()));
         }
-        sdl_stub.expect_swap_textures().returning(|| {
+        renderer_stub.expect_swap_textures().returning(|| {
             MockClock::advance(Duration::from_secs(1));
         });
-        sdl_stub.expect_handle_quit_event().returning(|| {
+        renderer_stub.expect_handle_quit_event().returning(|| {
             /* Until swap_textures is called (with an error image) and advances the time, return
              * Ok. Afterward, break the loop with a simulated Quit event to finish the test */
             if MockClock::time() <= Duration::from_secs(DISPLAY_INTERVAL) {
@@ -566,8 +1404,9 @@ mod tests {
         let result = run(
             &Cli::parse_from(cli_command.split(' ')),
             (&client_stub, &Jar::default()),
-            &mut sdl_stub,
+            &mut renderer_stub,
             FakeRandom::default(),
+            Control::default(),
             "1.2.3",
         );
 
@@ -577,13 +1416,1535 @@ mod tests {
         client_stub.checkpoint();
     }
 
-    impl MockSdl {
+    #[test]
+    fn current_photo_is_updated_after_swap() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(42, "photo42")],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning(|| {
+            MockClock::advance(Duration::from_secs(1));
+        });
+        renderer_stub.expect_handle_quit_event().returning(|| {
+            /* Break the loop with a simulated Quit event after the first swap */
+            if MockClock::time() <= Duration::from_secs(DISPLAY_INTERVAL) {
+                Ok(())
+            } else {
+                Err(QuitEvent)
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+        let control = Control::default();
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control.clone(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let current_photo = control.current_photo.lock().unwrap();
+        assert!(current_photo.info.is_some());
+        assert_eq!(current_photo.info.as_ref().unwrap().id, "42");
+    }
+
+    #[test]
+    fn when_export_current_is_set_then_displayed_image_is_written_after_swap() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(42, "photo42")],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning(|| {
+            MockClock::advance(Duration::from_secs(1));
+        });
+        renderer_stub.expect_handle_quit_event().returning(|| {
+            /* Break the loop with a simulated Quit event after the first swap */
+            if MockClock::time() <= Duration::from_secs(DISPLAY_INTERVAL) {
+                Ok(())
+            } else {
+                Err(QuitEvent)
+            }
+        });
+        let export_path = std::env::temp_dir().join(format!(
+            "syno-photo-frame-export-current-{}.png",
+            std::process::id()
+        ));
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg \
+            --export-current {}",
+            export_path.to_string_lossy()
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            Control::default(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let exported = image::open(&export_path);
+        std::fs::remove_file(&export_path).unwrap();
+        let exported = exported.unwrap();
+        assert_eq!((exported.width(), exported.height()), (198, 102));
+    }
+
+    #[test]
+    fn when_cache_dir_is_set_then_cached_photo_is_not_fetched_again() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        let cache_dir = std::env::temp_dir().join(format!(
+            "syno-photo-frame-lib-photo-cache-{}",
+            std::process::id()
+        ));
+        let photo_fetches = Arc::new(AtomicUsize::new(0));
+        let run_slideshow = || {
+            let mut client_stub = MockHttpClient::new();
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+                .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .returning(|_, _, _| {
+                    Ok(test_helpers::new_success_response_with_json(List {
+                        list: vec![test_helpers::new_photo_dto(42, "photo42")],
+                    }))
+                });
+            let photo_fetches = Arc::clone(&photo_fetches);
+            client_stub.expect_get().returning(move |_, _| {
+                photo_fetches.fetch_add(1, Ordering::Relaxed);
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+                Ok(get_photo_response)
+            });
+            MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+            let mut renderer_stub = MockRenderer::new().with_default_expectations();
+            renderer_stub.expect_swap_textures().returning(|| {
+                MockClock::advance(Duration::from_secs(1));
+            });
+            renderer_stub.expect_handle_quit_event().returning(|| {
+                /* Break the loop with a simulated Quit event after the first swap */
+                if MockClock::time() <= Duration::from_secs(DISPLAY_INTERVAL) {
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            });
+            let cli_command = format!(
+                "syno-photo-frame {SHARE_LINK} \
+                --interval {DISPLAY_INTERVAL} \
+                --disable-update-check \
+                --transition none \
+                --splash assets/test_loading.jpeg \
+                --cache-dir {}",
+                cache_dir.to_string_lossy()
+            );
+            run(
+                &Cli::parse_from(cli_command.split(' ')),
+                (&client_stub, &Jar::default()),
+                &mut renderer_stub,
+                FakeRandom::default(),
+                Control::default(),
+                "1.2.3",
+            )
+        };
+
+        let results = [run_slideshow(), run_slideshow()];
+
+        std::fs::remove_dir_all(&cache_dir).unwrap();
+        assert!(results
+            .into_iter()
+            .all(|result| result.is_err_and(|e| e.is::<QuitEvent>())));
+        /* The only photo of the album is fetched once, and then displayed from the cache in both
+         * runs */
+        assert_eq!(photo_fetches.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn pinned_photo_interrupts_slideshow_until_unpinned() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                        test_helpers::new_photo_dto(3, "photo3"),
+                    ],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let control = Control::default();
+        /* Ids of photos replaced on screen by each swap. The clock is never advanced, so photos
+         * change only because of pinning and unpinning */
+        let replaced_ids = Arc::new(Mutex::new(vec![]));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning({
+            let control = control.clone();
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                let current_photo = control.current_photo.lock().unwrap();
+                let mut replaced_ids = replaced_ids.lock().unwrap();
+                replaced_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+                let mut pinned_photo = control.pinned_photo.lock().unwrap();
+                match replaced_ids.len() {
+                    1 => pinned_photo.pin("3"),
+                    2 => pinned_photo.unpin(),
+                    _ => {}
+                }
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                /* Break the loop with a simulated Quit event after the third swap */
+                if replaced_ids.lock().unwrap().len() < 3 {
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control.clone(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let current_photo = control.current_photo.lock().unwrap();
+        let mut displayed_ids = replaced_ids.lock().unwrap().clone();
+        displayed_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+        /* Welcome screen, first photo, pinned photo, and the slideshow resumes with the second
+         * photo */
+        assert_eq!(
+            displayed_ids,
+            [None, Some("1"), Some("3"), Some("2")].map(|id| id.map(str::to_string))
+        );
+    }
+
+    #[test]
+    fn when_photos_are_fetched_concurrently_then_they_are_displayed_in_date_order() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                        test_helpers::new_photo_dto(3, "photo3"),
+                    ],
+                }))
+            });
+        client_stub.expect_get().returning(|_, query| {
+            let id: u64 = query
+                .iter()
+                .find_map(|(name, value)| (*name == "id").then(|| value.parse().unwrap()))
+                .unwrap();
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response.expect_bytes().return_once(move || {
+                /* Photos earlier in the album take longer to download, so that fetches finish
+                 * in reverse order */
+                thread::sleep(Duration::from_millis((3 - id) * 50));
+                Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?))
+            });
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let control = Control::default();
+        /* Ids of photos replaced on screen by each swap */
+        let replaced_ids = Arc::new(Mutex::new(vec![]));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning({
+            let control = control.clone();
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                let current_photo = control.current_photo.lock().unwrap();
+                let mut replaced_ids = replaced_ids.lock().unwrap();
+                replaced_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+                MockClock::advance(Duration::from_secs(DISPLAY_INTERVAL));
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                /* Break the loop with a simulated Quit event after the fourth swap */
+                if replaced_ids.lock().unwrap().len() < 4 {
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --fetch-concurrency 2 \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control.clone(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let current_photo = control.current_photo.lock().unwrap();
+        let mut displayed_ids = replaced_ids.lock().unwrap().clone();
+        displayed_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+        /* Welcome screen, album photos in order, and the next pass starts with the first photo
+         * again */
+        assert_eq!(
+            displayed_ids,
+            [None, Some("1"), Some("2"), Some("3"), Some("1")].map(|id| id.map(str::to_string))
+        );
+    }
+
+    #[test]
+    fn when_once_is_set_then_loop_ends_after_album_is_displayed_once() {
+        fn test_case(fetch_concurrency: u32) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+            let mut client_mock = MockHttpClient::new();
+            client_mock
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+                .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+            client_mock
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .times(1)
+                .returning(|_, _, _| {
+                    Ok(test_helpers::new_success_response_with_json(List {
+                        list: vec![
+                            test_helpers::new_photo_dto(1, "photo1"),
+                            test_helpers::new_photo_dto(2, "photo2"),
+                        ],
+                    }))
+                });
+            client_mock.expect_get().times(2).returning(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+                Ok(get_photo_response)
+            });
+
+            /* Avoid overflow when setting initial last_change */
+            const DISPLAY_INTERVAL: u64 = 30;
+            MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+            let control = Control::default();
+            let displayed_ids = Arc::new(Mutex::new(vec![]));
+            let mut renderer_stub = MockRenderer::new().with_default_expectations();
+            renderer_stub.expect_swap_textures().returning(|| {
+                MockClock::advance(Duration::from_secs(DISPLAY_INTERVAL));
+            });
+            renderer_stub.expect_handle_quit_event().returning({
+                let control = control.clone();
+                let displayed_ids = Arc::clone(&displayed_ids);
+                move || {
+                    let current_photo = control.current_photo.lock().unwrap();
+                    let id = current_photo.info.as_ref().map(|info| info.id.clone());
+                    let mut displayed_ids = displayed_ids.lock().unwrap();
+                    if displayed_ids.last() != Some(&id) {
+                        displayed_ids.push(id);
+                    }
+                    /* Safety net in case the loop does not end */
+                    if displayed_ids.len() < 10 {
+                        Ok(())
+                    } else {
+                        Err(QuitEvent)
+                    }
+                }
+            });
+            let cli_command = format!(
+                "syno-photo-frame {SHARE_LINK} \
+                --interval {DISPLAY_INTERVAL} \
+                --once \
+                --fetch-concurrency {fetch_concurrency} \
+                --disable-update-check \
+                --transition none \
+                --splash assets/test_loading.jpeg"
+            );
+
+            let result = run(
+                &Cli::parse_from(cli_command.split(' ')),
+                (&client_mock, &Jar::default()),
+                &mut renderer_stub,
+                FakeRandom::default(),
+                control,
+                "1.2.3",
+            );
+
+            assert!(result.is_ok());
+            assert_eq!(
+                *displayed_ids.lock().unwrap(),
+                [None, Some("1"), Some("2")].map(|id| id.map(str::to_string))
+            );
+        }
+
+        test_case(1);
+        test_case(2);
+    }
+
+    #[test]
+    fn intro_photos_are_displayed_first_and_once() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const INTRO_PATH: &str = "assets/test_exif.jpeg";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                    ],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let control = Control::default();
+        /* Ids of photos replaced on screen by each swap */
+        let replaced_ids = Arc::new(Mutex::new(vec![]));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning({
+            let control = control.clone();
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                let current_photo = control.current_photo.lock().unwrap();
+                let mut replaced_ids = replaced_ids.lock().unwrap();
+                replaced_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+                MockClock::advance(Duration::from_secs(DISPLAY_INTERVAL));
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                /* Break the loop with a simulated Quit event after the fifth swap */
+                if replaced_ids.lock().unwrap().len() < 5 {
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --intro {INTRO_PATH} \
+            --intro 2 \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control.clone(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let current_photo = control.current_photo.lock().unwrap();
+        let mut displayed_ids = replaced_ids.lock().unwrap().clone();
+        displayed_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+        /* Welcome screen, local and album intro photos, then two passes of the album */
+        assert_eq!(
+            displayed_ids,
+            [
+                None,
+                Some(INTRO_PATH),
+                Some("2"),
+                Some("1"),
+                Some("2"),
+                Some("1")
+            ]
+            .map(|id| id.map(str::to_string))
+        );
+    }
+
+    #[test]
+    fn arrow_keys_change_photo_immediately_and_go_back_to_previous_photo() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                        test_helpers::new_photo_dto(3, "photo3"),
+                    ],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change. The clock does not advance, so photos
+         * change only on key presses */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        const KEYS: [Key; 4] = [Key::Right, Key::Left, Key::Right, Key::Right];
+        let control = Control::default();
+        /* Ids of photos replaced on screen by each swap */
+        let replaced_ids = Arc::new(Mutex::new(vec![]));
+        let mut renderer_stub = MockRenderer::new();
+        renderer_stub.expect_size().return_const((198, 102));
+        renderer_stub
+            .expect_update_texture()
+            .returning(|_, _| Ok(()));
+        renderer_stub
+            .expect_copy_texture_to_canvas()
+            .returning(|_| Ok(()));
+        renderer_stub.expect_present_canvas().return_const(());
+        renderer_stub.expect_swap_textures().returning({
+            let control = control.clone();
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                let current_photo = control.current_photo.lock().unwrap();
+                let mut replaced_ids = replaced_ids.lock().unwrap();
+                replaced_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+            }
+        });
+        renderer_stub.expect_pressed_keys().returning({
+            let replaced_ids = Arc::clone(&replaced_ids);
+            let mut keys_pressed = 0;
+            move || {
+                /* One key press after each photo change, starting with the first photo */
+                let swaps = replaced_ids.lock().unwrap().len();
+                if keys_pressed < swaps && keys_pressed < KEYS.len() {
+                    keys_pressed += 1;
+                    vec![KEYS[keys_pressed - 1]]
+                } else {
+                    vec![]
+                }
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let replaced_ids = Arc::clone(&replaced_ids);
+            move || {
+                if replaced_ids.lock().unwrap().len() <= KEYS.len() {
+                    /* Give the fetcher thread a chance to prepare the next photo */
+                    std::thread::sleep(Duration::from_millis(5));
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control.clone(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let current_photo = control.current_photo.lock().unwrap();
+        let mut displayed_ids = replaced_ids.lock().unwrap().clone();
+        displayed_ids.push(current_photo.info.as_ref().map(|info| info.id.clone()));
+        /* Welcome screen, then next, previous, and next twice */
+        assert_eq!(
+            displayed_ids,
+            [None, Some("1"), Some("2"), Some("1"), Some("2"), Some("3")]
+                .map(|id| id.map(str::to_string))
+        );
+    }
+
+    #[test]
+    fn when_next_and_quit_are_requested_through_control_then_photo_changes_and_loop_ends() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                    ],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change. The clock does not advance, so photos
+         * change only on requests */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let control = Control::default();
+        let swaps = Arc::new(Mutex::new(0));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning({
+            let swaps = Arc::clone(&swaps);
+            move || *swaps.lock().unwrap() += 1
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let control = control.clone();
+            let swaps = Arc::clone(&swaps);
+            let mut requested_swaps = 0;
+            move || {
+                /* Welcome screen is replaced by the first photo without a request */
+                let swaps = *swaps.lock().unwrap();
+                if swaps == 2 {
+                    control.send(Command::Quit);
+                } else if swaps > requested_swaps {
+                    requested_swaps = swaps;
+                    control.send(Command::Next);
+                }
+                /* Give the fetcher thread a chance to prepare the next photo */
+                std::thread::sleep(Duration::from_millis(5));
+                Ok(())
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control.clone(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        assert_eq!(*swaps.lock().unwrap(), 2);
+        let current_photo = control.current_photo.lock().unwrap();
+        assert_eq!(current_photo.info.as_ref().unwrap().id, "2");
+    }
+
+    #[test]
+    fn when_same_photo_is_displayed_again_then_transition_is_skipped() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let crossfade_frames = Arc::new(Mutex::new(0));
+        /* Number of crossfade frames played before each swap */
+        let frames_at_swap = Arc::new(Mutex::new(vec![]));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_set_texture_alpha().returning({
+            let crossfade_frames = Arc::clone(&crossfade_frames);
+            move |_, _| {
+                *crossfade_frames.lock().unwrap() += 1;
+                MockClock::advance(Duration::from_millis(100));
+            }
+        });
+        renderer_stub.expect_swap_textures().returning({
+            let frames_at_swap = Arc::clone(&frames_at_swap);
+            move || {
+                frames_at_swap
+                    .lock()
+                    .unwrap()
+                    .push(*crossfade_frames.lock().unwrap());
+                MockClock::advance(Duration::from_secs(DISPLAY_INTERVAL));
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let frames_at_swap = Arc::clone(&frames_at_swap);
+            move || {
+                /* Break the loop with a simulated Quit event after the second swap */
+                if frames_at_swap.lock().unwrap().len() < 2 {
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition crossfade \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            Control::default(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let frames_at_swap = frames_at_swap.lock().unwrap();
+        /* Crossfade from the welcome screen to the photo, but not from the photo to itself */
+        assert!(frames_at_swap[0] > 0);
+        assert_eq!(frames_at_swap[1], frames_at_swap[0]);
+    }
+
+    #[test]
+    fn when_transition_is_random_then_effects_are_drawn_from_injected_random() {
+        /* Crossfade is played for the first photo only */
+        test_case(vec![0, 1], true, false);
+        /* Crossfade is played for the second photo only */
+        test_case(vec![1, 0], false, true);
+
+        fn test_case(random_sequence: Vec<u32>, expected_first: bool, expected_second: bool) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+            let mut client_stub = MockHttpClient::new();
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+                .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .returning(|_, _, _| {
+                    Ok(test_helpers::new_success_response_with_json(List {
+                        list: vec![
+                            test_helpers::new_photo_dto(1, "photo1"),
+                            test_helpers::new_photo_dto(2, "photo2"),
+                        ],
+                    }))
+                });
+            client_stub.expect_get().returning(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+                Ok(get_photo_response)
+            });
+
+            /* Avoid overflow when setting initial last_change */
+            const DISPLAY_INTERVAL: u64 = 30;
+            MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+            let crossfade_frames = Arc::new(Mutex::new(0));
+            /* Number of crossfade frames played before each swap */
+            let frames_at_swap = Arc::new(Mutex::new(vec![]));
+            let mut renderer_stub = MockRenderer::new().with_default_expectations();
+            renderer_stub.expect_set_texture_alpha().returning({
+                let crossfade_frames = Arc::clone(&crossfade_frames);
+                move |_, _| {
+                    *crossfade_frames.lock().unwrap() += 1;
+                    MockClock::advance(Duration::from_millis(100));
+                }
+            });
+            renderer_stub.expect_swap_textures().returning({
+                let frames_at_swap = Arc::clone(&frames_at_swap);
+                move || {
+                    frames_at_swap
+                        .lock()
+                        .unwrap()
+                        .push(*crossfade_frames.lock().unwrap());
+                    MockClock::advance(Duration::from_secs(DISPLAY_INTERVAL));
+                }
+            });
+            renderer_stub.expect_handle_quit_event().returning({
+                let frames_at_swap = Arc::clone(&frames_at_swap);
+                move || {
+                    /* Break the loop with a simulated Quit event after the second swap */
+                    if frames_at_swap.lock().unwrap().len() < 2 {
+                        Ok(())
+                    } else {
+                        Err(QuitEvent)
+                    }
+                }
+            });
+            let cli_command = format!(
+                "syno-photo-frame {SHARE_LINK} \
+                --interval {DISPLAY_INTERVAL} \
+                --disable-update-check \
+                --transition random \
+                --random-transitions crossfade,none \
+                --splash assets/test_loading.jpeg"
+            );
+
+            let result = run(
+                &Cli::parse_from(cli_command.split_whitespace()),
+                (&client_stub, &Jar::default()),
+                &mut renderer_stub,
+                FakeRandom::default().with_random_sequence(random_sequence),
+                Control::default(),
+                "1.2.3",
+            );
+
+            assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+            let frames_at_swap = frames_at_swap.lock().unwrap();
+            assert_eq!(frames_at_swap[0] > 0, expected_first);
+            assert_eq!(frames_at_swap[1] > frames_at_swap[0], expected_second);
+        }
+    }
+
+    #[test]
+    fn when_getting_photo_fails_then_loop_behaves_according_to_on_error() {
+        /* Hold displays the next photo right away, without waiting for the clock */
+        test_case("hold", false, 0, 1);
+        test_case("skip", true, 0, 1);
+        test_case("error-screen", true, 1, 2);
+
+        fn test_case(
+            on_error: &str,
+            advance_clock: bool,
+            expected_swaps_before_advance: usize,
+            expected_swaps: usize,
+        ) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+            let mut client_stub = MockHttpClient::new();
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+                .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .returning(|_, _, _| {
+                    Ok(test_helpers::new_success_response_with_json(List {
+                        list: vec![
+                            test_helpers::new_photo_dto(1, "bad_photo1"),
+                            test_helpers::new_photo_dto(2, "photo2"),
+                        ],
+                    }))
+                });
+            /* The first photo is requested again only after the main loop has taken its error
+             * from the channel */
+            let bad_photo_requests = Arc::new(Mutex::new(0));
+            client_stub
+                .expect_get()
+                .withf(|_, form| {
+                    test_helpers::is_get_photo_form(form, "FakeSharingId", "1", "bad_photo1", "xl")
+                })
+                .returning({
+                    let bad_photo_requests = Arc::clone(&bad_photo_requests);
+                    move |_, _| {
+                        *bad_photo_requests.lock().unwrap() += 1;
+                        let mut response = test_helpers::new_ok_response();
+                        response
+                            .expect_bytes()
+                            .return_once(|| Ok(Bytes::from("{ \"bad\": \"data\" }")));
+                        Ok(response)
+                    }
+                });
+            client_stub
+                .expect_get()
+                .withf(|_, form| {
+                    test_helpers::is_get_photo_form(form, "FakeSharingId", "2", "photo2", "xl")
+                })
+                .returning(|_, _| {
+                    let mut response = test_helpers::new_ok_response();
+                    response.expect_bytes().return_once(|| {
+                        Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?))
+                    });
+                    Ok(response)
+                });
+
+            /* Avoid overflow when setting initial last_change */
+            const DISPLAY_INTERVAL: u64 = 30;
+            MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+            let control = Control::default();
+            let swaps = Arc::new(Mutex::new(0));
+            let mut renderer_stub = MockRenderer::new().with_default_expectations();
+            renderer_stub.expect_swap_textures().returning({
+                let swaps = Arc::clone(&swaps);
+                move || {
+                    *swaps.lock().unwrap() += 1;
+                    MockClock::advance(Duration::from_secs(1));
+                }
+            });
+            let swaps_before_advance = Arc::new(Mutex::new(None));
+            renderer_stub.expect_handle_quit_event().returning({
+                let control = control.clone();
+                let swaps = Arc::clone(&swaps);
+                let swaps_before_advance = Arc::clone(&swaps_before_advance);
+                /* Hack: Break the loop eventually in case of assertion failure */
+                let started = std::time::Instant::now();
+                move || {
+                    let current_photo = control.current_photo.lock().unwrap();
+                    let current_id = current_photo.info.as_ref().map(|info| info.id.as_str());
+                    if current_id == Some("2") || started.elapsed() > Duration::from_secs(10) {
+                        return Err(QuitEvent);
+                    }
+                    let mut swaps_before_advance = swaps_before_advance.lock().unwrap();
+                    if advance_clock
+                        && swaps_before_advance.is_none()
+                        && *bad_photo_requests.lock().unwrap() >= 2
+                    {
+                        *swaps_before_advance = Some(*swaps.lock().unwrap());
+                        MockClock::advance(Duration::from_secs(DISPLAY_INTERVAL));
+                    }
+                    Ok(())
+                }
+            });
+            let cli_command = format!(
+                "syno-photo-frame {SHARE_LINK} \
+                --interval {DISPLAY_INTERVAL} \
+                --disable-update-check \
+                --transition none \
+                --on-error {on_error} \
+                --splash assets/test_loading.jpeg"
+            );
+
+            let result = run(
+                &Cli::parse_from(cli_command.split(' ')),
+                (&client_stub, &Jar::default()),
+                &mut renderer_stub,
+                FakeRandom::default(),
+                control.clone(),
+                "1.2.3",
+            );
+
+            assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+            let current_photo = control.current_photo.lock().unwrap();
+            assert_eq!(
+                current_photo.info.as_ref().map(|info| info.id.as_str()),
+                Some("2"),
+                "{on_error}"
+            );
+            if advance_clock {
+                assert_eq!(
+                    *swaps_before_advance.lock().unwrap(),
+                    Some(expected_swaps_before_advance),
+                    "{on_error}"
+                );
+            }
+            assert_eq!(*swaps.lock().unwrap(), expected_swaps, "{on_error}");
+        }
+    }
+
+    #[test]
+    fn first_photo_is_not_displayed_before_startup_settle_delay() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        const START: Duration = Duration::from_secs(DISPLAY_INTERVAL);
+        const SETTLE: Duration = Duration::from_millis(2500);
+        MockClock::set_time(START);
+        let first_swap_time = Arc::new(Mutex::new(None));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning({
+            let first_swap_time = Arc::clone(&first_swap_time);
+            move || {
+                first_swap_time
+                    .lock()
+                    .unwrap()
+                    .get_or_insert(MockClock::time());
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let first_swap_time = Arc::clone(&first_swap_time);
+            move || {
+                if first_swap_time.lock().unwrap().is_some() {
+                    return Err(QuitEvent);
+                }
+                /* Each loop iteration takes some time */
+                MockClock::advance(Duration::from_millis(100));
+                Ok(())
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --startup-settle-ms {} \
+            --splash assets/test_loading.jpeg",
+            SETTLE.as_millis()
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            Control::default(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        let first_swap_time = first_swap_time.lock().unwrap();
+        assert!(first_swap_time.is_some_and(|time| time >= START + SETTLE));
+    }
+
+    #[test]
+    fn when_paused_then_next_photo_is_not_displayed_until_resumed() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                }))
+            });
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+            Ok(get_photo_response)
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        const PAUSED_ITERATIONS: usize = 50;
+        let control = Control::default();
+        control.send(Command::Pause);
+        let iterations = Arc::new(Mutex::new(0));
+        let swap_iteration = Arc::new(Mutex::new(None));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning({
+            let iterations = Arc::clone(&iterations);
+            let swap_iteration = Arc::clone(&swap_iteration);
+            move || {
+                swap_iteration
+                    .lock()
+                    .unwrap()
+                    .get_or_insert(*iterations.lock().unwrap());
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let control = control.clone();
+            let swap_iteration = Arc::clone(&swap_iteration);
+            move || {
+                if swap_iteration.lock().unwrap().is_some() {
+                    return Err(QuitEvent);
+                }
+                let mut iterations = iterations.lock().unwrap();
+                *iterations += 1;
+                if *iterations == PAUSED_ITERATIONS {
+                    /* Resume, e.g. requested remotely */
+                    control.send(Command::Resume);
+                } else if *iterations < PAUSED_ITERATIONS {
+                    /* Give the fetcher thread a chance to prepare the photo */
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+                Ok(())
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            control,
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        assert!(swap_iteration
+            .lock()
+            .unwrap()
+            .is_some_and(|iteration| iteration >= PAUSED_ITERATIONS));
+    }
+
+    #[test]
+    fn when_outside_of_active_hours_then_screen_is_blank_and_photo_is_not_displayed() {
+        test_case("07:00-22:00", (12, 0), false);
+        test_case("07:00-22:00", (6, 59), true);
+        test_case("07:00-22:00", (22, 0), true);
+        /* Window wrapping past midnight */
+        test_case("22:00-06:00", (23, 30), false);
+        test_case("22:00-06:00", (1, 0), false);
+        test_case("22:00-06:00", (12, 0), true);
+
+        fn test_case(active_hours: &str, (hour, min): (u32, u32), expected_blank: bool) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+            let mut client_stub = MockHttpClient::new();
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+                .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .returning(|_, _, _| {
+                    Ok(test_helpers::new_success_response_with_json(List {
+                        list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                    }))
+                });
+            client_stub.expect_get().returning(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from(std::fs::read("assets/test_loading.jpeg")?)));
+                Ok(get_photo_response)
+            });
+
+            /* Avoid overflow when setting initial last_change */
+            const DISPLAY_INTERVAL: u64 = 30;
+            MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+            test_helpers::set_fake_local_now(
+                NaiveDate::from_ymd_opt(2024, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(hour, min, 0)
+                    .unwrap(),
+            );
+            let mut renderer_mock = MockRenderer::new();
+            renderer_mock
+                .expect_fill_canvas()
+                .withf(|color| *color == Color::rgba(0, 0, 0, u8::MAX))
+                .times(usize::from(expected_blank))
+                .returning(|_| Ok(()));
+            let swaps = Arc::new(AtomicUsize::new(0));
+            renderer_mock.expect_swap_textures().returning({
+                let swaps = Arc::clone(&swaps);
+                move || {
+                    swaps.fetch_add(1, Ordering::Relaxed);
+                }
+            });
+            let mut renderer_mock = renderer_mock.with_default_expectations();
+            let iterations = Arc::new(Mutex::new(0));
+            renderer_mock.expect_handle_quit_event().returning({
+                let swaps = Arc::clone(&swaps);
+                move || {
+                    let mut iterations = iterations.lock().unwrap();
+                    *iterations += 1;
+                    if *iterations < 200 && swaps.load(Ordering::Relaxed) == 0 {
+                        /* Give the fetcher thread a chance to prepare the photo */
+                        std::thread::sleep(Duration::from_millis(5));
+                        Ok(())
+                    } else {
+                        Err(QuitEvent)
+                    }
+                }
+            });
+            let cli_command = format!(
+                "syno-photo-frame {SHARE_LINK} \
+                --interval {DISPLAY_INTERVAL} \
+                --active-hours {active_hours} \
+                --disable-update-check \
+                --transition none \
+                --splash assets/test_loading.jpeg"
+            );
+
+            let result = run(
+                &Cli::parse_from(cli_command.split(' ')),
+                (&client_stub, &Jar::default()),
+                &mut renderer_mock,
+                FakeRandom::default(),
+                Control::default(),
+                "1.2.3",
+            );
+
+            assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+            assert_eq!(
+                swaps.load(Ordering::Relaxed) == 0,
+                expected_blank,
+                "{active_hours} at {hour:02}:{min:02}"
+            );
+        }
+    }
+
+    #[test]
+    fn when_first_photo_does_not_arrive_within_startup_timeout_then_error_screen_is_displayed() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        /* Login hangs until the test finishes */
+        let login_released = Arc::new(AtomicBool::new(false));
+        let mut client_stub = MockHttpClient::new();
+        client_stub.expect_post().returning({
+            let login_released = Arc::clone(&login_released);
+            move |_, _, _| {
+                while !login_released.load(Ordering::Relaxed) {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                Err(anyhow!("connection reset"))
+            }
+        });
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let screen_size = (198, 102);
+        let next_texture = Arc::new(Mutex::new(vec![]));
+        let swap_time = Arc::new(Mutex::new(None));
+        let mut renderer_stub = MockRenderer::new();
+        {
+            renderer_stub.expect_pressed_keys().returning(Vec::new);
+            renderer_stub.expect_size().return_const(screen_size);
+            renderer_stub
+                .expect_copy_texture_to_canvas()
+                .returning(|_| Ok(()));
+            renderer_stub.expect_fill_canvas().returning(|_| Ok(()));
+            renderer_stub.expect_present_canvas().return_const(());
+        }
+        renderer_stub.expect_update_texture().returning({
+            let next_texture = Arc::clone(&next_texture);
+            move |image_data, index| {
+                if index == TextureIndex::Next {
+                    *next_texture.lock().unwrap() = image_data.to_vec();
+                }
+                Ok(())
+            }
+        });
+        renderer_stub.expect_swap_textures().returning({
+            let swap_time = Arc::clone(&swap_time);
+            move || {
+                swap_time.lock().unwrap().get_or_insert(MockClock::time());
+            }
+        });
+        renderer_stub.expect_handle_quit_event().returning({
+            let swap_time = Arc::clone(&swap_time);
+            let login_released = Arc::clone(&login_released);
+            move || {
+                if swap_time.lock().unwrap().is_some() {
+                    login_released.store(true, Ordering::Relaxed);
+                    return Err(QuitEvent);
+                }
+                /* Each loop iteration takes some time */
+                MockClock::advance(Duration::from_millis(100));
+                Ok(())
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --startup-timeout {} \
+            --splash assets/test_loading.jpeg",
+            STARTUP_TIMEOUT.as_secs()
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            Control::default(),
+            "1.2.3",
+        );
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+        assert!(swap_time
+            .lock()
+            .unwrap()
+            .is_some_and(|time| time >= Duration::from_secs(DISPLAY_INTERVAL) + STARTUP_TIMEOUT));
+        let error_screen = asset::error_screen(None, screen_size, cli::Rotation::D0).unwrap();
+        assert!(*next_texture.lock().unwrap() == error_screen.as_bytes());
+    }
+
+    #[test]
+    fn when_color_transform_is_provided_then_finish_photo_applies_it() {
+        let cli = Cli::parse_from(["syno-photo-frame", "http://fake.dsm.addr/aa/sharing/Fake"]);
+        let mut color_transform_mock = MockColorTransform::new();
+        color_transform_mock
+            .expect_transform()
+            .times(1)
+            .return_const(());
+        let mut image = DynamicImage::new_rgb8(4, 2);
+
+        finish_photo(&mut image, &cli, None, None, Some(&color_transform_mock));
+
+        color_transform_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_fetching_photo_panics_then_loop_continues() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                }))
+            });
+        client_stub
+            .expect_get()
+            .returning(|_, _| panic!("simulated panic while fetching a photo"));
+
+        /* Avoid overflow when setting initial last_change */
+        const DISPLAY_INTERVAL: u64 = 30;
+        MockClock::set_time(Duration::from_secs(DISPLAY_INTERVAL));
+        let mut renderer_stub = MockRenderer::new().with_default_expectations();
+        renderer_stub.expect_swap_textures().returning(|| {
+            MockClock::advance(Duration::from_secs(1));
+        });
+        renderer_stub.expect_handle_quit_event().returning(|| {
+            /* Until swap_textures is called (with an error image) and advances the time, return
+             * Ok. Afterward, break the loop with a simulated Quit event to finish the test */
+            if MockClock::time() <= Duration::from_secs(DISPLAY_INTERVAL) {
+                Ok(())
+            } else {
+                Err(QuitEvent)
+            }
+        });
+        let cli_command = format!(
+            "syno-photo-frame {SHARE_LINK} \
+            --interval {DISPLAY_INTERVAL} \
+            --disable-update-check \
+            --transition none \
+            --splash assets/test_loading.jpeg"
+        );
+
+        let result = run(
+            &Cli::parse_from(cli_command.split(' ')),
+            (&client_stub, &Jar::default()),
+            &mut renderer_stub,
+            FakeRandom::default(),
+            Control::default(),
+            "1.2.3",
+        );
+
+        /* Panic was turned into an error screen instead of killing the fetcher thread, which
+         * would leave the main loop waiting for a photo forever */
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+    }
+
+    #[test]
+    fn when_neither_share_link_nor_manifest_is_given_then_selected_backend_fails() {
+        let mut cli = Cli::parse_from([
+            "syno-photo-frame",
+            "http://fake.dsm.addr/aa/sharing/FakeSharingId",
+        ]);
+        cli.share_link = None;
+
+        let result = selected_backend(&cli);
+
+        assert!(result.is_err_and(|e| e.to_string().contains("--manifest")));
+    }
+
+    impl MockRenderer {
         pub fn with_default_expectations(mut self) -> Self {
             self.expect_size().return_const((198, 102));
             self.expect_update_texture().returning(|_, _| Ok(()));
             self.expect_copy_texture_to_canvas().returning(|_| Ok(()));
             self.expect_fill_canvas().returning(|_| Ok(()));
             self.expect_present_canvas().return_const(());
+            self.expect_pressed_keys().returning(Vec::new);
             self
         }
     }
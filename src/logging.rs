@@ -1,13 +1,19 @@
 //! Logging
 
 use core::fmt::Debug;
+use std::time::Duration;
+#[cfg(not(test))]
+use std::time::Instant;
+
+#[cfg(test)]
+use mock_instant::Instant;
 
 use anyhow::Result;
 use log::Level;
 
 use crate::http::{HttpClient, HttpResponse};
 
-/// Adds logging to [HttpClient]
+/// Adds logging of requests, responses and request durations to [HttpClient]
 #[derive(Clone, Debug)]
 pub struct LoggingClientDecorator<C> {
     client: C,
@@ -50,15 +56,55 @@ where
             self.level,
             "POST {url}, form: {obfuscated_form:?}, header: {header:?}"
         );
-        let response = self.client.post(url, form, header);
-        log::log!(self.level, "{response:?}");
+        let (response, elapsed) = measure(|| self.client.post(url, form, header));
+        log::log!(self.level, "{response:?}, elapsed: {elapsed:?}");
         response
     }
 
     fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<Self::Response> {
         log::log!(self.level, "GET {url}, query: {query:?}");
-        let response = self.client.get(url, query);
-        log::log!(self.level, "{response:?}");
+        let (response, elapsed) = measure(|| self.client.get(url, query));
+        log::log!(self.level, "{response:?}, elapsed: {elapsed:?}");
+        response
+    }
+
+    fn get_with_timeout(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        timeout: Duration,
+    ) -> Result<Self::Response> {
+        log::log!(
+            self.level,
+            "GET {url}, query: {query:?}, timeout: {timeout:?}"
+        );
+        let (response, elapsed) = measure(|| self.client.get_with_timeout(url, query, timeout));
+        log::log!(self.level, "{response:?}, elapsed: {elapsed:?}");
         response
     }
 }
+
+/// Calls `f` and measures how long it took
+fn measure<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    (result, Instant::now() - start)
+}
+
+#[cfg(test)]
+mod tests {
+    use mock_instant::MockClock;
+
+    use super::*;
+
+    #[test]
+    fn measure_returns_result_and_elapsed_time() {
+        let (result, elapsed) = measure(|| {
+            MockClock::advance(Duration::from_millis(1500));
+            42
+        });
+
+        assert_eq!(result, 42);
+        assert_eq!(elapsed, Duration::from_millis(1500));
+    }
+}
@@ -1,16 +1,23 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+    time::Duration,
+};
 
 use anyhow::{anyhow, bail, Result};
 use log::LevelFilter;
+use signal_hook::consts::{SIGINT, SIGTERM};
 use simple_logger::SimpleLogger;
 
 use syno_photo_frame::{
     self,
-    cli::{Cli, Parser},
-    http::ClientBuilder,
+    cli::Cli,
+    display_power,
+    http::{self, ClientBuilder},
     logging::LoggingClientDecorator,
+    mqtt, remote,
     sdl::{self, SdlWrapper},
-    LoginError, QuitEvent, RandomImpl,
+    Control, LoginError, QuitEvent, RandomImpl, ShareLinkExpired,
 };
 
 fn main() -> Result<()> {
@@ -26,6 +33,9 @@ fn main() -> Result<()> {
             }
             log::error!("{error}");
             match error.downcast_ref::<LoginError>() {
+                Some(LoginError(error)) if error.is::<ShareLinkExpired>() => {
+                    bail!("Share link is no longer valid. Create a new one and restart.")
+                }
                 Some(LoginError(_)) => {
                     bail!(
                         "Login failed. Make sure the share link is pointing to a \
@@ -42,26 +52,71 @@ fn main() -> Result<()> {
 
 /// Setup "real" dependencies and run
 fn init_and_run() -> Result<()> {
-    let cli = Cli::parse();
+    let cli = Cli::parse_with_config();
 
     /* HTTP client */
     let cookie_store = Arc::new(reqwest::cookie::Jar::default());
-    let http_client = ClientBuilder::new()
+    let mut client_builder = ClientBuilder::new()
         .cookie_provider(Arc::clone(&cookie_store))
-        .timeout(Duration::from_secs(cli.timeout_seconds as u64))
-        .build()?;
+        .timeout(Duration::from_secs(cli.timeout_seconds as u64));
+    client_builder = http::with_ip_version(client_builder, cli.ip_version);
+    client_builder = http::with_dns_overrides(client_builder, &cli.resolve);
+    if let Some(identity) =
+        http::client_identity(cli.client_cert.as_deref(), cli.client_key.as_deref())?
+    {
+        client_builder = client_builder.identity(identity);
+    }
+    if let Some(certificate) = http::root_certificate(cli.cacert.as_deref())? {
+        client_builder = client_builder.add_root_certificate(certificate);
+    }
+    if cli.danger_accept_invalid_certs {
+        log::warn!("TLS certificates of the server are not verified");
+        client_builder = client_builder.danger_accept_invalid_certs(true);
+    }
+    let http_client = client_builder.build()?;
+
+    if let Some(path) = &cli.json_metadata_dump {
+        return syno_photo_frame::dump_metadata(&cli, (&http_client, cookie_store.as_ref()), path);
+    }
 
     /* SDL */
     let video = sdl::init_video()?;
-    let display_size = sdl::display_size(&video)?;
-    let canvas = sdl::create_canvas(&video, display_size)?;
+    let detected_size = sdl::display_size(&video)?;
+    let display_size = match cli.resolution {
+        Some(forced_size @ (w, h)) => {
+            let (detected_w, detected_h) = detected_size;
+            log::info!("Detected screen resolution {detected_w}x{detected_h}, forcing {w}x{h}");
+            forced_size
+        }
+        None => detected_size,
+    };
+    let mut canvas = sdl::create_canvas(&video, display_size)?;
+    let render_size = sdl::render_size(display_size, cli.render_scale);
+    if render_size != display_size {
+        let (w, h) = render_size;
+        log::info!("Rendering at {w}x{h}");
+    }
+    sdl::set_render_size(&mut canvas, render_size)?;
     let texture_creator = canvas.texture_creator();
     let textures = [
-        sdl::create_texture(&texture_creator, display_size)?,
-        sdl::create_texture(&texture_creator, display_size)?,
+        sdl::create_texture(&texture_creator, render_size)?,
+        sdl::create_texture(&texture_creator, render_size)?,
     ];
     let events = video.sdl().event_pump().map_err(|s| anyhow!(s))?;
-    let mut sdl = SdlWrapper::new(canvas, textures, events);
+    let mut sdl = SdlWrapper::new(canvas, textures, events)
+        .with_screensaver_mode(cli.screensaver_mode)
+        .with_quit_flag(quit_on_signals()?);
+
+    let control = Control::default();
+    if cli.pause_when_display_off {
+        watch_display_power(&control)?;
+    }
+    if let Some(broker) = cli.mqtt_broker.clone() {
+        mqtt::spawn(broker, cli.mqtt_topic.clone(), control.clone())?;
+    }
+    if let Some(port) = cli.control_port {
+        remote::spawn((cli.control_bind, port), control.clone())?;
+    }
 
     /* This crate version */
     let installed_version = env!("CARGO_PKG_VERSION");
@@ -74,6 +129,34 @@ fn init_and_run() -> Result<()> {
         ),
         &mut sdl,
         RandomImpl,
+        control,
         installed_version,
     )
 }
+
+/// Returns a flag set on SIGTERM or SIGINT, so that the app quits through the slideshow's normal
+/// [QuitEvent] path and SDL restores the display (e.g. when stopped by a script). A second signal
+/// terminates the app immediately, in case it does not respond
+fn quit_on_signals() -> Result<Arc<AtomicBool>> {
+    let quit_requested = Arc::new(AtomicBool::new(false));
+    for signal in [SIGTERM, SIGINT] {
+        /* Registered after SDL initialization, so that SDL's own handlers are still called */
+        signal_hook::flag::register_conditional_shutdown(signal, 1, Arc::clone(&quit_requested))?;
+        signal_hook::flag::register(signal, Arc::clone(&quit_requested))?;
+    }
+    Ok(quit_requested)
+}
+
+#[cfg(target_os = "linux")]
+fn watch_display_power(control: &Control) -> Result<()> {
+    let display_off = Arc::clone(&control.display_off);
+    thread::spawn(move || {
+        display_power::watch(display_power::DrmDisplayPower::new(), &display_off)
+    });
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn watch_display_power(_: &Control) -> Result<()> {
+    bail!("--pause-when-display-off is only supported on Linux")
+}
@@ -0,0 +1,396 @@
+//! Photo metadata
+
+use std::io::Write;
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use crate::api_client::{ApiClient, SortBy};
+
+/// Information about a photo known before its bytes are fetched
+pub trait Metadata {
+    /// Identifier of the photo in the album
+    fn id(&self) -> String;
+
+    /// Identifies the photo's content, e.g. to cache it on disk. Backends which report a version
+    /// of the photo change it when the photo gets edited
+    fn cache_key(&self) -> String {
+        self.id()
+    }
+
+    /// Date and time when the photo was taken, local to where it was taken
+    fn date(&self) -> Option<NaiveDateTime>;
+
+    /// Human-readable name of the place where the photo was taken
+    fn location(&self) -> Option<String>;
+
+    /// Name of the photo's file
+    fn file_name(&self) -> Option<String>;
+
+    /// Make and model of the camera the photo was taken with
+    fn camera(&self) -> Option<String>;
+
+    /// Description of the photo entered by its owner
+    fn caption(&self) -> Option<String>;
+}
+
+/// Whether the date is unknown. Servers may report the Unix epoch instead of a missing date
+pub fn is_undated(date: Option<NaiveDateTime>) -> bool {
+    match date {
+        Some(date) => date == DateTime::UNIX_EPOCH.naive_utc(),
+        None => true,
+    }
+}
+
+/// Metadata of a fetched photo, detached from the backend specific type
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhotoInfo {
+    pub id: String,
+    pub date: Option<NaiveDateTime>,
+    pub location: Option<String>,
+    pub file_name: Option<String>,
+    pub camera: Option<String>,
+    pub caption: Option<String>,
+    /// Position of the photo in the current pass through the album (starting at 1) and the number
+    /// of photos in the pass. In random orders the position is within the current shuffle.
+    /// `None` for photos displayed outside the display sequence, e.g. pinned photos
+    pub position: Option<(usize, usize)>,
+}
+
+impl<M: Metadata> From<&M> for PhotoInfo {
+    fn from(photo: &M) -> Self {
+        Self {
+            id: photo.id(),
+            date: photo.date(),
+            location: photo.location(),
+            file_name: photo.file_name(),
+            camera: photo.camera(),
+            caption: photo.caption(),
+            position: None,
+        }
+    }
+}
+
+impl PhotoInfo {
+    /// Caption like "42 / 318" showing the photo's position in the current pass
+    pub fn counter(&self) -> Option<String> {
+        self.position
+            .map(|(position, count)| format!("{position} / {count}"))
+    }
+
+    /// Caption like "5 years ago today", when the photo was taken on the same month and day as
+    /// `today` in an earlier year. Photos taken on February 29 only have anniversaries in leap
+    /// years
+    pub fn anniversary(&self, today: NaiveDate) -> Option<String> {
+        let taken = self.date?.date();
+        if (taken.month(), taken.day()) != (today.month(), today.day()) {
+            return None;
+        }
+        match today.year() - taken.year() {
+            years if years < 1 => None,
+            1 => Some("1 year ago today".to_string()),
+            years => Some(format!("{years} years ago today")),
+        }
+    }
+
+    /// Caption composed from a template like "{date} — {location}". Supported tokens are
+    /// `{date}`, `{time}`, `{location}`, `{filename}`, `{camera}` and `{caption}`; unknown tokens
+    /// are kept as they are. Text between tokens is left out next to a token without a value, so
+    /// that e.g. the separator disappears for photos without a location. `None` when no token has
+    /// a value
+    pub fn format(&self, template: &str) -> Option<String> {
+        let date = (!is_undated(self.date)).then_some(self.date).flatten();
+        let value = |token: &str| match token {
+            "date" => date.map(|date| date.format("%Y-%m-%d").to_string()),
+            "time" => date.map(|date| date.format("%H:%M").to_string()),
+            "location" => self.location.clone(),
+            "filename" => self.file_name.clone(),
+            "camera" => self.camera.clone(),
+            "caption" => self.caption.clone(),
+            _ => Some(format!("{{{token}}}")),
+        };
+        let parts = parse_template(template);
+        let values = parts
+            .iter()
+            .map(|part| match part {
+                TemplatePart::Literal(_) => None,
+                TemplatePart::Token(token) => value(token).filter(|value| !value.is_empty()),
+            })
+            .collect::<Vec<_>>();
+        let is_token = |part: &TemplatePart| matches!(part, TemplatePart::Token(_));
+        if parts.iter().any(is_token) && values.iter().all(Option::is_none) {
+            return None;
+        }
+        let is_missing = |index: Option<usize>| {
+            index
+                .filter(|&index| index < parts.len())
+                .is_some_and(|index| is_token(&parts[index]) && values[index].is_none())
+        };
+        let text = parts
+            .iter()
+            .enumerate()
+            .filter_map(|(index, part)| match part {
+                TemplatePart::Literal(text) => (!is_missing(index.checked_sub(1))
+                    && !is_missing(Some(index + 1)))
+                .then_some(*text),
+                TemplatePart::Token(_) => values[index].as_deref(),
+            })
+            .collect::<String>();
+        Some(text)
+    }
+}
+
+/// Part of a caption template, see [PhotoInfo::format]
+#[derive(Debug, PartialEq)]
+enum TemplatePart<'a> {
+    Literal(&'a str),
+    Token(&'a str),
+}
+
+/// Splits a template into literal text and `{token}` names. Unclosed braces are literal text
+fn parse_template(template: &str) -> Vec<TemplatePart<'_>> {
+    let mut parts = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(length) = rest[start..].find('}') else {
+            break;
+        };
+        if start > 0 {
+            parts.push(TemplatePart::Literal(&rest[..start]));
+        }
+        parts.push(TemplatePart::Token(&rest[start + 1..start + length]));
+        rest = &rest[start + length + 1..];
+    }
+    if !rest.is_empty() {
+        parts.push(TemplatePart::Literal(rest));
+    }
+    parts
+}
+
+/// Photo currently displayed on screen, shared with threads answering queries about it
+#[derive(Debug, Default)]
+pub struct CurrentPhoto {
+    /// `None` until the first photo is displayed, and while an error screen or album QR code is
+    /// displayed
+    pub info: Option<PhotoInfo>,
+}
+
+/// Writes metadata of all album photos to `writer` as a JSON array
+pub(crate) fn write_json<A: ApiClient>(
+    api_client: &A,
+    sort_by: SortBy,
+    writer: impl Write,
+) -> Result<()> {
+    if !api_client.is_logged_in() {
+        api_client.login()?;
+    }
+    let records = api_client
+        .get_photo_metadata(sort_by)?
+        .iter()
+        .map(MetadataRecord::from)
+        .collect::<Vec<_>>();
+    serde_json::to_writer_pretty(writer, &records)?;
+    Ok(())
+}
+
+/// Metadata of a photo as exchanged with external tools
+#[derive(Debug, Serialize)]
+pub(crate) struct MetadataRecord {
+    id: String,
+    /// Formatted as e.g. 2024-05-01T14:30:00
+    date: Option<String>,
+    location: Option<String>,
+    file_name: Option<String>,
+}
+
+impl<M: Metadata> From<&M> for MetadataRecord {
+    fn from(photo: &M) -> Self {
+        Self {
+            id: photo.id(),
+            date: photo
+                .date()
+                .map(|date| date.format("%Y-%m-%dT%H:%M:%S").to_string()),
+            location: photo.location(),
+            file_name: photo.file_name(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use syno_api::{dto::List, foto::browse::item::dto::Item};
+
+    use crate::{
+        api_client::syno_client::{Login, SynoApiClient},
+        http::{Jar, Url},
+        test_helpers::{self, MockHttpClient},
+    };
+
+    #[test]
+    fn anniversary_is_shown_when_month_and_day_match() {
+        let photo = new_photo_info_taken_on(2019, 6, 15);
+
+        assert_eq!(
+            photo.anniversary(new_date(2024, 6, 15)),
+            Some("5 years ago today".to_string())
+        );
+        assert_eq!(
+            photo.anniversary(new_date(2020, 6, 15)),
+            Some("1 year ago today".to_string())
+        );
+    }
+
+    #[test]
+    fn anniversary_is_omitted_for_non_matching_dates() {
+        let photo = new_photo_info_taken_on(2019, 6, 15);
+
+        assert_eq!(photo.anniversary(new_date(2024, 6, 14)), None);
+        assert_eq!(photo.anniversary(new_date(2024, 7, 15)), None);
+        /* Taken today or in the future */
+        assert_eq!(photo.anniversary(new_date(2019, 6, 15)), None);
+        assert_eq!(photo.anniversary(new_date(2018, 6, 15)), None);
+        /* Unknown date */
+        assert_eq!(
+            PhotoInfo::default().anniversary(new_date(2024, 6, 15)),
+            None
+        );
+    }
+
+    #[test]
+    fn anniversary_of_photo_taken_on_leap_day_is_shown_only_in_leap_years() {
+        let photo = new_photo_info_taken_on(2016, 2, 29);
+
+        assert_eq!(photo.anniversary(new_date(2023, 2, 28)), None);
+        assert_eq!(photo.anniversary(new_date(2023, 3, 1)), None);
+        assert_eq!(
+            photo.anniversary(new_date(2024, 2, 29)),
+            Some("8 years ago today".to_string())
+        );
+    }
+
+    #[test]
+    fn anniversary_of_photo_taken_on_february_28_is_shown_in_leap_years() {
+        let photo = new_photo_info_taken_on(2023, 2, 28);
+
+        assert_eq!(
+            photo.anniversary(new_date(2024, 2, 28)),
+            Some("1 year ago today".to_string())
+        );
+        assert_eq!(photo.anniversary(new_date(2024, 2, 29)), None);
+    }
+
+    #[test]
+    fn format_replaces_tokens_with_photo_metadata() {
+        let photo = PhotoInfo {
+            location: Some("Lisbon, Portugal".to_string()),
+            file_name: Some("IMG_0001.jpg".to_string()),
+            camera: Some("Canon EOS R6".to_string()),
+            ..new_photo_info_taken_on(2019, 6, 15)
+        };
+
+        assert_eq!(
+            photo.format("{date} {time} — {location}"),
+            Some("2019-06-15 12:30 — Lisbon, Portugal".to_string())
+        );
+        assert_eq!(
+            photo.format("{filename} ({camera})"),
+            Some("IMG_0001.jpg (Canon EOS R6)".to_string())
+        );
+        assert_eq!(
+            photo.format("{unknown} {date"),
+            Some("{unknown} {date".to_string())
+        );
+    }
+
+    #[test]
+    fn format_leaves_out_text_next_to_tokens_without_value() {
+        let photo = new_photo_info_taken_on(2019, 6, 15);
+
+        assert_eq!(
+            photo.format("{date} — {location}"),
+            Some("2019-06-15".to_string())
+        );
+        assert_eq!(
+            photo.format("Taken in {location} on {date}"),
+            Some("2019-06-15".to_string())
+        );
+        assert_eq!(photo.format("{location} {camera}"), None);
+        /* Unix epoch is reported for photos without a date */
+        let undated_photo = PhotoInfo {
+            date: Some(DateTime::UNIX_EPOCH.naive_utc()),
+            file_name: Some("IMG_0001.jpg".to_string()),
+            ..PhotoInfo::default()
+        };
+        assert_eq!(
+            undated_photo.format("{date} — {filename}"),
+            Some("IMG_0001.jpg".to_string())
+        );
+    }
+
+    #[test]
+    fn write_json_writes_metadata_of_all_photos() {
+        let mut client_stub = MockHttpClient::new();
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_login_form(form, "FakeSharingId"))
+            .return_once(|_, _, _| Ok(test_helpers::new_success_response_with_json(Login {})));
+        client_stub
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        Item {
+                            filename: "IMG_0001.jpg".to_string(),
+                            /* 2024-05-01 14:30:00 */
+                            time: 1714573800,
+                            ..test_helpers::new_photo_dto(1, "photo1")
+                        },
+                        Item {
+                            filename: "IMG_0002.jpg".to_string(),
+                            ..test_helpers::new_photo_dto(2, "photo2")
+                        },
+                    ],
+                }))
+            });
+        let share_link = Url::parse("http://fake.dsm.addr/aa/sharing/FakeSharingId").unwrap();
+        let cookie_store = Jar::default();
+        let api_client = SynoApiClient::build(&client_stub, &cookie_store, &share_link).unwrap();
+        let mut output = vec![];
+
+        let result = write_json(&api_client, SortBy::TakenTime, &mut output);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&output).unwrap(),
+            serde_json::json!([
+                {
+                    "id": "1",
+                    "date": "2024-05-01T14:30:00",
+                    "location": null,
+                    "file_name": "IMG_0001.jpg"
+                },
+                {
+                    "id": "2",
+                    "date": "1970-01-01T00:00:00",
+                    "location": null,
+                    "file_name": "IMG_0002.jpg"
+                }
+            ])
+        );
+    }
+
+    fn new_photo_info_taken_on(year: i32, month: u32, day: u32) -> PhotoInfo {
+        PhotoInfo {
+            date: new_date(year, month, day).and_hms_opt(12, 30, 0),
+            ..PhotoInfo::default()
+        }
+    }
+
+    fn new_date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+}
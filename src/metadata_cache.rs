@@ -0,0 +1,212 @@
+//! Album metadata stored on disk between runs, see `--metadata-cache`
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api_client::SortBy;
+
+/// Photo metadata which can be stored in [MetadataCache]
+pub trait CachedPhoto: Sized {
+    fn to_cache(&self) -> Result<Value>;
+
+    fn from_cache(value: Value) -> Result<Self>;
+}
+
+/// Album metadata cached in a JSON file. Cached metadata is used until it is older than the TTL,
+/// and only for the album it was fetched from
+#[derive(Debug)]
+pub struct MetadataCache {
+    path: PathBuf,
+    ttl: Duration,
+    /// Identifies the album, e.g. by its share link
+    album: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    album: String,
+    sort_by: String,
+    /// Seconds since Unix epoch
+    saved_at: u64,
+    photos: Vec<Value>,
+}
+
+impl MetadataCache {
+    pub fn new(path: &Path, ttl: Duration, album: &str) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            ttl,
+            album: album.to_string(),
+        }
+    }
+
+    /// Returns cached metadata if it is fresh at `now` and belongs to the album
+    pub fn load<P: CachedPhoto>(&self, sort_by: SortBy, now: SystemTime) -> Option<Vec<P>> {
+        match self.try_load(sort_by, now) {
+            Ok(Some(photos)) => {
+                log::info!("Using album metadata cached in {}", self.path_str());
+                Some(photos)
+            }
+            Ok(None) => None,
+            Err(error) => {
+                log::warn!("Metadata cache {}: {error}", self.path_str());
+                None
+            }
+        }
+    }
+
+    /// Replaces the cached metadata. Failures are logged, as the slideshow works without the cache
+    pub fn save<P: CachedPhoto>(&self, sort_by: SortBy, photos: &[P], now: SystemTime) {
+        if let Err(error) = self.try_save(sort_by, photos, now) {
+            log::warn!("Metadata cache {}: {error}", self.path_str());
+        }
+    }
+
+    fn try_load<P: CachedPhoto>(&self, sort_by: SortBy, now: SystemTime) -> Result<Option<Vec<P>>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let cache_file: CacheFile =
+            serde_json::from_reader(BufReader::new(File::open(&self.path)?))?;
+        let age = Duration::from_secs(unix_seconds(now)?.saturating_sub(cache_file.saved_at));
+        if cache_file.album != self.album
+            || cache_file.sort_by != format!("{sort_by:?}")
+            || age >= self.ttl
+        {
+            return Ok(None);
+        }
+        let photos = cache_file
+            .photos
+            .into_iter()
+            .map(P::from_cache)
+            .collect::<Result<_>>()?;
+        Ok(Some(photos))
+    }
+
+    fn try_save<P: CachedPhoto>(
+        &self,
+        sort_by: SortBy,
+        photos: &[P],
+        now: SystemTime,
+    ) -> Result<()> {
+        let cache_file = CacheFile {
+            album: self.album.clone(),
+            sort_by: format!("{sort_by:?}"),
+            saved_at: unix_seconds(now)?,
+            photos: photos.iter().map(P::to_cache).collect::<Result<_>>()?,
+        };
+        serde_json::to_writer(BufWriter::new(File::create(&self.path)?), &cache_file)?;
+        Ok(())
+    }
+
+    fn path_str(&self) -> String {
+        self.path.to_string_lossy().into_owned()
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> Result<u64> {
+    Ok(time
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| anyhow!("system time is before Unix epoch"))?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use serde_json::json;
+
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(60 * 60);
+    const ALBUM: &str = "https://fake.dsm.addr/aa/sharing/FakeSharingId";
+
+    #[test]
+    fn when_cache_is_fresh_then_load_returns_cached_photos() {
+        let path = temp_file_path("fresh");
+        let cache = MetadataCache::new(&path, TTL, ALBUM);
+        let saved_at = SystemTime::now();
+        cache.save(SortBy::TakenTime, &new_photos(), saved_at);
+
+        let result = cache.load::<FakePhoto>(SortBy::TakenTime, saved_at + TTL / 2);
+
+        assert_eq!(result, Some(new_photos()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn when_cache_is_stale_then_load_returns_none() {
+        let path = temp_file_path("stale");
+        let cache = MetadataCache::new(&path, TTL, ALBUM);
+        let saved_at = SystemTime::now();
+        cache.save(SortBy::TakenTime, &new_photos(), saved_at);
+
+        let result = cache.load::<FakePhoto>(SortBy::TakenTime, saved_at + TTL);
+
+        assert_eq!(result, None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn when_share_link_changes_then_load_returns_none() {
+        let path = temp_file_path("link-change");
+        let saved_at = SystemTime::now();
+        MetadataCache::new(&path, TTL, ALBUM).save(SortBy::TakenTime, &new_photos(), saved_at);
+        let cache = MetadataCache::new(
+            &path,
+            TTL,
+            "https://fake.dsm.addr/aa/sharing/OtherSharingId",
+        );
+
+        let result = cache.load::<FakePhoto>(SortBy::TakenTime, saved_at);
+
+        assert_eq!(result, None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn when_sort_order_changes_then_load_returns_none() {
+        let path = temp_file_path("sort-change");
+        let cache = MetadataCache::new(&path, TTL, ALBUM);
+        let saved_at = SystemTime::now();
+        cache.save(SortBy::TakenTime, &new_photos(), saved_at);
+
+        let result = cache.load::<FakePhoto>(SortBy::FileName, saved_at);
+
+        assert_eq!(result, None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct FakePhoto(u32);
+
+    impl CachedPhoto for FakePhoto {
+        fn to_cache(&self) -> Result<Value> {
+            Ok(json!(self.0))
+        }
+
+        fn from_cache(value: Value) -> Result<Self> {
+            Ok(FakePhoto(serde_json::from_value(value)?))
+        }
+    }
+
+    fn new_photos() -> Vec<FakePhoto> {
+        vec![FakePhoto(1), FakePhoto(2)]
+    }
+
+    fn temp_file_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "syno-photo-frame-metadata-cache-{}-{name}.json",
+            process::id()
+        ))
+    }
+}
@@ -0,0 +1,108 @@
+//! MQTT integration for home automation, e.g. Home Assistant, see `--mqtt-broker`
+//!
+//! Topics are placed under a prefix set with `--mqtt-topic`:
+//! - `{prefix}/availability`: retained "online", or "offline" published by the broker once the
+//!   app disconnects
+//! - `{prefix}/status`: retained JSON with the state of the slideshow, as returned by
+//!   [crate::control::Control::status]
+//! - `{prefix}/command`: subscribed to commands like "next", "pause" or "resume", sent to the
+//!   slideshow like the other [crate::control::Command]s
+
+use anyhow::Result;
+
+use crate::control::Control;
+
+/// Connects to the broker at `(host, port)` in a background thread, publishing the state of the
+/// slideshow and sending received commands through `control`. Reconnects when the connection
+/// fails. Fails right away when the app was built without the `mqtt` feature
+#[cfg(feature = "mqtt")]
+pub fn spawn((host, port): (String, u16), topic_prefix: String, control: Control) -> Result<()> {
+    std::thread::spawn(move || {
+        if let Err(error) = serve((&host, port), &topic_prefix, &control) {
+            log::error!("{error}");
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "mqtt"))]
+pub fn spawn(_broker: (String, u16), _topic_prefix: String, _control: Control) -> Result<()> {
+    anyhow::bail!("--mqtt-broker is not supported, the app was built without the mqtt feature")
+}
+
+#[cfg(feature = "mqtt")]
+fn serve((host, port): (&str, u16), topic_prefix: &str, control: &Control) -> Result<()> {
+    use std::{thread, time::Duration};
+
+    use rumqttc::{Client, Event, LastWill, MqttOptions, Packet, QoS, RecvTimeoutError};
+
+    use crate::control::Command;
+
+    /* Status changes are published within this delay */
+    const POLL_INTERVAL: Duration = Duration::from_millis(500);
+    const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+    let topic = |suffix: &str| format!("{topic_prefix}/{suffix}");
+    let mut options = MqttOptions::new(topic_prefix.replace('/', "-"), host, port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        topic("availability"),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    let (client, mut connection) = Client::new(options, 10);
+    let mut published_status = None;
+    loop {
+        match connection.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(Event::Incoming(Packet::ConnAck(_)))) => {
+                log::info!("Connected to MQTT broker {host}:{port}");
+                /* Subscribed again on every reconnect, as the session is not kept by the broker */
+                client.subscribe(topic("command"), QoS::AtLeastOnce)?;
+                client.publish(topic("availability"), QoS::AtLeastOnce, true, "online")?;
+                published_status = None;
+            }
+            Ok(Ok(Event::Incoming(Packet::Publish(publish)))) => {
+                match std::str::from_utf8(&publish.payload)
+                    .ok()
+                    .and_then(|name| Command::parse(name.trim()))
+                {
+                    Some(command) => control.send(command),
+                    None => log::warn!("Unknown MQTT command {:?}", publish.payload),
+                }
+            }
+            Ok(Ok(_)) | Err(RecvTimeoutError::Timeout) => {}
+            Ok(Err(error)) => {
+                log::warn!("MQTT broker {host}:{port}: {error}");
+                thread::sleep(RECONNECT_DELAY);
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        let status = control.status();
+        if published_status.as_ref() != Some(&status) {
+            let payload = serde_json::to_string(&status)?;
+            match client.try_publish(topic("status"), QoS::AtLeastOnce, true, payload) {
+                Ok(()) => published_status = Some(status),
+                /* Requests are queued while reconnecting, publish again later */
+                Err(error) => log::debug!("MQTT status not published: {error}"),
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "mqtt")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn when_mqtt_feature_is_disabled_then_spawn_fails() {
+        let result = spawn(
+            ("broker.local".to_string(), 1883),
+            "syno-photo-frame".to_string(),
+            Control::default(),
+        );
+
+        assert!(result.is_err_and(|e| e.to_string().contains("mqtt feature")));
+    }
+}
@@ -0,0 +1,247 @@
+//! Photos fitted to the screen stored on disk between runs, see `--cache-dir`
+
+use std::{
+    collections::VecDeque,
+    ffi::OsStr,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use anyhow::Result;
+use image::{codecs::jpeg::JpegEncoder, DynamicImage};
+use serde::{Deserialize, Serialize};
+
+/// Quality of cached photos. Photos are already fitted to the screen, so the files stay small
+const JPEG_QUALITY: u8 = 90;
+
+const SETTINGS_FILE_NAME: &str = "settings.json";
+const PHOTO_EXTENSION: &str = "jpg";
+
+/// Options which the cached photos have been processed with. Photos cached with different settings
+/// are removed when the cache is opened
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CacheSettings {
+    pub screen_size: (u32, u32),
+    pub rotation: String,
+    /// Other options affecting the processed photos, e.g. background
+    pub processing: String,
+}
+
+/// Directory of processed photos, keyed by [crate::metadata::Metadata::cache_key]. The least
+/// recently used photos are removed once the total size of the files exceeds the limit
+#[derive(Debug)]
+pub struct PhotoCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    /// File names and sizes of cached photos, least recently used first
+    entries: Mutex<VecDeque<(String, u64)>>,
+}
+
+impl PhotoCache {
+    /// Opens the cache directory, creating it when needed, and removes photos cached with other
+    /// settings
+    pub fn open(dir: &Path, max_bytes: u64, settings: &CacheSettings) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let settings_path = dir.join(SETTINGS_FILE_NAME);
+        let cached_settings = File::open(&settings_path).ok().and_then(|file| {
+            serde_json::from_reader::<_, CacheSettings>(BufReader::new(file)).ok()
+        });
+        let mut entries = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension() != Some(OsStr::new(PHOTO_EXTENSION)) {
+                continue;
+            }
+            if cached_settings.as_ref() != Some(settings) {
+                fs::remove_file(&path)?;
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((
+                modified,
+                entry.file_name().to_string_lossy().into_owned(),
+                metadata.len(),
+            ));
+        }
+        if cached_settings.as_ref() != Some(settings) {
+            serde_json::to_writer(BufWriter::new(File::create(&settings_path)?), settings)?;
+        }
+        /* Least recently used is approximated by the time of caching across runs */
+        entries.sort();
+        let photo_cache = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            entries: Mutex::new(
+                entries
+                    .into_iter()
+                    .map(|(_, file_name, size)| (file_name, size))
+                    .collect(),
+            ),
+        };
+        photo_cache.evict();
+        Ok(photo_cache)
+    }
+
+    /// Returns the cached photo, or `None` when it is not cached or cannot be read
+    pub fn get(&self, key: &str) -> Option<DynamicImage> {
+        let file_name = file_name(key);
+        {
+            let mut entries = self.entries.lock().ok()?;
+            let index = entries.iter().position(|(name, _)| *name == file_name)?;
+            /* Becomes the most recently used */
+            let entry = entries.remove(index)?;
+            entries.push_back(entry);
+        }
+        match image::open(self.dir.join(&file_name)) {
+            Ok(image) => Some(image),
+            Err(error) => {
+                log::warn!("Cached photo {file_name}: {error}");
+                None
+            }
+        }
+    }
+
+    /// Stores a photo. Failures are logged, as the slideshow works without the cache
+    pub fn put(&self, key: &str, image: &DynamicImage) {
+        let file_name = file_name(key);
+        match self.write(&file_name, image) {
+            Ok(size) => {
+                if let Ok(mut entries) = self.entries.lock() {
+                    entries.retain(|(name, _)| *name != file_name);
+                    entries.push_back((file_name, size));
+                }
+                self.evict();
+            }
+            Err(error) => log::warn!("Caching photo {file_name}: {error}"),
+        }
+    }
+
+    /// Writes the file under a temporary name first, so that a photo is not cached partially when
+    /// the app gets terminated. Returns the size of the file
+    fn write(&self, file_name: &str, image: &DynamicImage) -> Result<u64> {
+        let temp_path = self.dir.join(format!(".{file_name}.tmp"));
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        image
+            .to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY))?;
+        drop(writer);
+        let size = fs::metadata(&temp_path)?.len();
+        fs::rename(&temp_path, self.dir.join(file_name))?;
+        Ok(size)
+    }
+
+    /// Removes the least recently used photos while the cache is over the size limit
+    fn evict(&self) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        let mut total_bytes = entries.iter().map(|(_, size)| size).sum::<u64>();
+        while total_bytes > self.max_bytes {
+            let Some((file_name, size)) = entries.pop_front() else {
+                break;
+            };
+            if let Err(error) = fs::remove_file(self.dir.join(&file_name)) {
+                log::warn!("Removing cached photo {file_name}: {error}");
+            }
+            total_bytes -= size;
+        }
+    }
+}
+
+/// Cache keys may contain characters not allowed in file names (e.g. paths of local photos), so
+/// files are named by a hash of the key
+fn file_name(key: &str) -> String {
+    /* 64-bit FNV-1a, stable across Rust versions unlike the std hasher */
+    let hash = key.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}.{PHOTO_EXTENSION}")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    #[test]
+    fn put_photo_can_be_read_back() {
+        let dir = temp_dir_path("put-get");
+        let cache = PhotoCache::open(&dir, u64::MAX, &new_settings((4, 2))).unwrap();
+
+        cache.put("42_abc", &DynamicImage::new_rgb8(4, 2));
+        let result = cache.get("42_abc");
+        let missing = cache.get("43_abc");
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            result.map(|image| (image.width(), image.height())),
+            Some((4, 2))
+        );
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn when_settings_change_then_cached_photos_are_removed() {
+        let dir = temp_dir_path("settings-change");
+        PhotoCache::open(&dir, u64::MAX, &new_settings((4, 2)))
+            .unwrap()
+            .put("42_abc", &DynamicImage::new_rgb8(4, 2));
+
+        let same_settings = PhotoCache::open(&dir, u64::MAX, &new_settings((4, 2)))
+            .unwrap()
+            .get("42_abc");
+        let other_settings = PhotoCache::open(&dir, u64::MAX, &new_settings((2, 4)))
+            .unwrap()
+            .get("42_abc");
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(same_settings.is_some());
+        assert!(other_settings.is_none());
+    }
+
+    #[test]
+    fn when_cache_exceeds_size_limit_then_least_recently_used_photos_are_removed() {
+        let dir = temp_dir_path("eviction");
+        let image = DynamicImage::new_rgb8(4, 2);
+        let cache = PhotoCache::open(&dir, u64::MAX, &new_settings((4, 2))).unwrap();
+        cache.put("1", &image);
+        let photo_size = fs::metadata(dir.join(file_name("1"))).unwrap().len();
+        let cache = PhotoCache::open(&dir, photo_size * 2, &new_settings((4, 2))).unwrap();
+
+        cache.put("2", &image);
+        /* Photo 1 becomes more recently used than photo 2 */
+        cache.get("1");
+        cache.put("3", &image);
+
+        let cached = ["1", "2", "3"].map(|key| dir.join(file_name(key)).exists());
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(cached, [true, false, true]);
+    }
+
+    #[test]
+    fn file_name_is_stable_hash_of_key() {
+        assert_eq!(file_name(""), "cbf29ce484222325.jpg");
+        assert_ne!(file_name("a/b"), file_name("a_b"));
+    }
+
+    fn new_settings(screen_size: (u32, u32)) -> CacheSettings {
+        CacheSettings {
+            screen_size,
+            rotation: "D0".to_string(),
+            processing: String::new(),
+        }
+    }
+
+    fn temp_dir_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "syno-photo-frame-photo-cache-{}-{name}",
+            process::id()
+        ))
+    }
+}
@@ -0,0 +1,237 @@
+//! Selection of displayed photos, by an external command or by file name patterns
+
+use std::{
+    collections::HashSet,
+    io::Write,
+    process::{Command, Stdio},
+    thread,
+};
+
+use anyhow::{bail, Result};
+use globset::GlobMatcher;
+
+use crate::metadata::{Metadata, MetadataRecord};
+
+/// Runs a shell command deciding which album photos are displayed. The command receives metadata
+/// of all photos on standard input, as a JSON array in the same format as `--json-metadata-dump`
+/// writes, and prints a JSON array of ids of the photos to display on standard output
+#[derive(Debug)]
+pub struct PhotoFilter {
+    command: String,
+}
+
+impl PhotoFilter {
+    pub fn new(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+        }
+    }
+
+    /// Keeps photos selected by the command, in their original order. When the command fails, all
+    /// photos are kept, so that a broken script does not stop the slideshow
+    pub fn filter<P: Metadata>(&self, photos: Vec<P>) -> Vec<P> {
+        match self.selected_ids(&photos) {
+            Ok(ids) => photos
+                .into_iter()
+                .filter(|photo| ids.contains(&photo.id()))
+                .collect(),
+            Err(error) => {
+                log::warn!("--photo-filter-script failed, displaying all photos: {error}");
+                photos
+            }
+        }
+    }
+
+    fn selected_ids<P: Metadata>(&self, photos: &[P]) -> Result<HashSet<String>> {
+        let records = photos.iter().map(MetadataRecord::from).collect::<Vec<_>>();
+        let input = serde_json::to_vec(&records)?;
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().expect("stdin should be piped");
+        /* Written from another thread, otherwise a command printing before reading all of its
+         * input could block both processes */
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        if let Ok(Err(error)) = writer.join() {
+            /* The command does not have to read its input */
+            log::debug!("--photo-filter-script input: {error}");
+        }
+        if !output.status.success() {
+            bail!("{}", output.status);
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+}
+
+/// Shows or hides photos by their file names, see `--include-glob` and `--exclude-glob`
+#[derive(Debug)]
+pub struct FileNameFilter {
+    include: Vec<GlobMatcher>,
+    exclude: Vec<GlobMatcher>,
+}
+
+impl FileNameFilter {
+    /// Returns `None` when there are no patterns
+    pub fn new(include: &[GlobMatcher], exclude: &[GlobMatcher]) -> Option<Self> {
+        (!include.is_empty() || !exclude.is_empty()).then(|| Self {
+            include: include.to_vec(),
+            exclude: exclude.to_vec(),
+        })
+    }
+
+    /// Keeps photos whose file names match any include pattern (when there are any) and no
+    /// exclude pattern, in their original order. Exclude patterns take precedence. Photos without
+    /// a known file name are kept only when there are no include patterns
+    pub fn filter<P: Metadata>(&self, photos: Vec<P>) -> Vec<P> {
+        photos
+            .into_iter()
+            .filter(|photo| self.is_included(photo.file_name().as_deref()))
+            .collect()
+    }
+
+    fn is_included(&self, file_name: Option<&str>) -> bool {
+        let matches_any =
+            |globs: &[GlobMatcher], name: &str| globs.iter().any(|glob| glob.is_match(name));
+        match file_name {
+            Some(name) => {
+                (self.include.is_empty() || matches_any(&self.include, name))
+                    && !matches_any(&self.exclude, name)
+            }
+            None => self.include.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process};
+
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    use crate::api_client::manifest_client::ManifestPhoto;
+
+    #[test]
+    fn filter_keeps_photos_selected_by_command_in_original_order() {
+        let filter = PhotoFilter::new(r#"cat > /dev/null; echo '["c.jpg", "a.jpg"]'"#);
+
+        let result = filter.filter(new_photos());
+
+        assert_eq!(ids(&result), ["a.jpg", "c.jpg"]);
+    }
+
+    #[test]
+    fn filter_passes_photo_metadata_as_json_to_command() {
+        let input = temp_file_path("input");
+        let filter = PhotoFilter::new(&format!("cat > {}; echo '[]'", input.to_string_lossy()));
+
+        let result = filter.filter(new_photos());
+
+        let input_json: serde_json::Value =
+            serde_json::from_slice(&fs::read(&input).unwrap()).unwrap();
+        fs::remove_file(&input).unwrap();
+        assert!(result.is_empty());
+        assert_eq!(
+            input_json[0],
+            serde_json::json!({
+                "id": "a.jpg",
+                "date": "2024-05-01T14:30:00",
+                "location": "Gdansk",
+                "file_name": "a.jpg",
+            })
+        );
+        assert_eq!(input_json.as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn when_command_fails_then_filter_keeps_all_photos() {
+        for command in ["exit 1", "echo 'not json'"] {
+            let filter = PhotoFilter::new(command);
+
+            let result = filter.filter(new_photos());
+
+            assert_eq!(ids(&result), ["a.jpg", "b.jpg", "c.jpg"]);
+        }
+    }
+
+    #[test]
+    fn file_name_filter_keeps_photos_matching_include_and_not_exclude_patterns() {
+        let photos = || new_photos_named(&["a.jpg", "B.JPG", "scan_1.jpg", "c.png"]);
+        test_case(photos(), &["*.jpg"], &[], &["a.jpg", "B.JPG", "scan_1.jpg"]);
+        test_case(photos(), &[], &["scan_*"], &["a.jpg", "B.JPG", "c.png"]);
+        /* Exclude patterns take precedence */
+        test_case(photos(), &["*.jpg"], &["scan_*", "b.*"], &["a.jpg"]);
+        test_case(photos(), &["*.jpg", "*.png"], &["*"], &[]);
+
+        fn test_case(
+            photos: Vec<ManifestPhoto>,
+            include: &[&str],
+            exclude: &[&str],
+            expected: &[&str],
+        ) {
+            let filter = FileNameFilter::new(&matchers(include), &matchers(exclude)).unwrap();
+
+            let result = filter.filter(photos);
+
+            assert_eq!(ids(&result), expected, "{include:?} {exclude:?}");
+        }
+    }
+
+    #[test]
+    fn file_name_filter_is_none_without_patterns() {
+        assert!(FileNameFilter::new(&[], &[]).is_none());
+    }
+
+    #[test]
+    fn photos_without_file_name_are_kept_only_without_include_patterns() {
+        let filter = FileNameFilter::new(&[], &matchers(&["scan_*"])).unwrap();
+        assert!(filter.is_included(None));
+
+        let filter = FileNameFilter::new(&matchers(&["*.jpg"]), &[]).unwrap();
+        assert!(!filter.is_included(None));
+    }
+
+    fn matchers(patterns: &[&str]) -> Vec<GlobMatcher> {
+        patterns
+            .iter()
+            .map(|pattern| crate::cli::try_parse_glob(pattern).unwrap())
+            .collect()
+    }
+
+    fn new_photos_named(paths: &[&str]) -> Vec<ManifestPhoto> {
+        paths
+            .iter()
+            .map(|path| ManifestPhoto {
+                path: PathBuf::from(path),
+                date: None,
+                location: None,
+            })
+            .collect()
+    }
+
+    fn new_photos() -> Vec<ManifestPhoto> {
+        ["a.jpg", "b.jpg", "c.jpg"]
+            .into_iter()
+            .map(|path| ManifestPhoto {
+                path: PathBuf::from(path),
+                date: NaiveDate::from_ymd_opt(2024, 5, 1)
+                    .unwrap()
+                    .and_hms_opt(14, 30, 0),
+                location: Some("Gdansk".to_string()),
+            })
+            .collect()
+    }
+
+    fn ids(photos: &[ManifestPhoto]) -> Vec<String> {
+        photos.iter().map(Metadata::id).collect()
+    }
+
+    fn temp_file_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("syno-photo-frame-filter-{}-{name}", process::id()))
+    }
+}
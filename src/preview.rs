@@ -0,0 +1,67 @@
+//! Preview of the next photo, see `--show-preview`
+
+use crate::{
+    cli::{Corner, Rotation},
+    render::Rect,
+};
+
+/// Fraction of the screen width and height taken by the preview
+const PREVIEW_SCALE: u32 = 5;
+
+/// Returns the rectangle of the canvas in which the next photo is previewed, in the bottom-right
+/// corner of the (rotated) screen
+pub fn preview_rect((w, h): (u32, u32), rotation: Rotation) -> Rect {
+    let (preview_w, preview_h) = ((w / PREVIEW_SCALE).max(1), (h / PREVIEW_SCALE).max(1));
+    /* Small margin separating the preview from the screen edges */
+    let margin = w.min(h) / 50;
+    let right = w.saturating_sub(preview_w + margin) as i32;
+    let bottom = h.saturating_sub(preview_h + margin) as i32;
+    let margin = margin as i32;
+    let (x, y) = match Corner::BottomRight.rotate(rotation) {
+        Corner::TopLeft => (margin, margin),
+        Corner::TopRight => (right, margin),
+        Corner::BottomRight => (right, bottom),
+        Corner::BottomLeft => (margin, bottom),
+    };
+    Rect::new(x, y, preview_w, preview_h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN_SIZE: (u32, u32) = (1920, 1080);
+
+    #[test]
+    fn preview_is_in_bottom_right_corner_of_unrotated_screen() {
+        let result = preview_rect(SCREEN_SIZE, Rotation::D0);
+
+        assert_eq!(result, Rect::new(1515, 843, 384, 216));
+    }
+
+    #[test]
+    fn preview_follows_bottom_right_corner_of_rotated_screen() {
+        assert_eq!(
+            preview_rect(SCREEN_SIZE, Rotation::D90),
+            Rect::new(21, 843, 384, 216)
+        );
+        assert_eq!(
+            preview_rect(SCREEN_SIZE, Rotation::D180),
+            Rect::new(21, 21, 384, 216)
+        );
+        assert_eq!(
+            preview_rect(SCREEN_SIZE, Rotation::D270),
+            Rect::new(1515, 21, 384, 216)
+        );
+    }
+
+    #[test]
+    fn preview_fits_on_screen() {
+        for screen_size in [(1, 1), (4, 3), (800, 480)] {
+            let Rect { x, y, w, h } = preview_rect(screen_size, Rotation::D0);
+
+            assert!(x >= 0 && y >= 0);
+            assert!(x as u32 + w <= screen_size.0 && y as u32 + h <= screen_size.1);
+        }
+    }
+}
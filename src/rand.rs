@@ -12,6 +12,7 @@ pub trait Random {
     }
 }
 
+#[derive(Clone)]
 pub struct RandomImpl;
 
 impl Random for RandomImpl {}
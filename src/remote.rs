@@ -0,0 +1,150 @@
+//! HTTP endpoint for controlling the slideshow remotely, e.g. from home automation, see
+//! `--control-port`
+//!
+//! - `POST /next`, `/pause`, `/resume`, `/toggle-pause`, `/reload` and `/quit` send a
+//!   [crate::control::Command] to the slideshow
+//! - `GET /status` returns JSON with the state of the slideshow, as returned by
+//!   [crate::control::Control::status]
+//!
+//! There is no `/toggle-info`, as no photo info is displayed on screen to toggle. Use
+//! `--on-photo` to pass it to another program
+
+use std::net::IpAddr;
+
+use anyhow::Result;
+
+use crate::control::Control;
+
+/// Starts listening for requests on `address` and `port` in a background thread, sending
+/// commands to the slideshow through `control`. Fails when the server cannot be started, or
+/// right away when the app was built without the `remote` feature
+#[cfg(feature = "remote")]
+pub fn spawn((address, port): (IpAddr, u16), control: Control) -> Result<()> {
+    use anyhow::anyhow;
+    use tiny_http::{Header, Response, Server};
+
+    let server = Server::http((address, port))
+        .map_err(|error| anyhow!("Control server on {address}:{port}: {error}"))?;
+    log::info!("Control server listening on {address}:{port}");
+    std::thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let (status_code, body) = handle(request.method(), request.url(), &control);
+            let mut response = Response::from_string(body).with_status_code(status_code);
+            if status_code == 200 {
+                response.add_header(
+                    Header::from_bytes("Content-Type", "application/json")
+                        .expect("header should be valid"),
+                );
+            }
+            if let Err(error) = request.respond(response) {
+                log::warn!("Control server: {error}");
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "remote"))]
+pub fn spawn(_address: (IpAddr, u16), _control: Control) -> Result<()> {
+    anyhow::bail!("--control-port is not supported, the app was built without the remote feature")
+}
+
+/// Returns status code and body of the response
+#[cfg(feature = "remote")]
+fn handle(method: &tiny_http::Method, url: &str, control: &Control) -> (u16, String) {
+    use tiny_http::Method;
+
+    use crate::control::Command;
+
+    let path = url.split('?').next().unwrap_or_default();
+    let command = path.strip_prefix('/').and_then(Command::parse);
+    match (method, command, path) {
+        (Method::Post, Some(command), _) => {
+            control.send(command);
+            (204, String::new())
+        }
+        (Method::Get, _, "/status") => match serde_json::to_string(&control.status()) {
+            Ok(json) => (200, json),
+            Err(error) => (500, error.to_string()),
+        },
+        (_, Some(_), _) | (_, _, "/status") => (405, String::new()),
+        _ => (404, String::new()),
+    }
+}
+
+#[cfg(all(test, feature = "remote"))]
+mod tests {
+    use tiny_http::Method;
+
+    use crate::{control::Command, metadata::PhotoInfo};
+
+    use super::*;
+
+    #[test]
+    fn post_requests_send_commands_to_slideshow() {
+        let control = Control::default();
+
+        for path in [
+            "/pause",
+            "/resume",
+            "/toggle-pause",
+            "/next?from=phone",
+            "/reload",
+            "/quit",
+        ] {
+            assert_eq!(handle(&Method::Post, path, &control).0, 204, "{path}");
+        }
+
+        assert_eq!(
+            control.take_commands(),
+            [
+                Command::Pause,
+                Command::Resume,
+                Command::TogglePause,
+                Command::Next,
+                Command::Reload,
+                Command::Quit
+            ]
+        );
+    }
+
+    #[test]
+    fn get_status_reports_displayed_photo() {
+        let control = Control::default();
+        assert_eq!(
+            handle(&Method::Get, "/status", &control),
+            (
+                200,
+                r#"{"paused":false,"id":null,"position":null,"count":null,"caption":null}"#
+                    .to_string()
+            )
+        );
+
+        control.current_photo.lock().unwrap().info = Some(PhotoInfo {
+            id: "42".to_string(),
+            position: Some((3, 318)),
+            caption: Some("Summer holidays".to_string()),
+            ..PhotoInfo::default()
+        });
+        control.set_paused(true);
+
+        assert_eq!(
+            handle(&Method::Get, "/status", &control),
+            (
+                200,
+                r#"{"paused":true,"id":"42","position":3,"count":318,"caption":"Summer holidays"}"#
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_requests_are_rejected() {
+        let control = Control::default();
+
+        assert_eq!(handle(&Method::Get, "/next", &control).0, 405);
+        assert_eq!(handle(&Method::Post, "/status", &control).0, 405);
+        assert_eq!(handle(&Method::Post, "/no-such-command", &control).0, 404);
+        assert!(control.take_commands().is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Display output abstraction
+//!
+//! The slideshow draws through the [Renderer] trait only. The default implementation uses SDL
+//! (see `sdl` module, enabled by the `sdl` feature), but an application embedding the slideshow
+//! can implement [Renderer] for other kinds of output, e.g. a framebuffer or an e-ink display, and
+//! pass it to [crate::run].
+
+use anyhow::Result;
+
+use crate::{cli::Rotation, QuitEvent};
+
+/// Display output used by the slideshow
+///
+/// A renderer holds two off-screen images ("textures") of the screen size, see [TextureIndex],
+/// and a back buffer ("canvas") which becomes visible on screen when presented. Every frame is
+/// drawn by copying textures and filling the canvas, and finally calling
+/// [Renderer::present_canvas]. Renderers which cannot blend images (e.g. e-ink displays) may
+/// ignore alpha, in which case transition effects degrade to a simple switch to the next photo.
+#[cfg_attr(test, mockall::automock)]
+pub trait Renderer {
+    /// Gets screen size
+    fn size(&self) -> (u32, u32);
+
+    /// Replaces the contents of a texture. `image_data` contains 8-bit RGB pixels, row by row,
+    /// of an image exactly matching the screen size
+    fn update_texture(&mut self, image_data: &[u8], index: TextureIndex) -> Result<()>;
+
+    /// Sets opacity used when the texture is copied to the canvas. 255 is fully opaque
+    fn set_texture_alpha(&mut self, alpha: u8, index: TextureIndex);
+
+    /// Draws a texture over the whole canvas, blending it according to its alpha
+    fn copy_texture_to_canvas(&mut self, index: TextureIndex) -> Result<()>;
+
+    /// Draws a texture scaled by `zoom` around the center of the canvas, blending it according to
+    /// its alpha. Parts not fitting on the canvas are cut off. Renderers which cannot scale
+    /// textures may keep the default implementation, which ignores the zoom
+    fn copy_texture_to_canvas_zoomed(&mut self, index: TextureIndex, _zoom: f64) -> Result<()> {
+        self.copy_texture_to_canvas(index)
+    }
+
+    /// Draws a texture scaled down (or up) into a rectangle of the canvas, blending it according
+    /// to its alpha. Used for overlays like the preview of the next photo. Renderers which cannot
+    /// scale textures may keep the default implementation, which draws nothing
+    fn copy_texture_to_canvas_rect(&mut self, _index: TextureIndex, _rect: Rect) -> Result<()> {
+        Ok(())
+    }
+
+    /// Draws a part of a texture (`src`, in texture pixels) into a rectangle of the canvas (`dst`)
+    /// of the same size, blending it according to its alpha. Used by the slide transition.
+    /// Renderers which cannot copy parts of textures may keep the default implementation, which
+    /// draws the whole texture, so that the transition degrades to a simple switch
+    fn copy_texture_region(&mut self, index: TextureIndex, _src: Rect, _dst: Rect) -> Result<()> {
+        self.copy_texture_to_canvas(index)
+    }
+
+    /// Swaps current texture with the next one
+    fn swap_textures(&mut self);
+
+    /// Fills the whole canvas with a color, blending it according to its alpha
+    fn fill_canvas(&mut self, color: Color) -> Result<()>;
+
+    /// Fills a rectangle of the canvas with a color, blending it according to its alpha. Used for
+    /// overlays like the alignment grid. Renderers which cannot draw shapes may keep the default
+    /// implementation, which draws nothing
+    fn fill_canvas_rect(&mut self, _rect: Rect, _color: Color) -> Result<()> {
+        Ok(())
+    }
+
+    /// Displays the canvas on screen
+    fn present_canvas(&mut self);
+
+    /// Called regularly by the slideshow. Returns [QuitEvent] when the app should terminate, e.g.
+    /// because the window was closed
+    fn handle_quit_event(&mut self) -> Result<(), QuitEvent>;
+
+    /// Returns keys pressed since the last call, in order. Called regularly by the slideshow after
+    /// [Renderer::handle_quit_event]. Renderers without a keyboard may keep the default
+    /// implementation
+    fn pressed_keys(&mut self) -> Vec<Key> {
+        vec![]
+    }
+}
+
+/// Index of a texture to operate on (used mainly by transition effects)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureIndex {
+    /// Currently active texture containing displayed image
+    Current,
+    /// Texture containing the next image to display
+    Next,
+}
+
+/// Key pressed on a keyboard attached to the display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// Key producing a character, e.g. a letter (lowercase), a digit or a space
+    Char(char),
+    /// Left arrow
+    Left,
+    /// Right arrow
+    Right,
+    /// Any other key
+    Other,
+}
+
+/// Rectangle on the canvas, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
+impl Rect {
+    pub const fn new(x: i32, y: i32, w: u32, h: u32) -> Self {
+        Self { x, y, w, h }
+    }
+
+    /// Maps a rectangle of the upright screen of `upright_size` to the canvas, in the same way as
+    /// photos are rotated (see [crate::img::Framed::rotate])
+    pub(crate) fn rotate(self, (upright_w, upright_h): (u32, u32), rotation: Rotation) -> Rect {
+        let Rect { x, y, w, h } = self;
+        let (upright_w, upright_h) = (upright_w as i32, upright_h as i32);
+        match rotation {
+            Rotation::D0 => self,
+            Rotation::D90 => Rect::new(upright_h - y - h as i32, x, h, w),
+            Rotation::D180 => Rect::new(upright_w - x - w as i32, upright_h - y - h as i32, w, h),
+            Rotation::D270 => Rect::new(y, upright_w - x - w as i32, h, w),
+        }
+    }
+}
+
+/// RGBA color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_maps_rect_like_photos_are_rotated() {
+        /* Upright screen is 4x6, i.e. the canvas is 6x4 when rotated by 90 or 270 degrees */
+        let rect = Rect::new(1, 0, 2, 1);
+
+        assert_eq!(rect.rotate((4, 6), Rotation::D0), rect);
+        assert_eq!(rect.rotate((4, 6), Rotation::D90), Rect::new(5, 1, 1, 2));
+        assert_eq!(rect.rotate((4, 6), Rotation::D180), Rect::new(1, 5, 2, 1));
+        assert_eq!(rect.rotate((4, 6), Rotation::D270), Rect::new(0, 1, 1, 2));
+    }
+}
@@ -1,44 +1,28 @@
-//! Rendering
+//! SDL implementation of [Renderer]
 
-pub(crate) use sdl2::pixels::Color;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
 use anyhow::{anyhow, Result};
 
 use sdl2::{
     event::Event,
-    pixels::PixelFormatEnum,
+    keyboard::Keycode,
+    pixels::{self, PixelFormatEnum},
+    rect::Rect,
     render::{BlendMode, Canvas, Texture, TextureCreator},
     video::{DisplayMode, Window, WindowContext},
     EventPump, VideoSubsystem,
 };
 
-use crate::QuitEvent;
-
-/// Isolates [sdl2::Sdl] context for testing
-#[cfg_attr(test, mockall::automock)]
-pub trait Sdl {
-    /// Gets screen size
-    fn size(&self) -> (u32, u32);
-    fn update_texture(&mut self, image_data: &[u8], index: TextureIndex) -> Result<()>;
-    fn set_texture_alpha(&mut self, alpha: u8, index: TextureIndex);
-    fn copy_texture_to_canvas(&mut self, index: TextureIndex) -> Result<()>;
-    /// Swaps current texture with the next one
-    fn swap_textures(&mut self);
-    fn fill_canvas(&mut self, color: Color) -> Result<()>;
-    fn present_canvas(&mut self);
-    fn handle_quit_event(&mut self) -> Result<(), QuitEvent>;
-}
-
-/// Index of a texture to operate on (used mainly by transition effects)
-#[derive(Debug, PartialEq, Eq)]
-pub enum TextureIndex {
-    /// Currently active texture containing displayed image
-    Current,
-    /// Texture containing the next image to display
-    Next,
-}
+use crate::{
+    render::{self, Color, Key, Renderer, TextureIndex},
+    QuitEvent,
+};
 
-impl Sdl for SdlWrapper<'_> {
+impl Renderer for SdlWrapper<'_> {
     fn size(&self) -> (u32, u32) {
         self.size
     }
@@ -57,33 +41,139 @@ impl Sdl for SdlWrapper<'_> {
             .map_err(|s| anyhow!(s))
     }
 
+    fn copy_texture_to_canvas_zoomed(&mut self, index: TextureIndex, zoom: f64) -> Result<()> {
+        let (w, h) = self.size;
+        let zoomed = Rect::from_center(
+            ((w / 2) as i32, (h / 2) as i32),
+            (w as f64 * zoom).round() as u32,
+            (h as f64 * zoom).round() as u32,
+        );
+        self.canvas
+            .copy(&self.textures[self.texture_index(index)], None, zoomed)
+            .map_err(|s| anyhow!(s))
+    }
+
+    fn copy_texture_to_canvas_rect(
+        &mut self,
+        index: TextureIndex,
+        render::Rect { x, y, w, h }: render::Rect,
+    ) -> Result<()> {
+        self.canvas
+            .copy(
+                &self.textures[self.texture_index(index)],
+                None,
+                Rect::new(x, y, w, h),
+            )
+            .map_err(|s| anyhow!(s))
+    }
+
+    fn copy_texture_region(
+        &mut self,
+        index: TextureIndex,
+        src: render::Rect,
+        dst: render::Rect,
+    ) -> Result<()> {
+        self.canvas
+            .copy(
+                &self.textures[self.texture_index(index)],
+                Rect::new(src.x, src.y, src.w, src.h),
+                Rect::new(dst.x, dst.y, dst.w, dst.h),
+            )
+            .map_err(|s| anyhow!(s))
+    }
+
     fn swap_textures(&mut self) {
         self.current_texture = (self.current_texture + 1) % self.textures.len();
     }
 
-    fn fill_canvas(&mut self, color: Color) -> Result<()> {
-        self.canvas.set_draw_color(color);
+    fn fill_canvas(&mut self, Color { r, g, b, a }: Color) -> Result<()> {
+        self.canvas.set_draw_color(pixels::Color::RGBA(r, g, b, a));
         self.canvas.fill_rect(None).map_err(|s| anyhow!(s))
     }
 
+    fn fill_canvas_rect(
+        &mut self,
+        render::Rect { x, y, w, h }: render::Rect,
+        Color { r, g, b, a }: Color,
+    ) -> Result<()> {
+        self.canvas.set_draw_color(pixels::Color::RGBA(r, g, b, a));
+        self.canvas
+            .fill_rect(Rect::new(x, y, w, h))
+            .map_err(|s| anyhow!(s))
+    }
+
     fn present_canvas(&mut self) {
         self.canvas.present()
     }
 
     fn handle_quit_event(&mut self) -> Result<(), QuitEvent> {
-        let exit_requested = self.events.poll_iter().any(|e| match e {
-            event @ (Event::Quit { .. } | Event::AppTerminating { .. }) => {
-                log::debug!("SDL event received: {event:?}");
-                true
+        let mut exit_requested = false;
+        /* Drain all pending events, key presses are kept for Renderer::pressed_keys */
+        for event in self.events.poll_iter() {
+            match event {
+                Event::Quit { .. } | Event::AppTerminating { .. } => {
+                    log::debug!("SDL event received: {event:?}");
+                    exit_requested = true;
+                }
+                _ if self.screensaver_mode && is_screensaver_exit_event(&event) => {
+                    log::debug!("SDL event received in screensaver mode: {event:?}");
+                    exit_requested = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => self.pressed_keys.push(to_key(keycode)),
+                _ => {}
             }
-            _ => false,
-        });
+        }
+        if self.quit_requested.load(Ordering::Relaxed) {
+            log::debug!("Quit requested");
+            exit_requested = true;
+        }
         if exit_requested {
             Err(QuitEvent)
         } else {
             Ok(())
         }
     }
+
+    fn pressed_keys(&mut self) -> Vec<Key> {
+        std::mem::take(&mut self.pressed_keys)
+    }
+}
+
+/// Whether an input event should end the slideshow in screensaver mode
+fn is_screensaver_exit_event(event: &Event) -> bool {
+    /* SDL reports mouse motion e.g. when the window appears under the cursor or when the cursor
+     * gets hidden, so motion at startup and small jitter is ignored */
+    const STARTUP_GRACE_MS: u32 = 2000;
+    const MIN_MOUSE_MOTION: i32 = 5;
+    match *event {
+        Event::KeyDown { .. } | Event::MouseButtonDown { .. } | Event::FingerDown { .. } => true,
+        Event::MouseMotion {
+            timestamp,
+            xrel,
+            yrel,
+            ..
+        } => timestamp >= STARTUP_GRACE_MS && xrel.abs() + yrel.abs() >= MIN_MOUSE_MOTION,
+        _ => false,
+    }
+}
+
+fn to_key(keycode: Keycode) -> Key {
+    match keycode {
+        Keycode::Left => return Key::Left,
+        Keycode::Right => return Key::Right,
+        Keycode::Space => return Key::Char(' '),
+        _ => {}
+    }
+    let name = keycode.name();
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Key::Char(c.to_ascii_lowercase()),
+        _ => Key::Other,
+    }
 }
 
 /// Container for components from [sdl2::Sdl]
@@ -92,6 +182,12 @@ pub struct SdlWrapper<'a> {
     textures: [Texture<'a>; 2],
     current_texture: usize,
     events: EventPump,
+    /// Key presses received by [Renderer::handle_quit_event] and not yet taken
+    pressed_keys: Vec<Key>,
+    /// Quit on any keyboard, mouse or touch input
+    screensaver_mode: bool,
+    /// Set from outside of the event loop to quit, e.g. by a signal handler
+    quit_requested: Arc<AtomicBool>,
     size: (u32, u32),
     /// Number of bytes in a row of pixel data, in other words image width multiplied by bytes-per-pixel
     pitch: usize,
@@ -99,7 +195,11 @@ pub struct SdlWrapper<'a> {
 
 impl<'a> SdlWrapper<'a> {
     pub fn new(canvas: Canvas<Window>, textures: [Texture<'a>; 2], events: EventPump) -> Self {
-        let size = canvas.window().size();
+        /* Logical size is set when rendering at reduced resolution, see set_render_size */
+        let size = match canvas.logical_size() {
+            (0, 0) => canvas.window().size(),
+            logical_size => logical_size,
+        };
         let (w, ..) = size;
         const BYTE_SIZE_PER_PIXEL: usize = 3;
         SdlWrapper {
@@ -107,11 +207,25 @@ impl<'a> SdlWrapper<'a> {
             textures,
             current_texture: 0,
             events,
+            pressed_keys: vec![],
+            screensaver_mode: false,
+            quit_requested: Arc::default(),
             size,
             pitch: w as usize * BYTE_SIZE_PER_PIXEL,
         }
     }
 
+    pub fn with_screensaver_mode(mut self, screensaver_mode: bool) -> Self {
+        self.screensaver_mode = screensaver_mode;
+        self
+    }
+
+    /// Makes [Renderer::handle_quit_event] return [QuitEvent] once `quit_requested` is set
+    pub fn with_quit_flag(mut self, quit_requested: Arc<AtomicBool>) -> Self {
+        self.quit_requested = quit_requested;
+        self
+    }
+
     fn texture_index(&self, index: TextureIndex) -> usize {
         match index {
             TextureIndex::Current => self.current_texture,
@@ -121,7 +235,12 @@ impl<'a> SdlWrapper<'a> {
 }
 
 /// Initializes SDL video subsystem. **Must be called before using any other function in this module**
+///
+/// Only the video subsystem (and the events subsystem, which SDL initializes together with it) is
+/// initialized. In particular audio is not, so missing audio devices on headless setups do not
+/// matter
 pub fn init_video() -> Result<VideoSubsystem> {
+    /* Unlike SDL_INIT_EVERYTHING, this initializes no subsystem by itself (SDL_Init(0)) */
     sdl2::init()
         .map_err(|s| anyhow!(s))?
         .video()
@@ -150,12 +269,141 @@ pub fn create_canvas(video: &VideoSubsystem, (w, h): (u32, u32)) -> Result<Canva
     Ok(canvas)
 }
 
+/// Returns the size photos are processed and rendered at, when rendering at a fraction of the
+/// screen resolution
+pub fn render_size((w, h): (u32, u32), scale: f64) -> (u32, u32) {
+    let scaled = |dimension: u32| ((dimension as f64 * scale).round() as u32).max(1);
+    (scaled(w), scaled(h))
+}
+
+/// Makes the canvas render at `size`, upscaled (or downscaled) to the window size when presented
+pub fn set_render_size(canvas: &mut Canvas<Window>, (w, h): (u32, u32)) -> Result<()> {
+    if canvas.window().size() == (w, h) {
+        return Ok(());
+    }
+    sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "linear");
+    canvas.set_logical_size(w, h)?;
+    Ok(())
+}
+
 /// Creates a texture which will contain rendered images
 pub fn create_texture(
     texture_creator: &TextureCreator<WindowContext>,
     (w, h): (u32, u32),
-) -> Result<Texture> {
+) -> Result<Texture<'_>> {
     let mut texture = texture_creator.create_texture_static(PixelFormatEnum::RGB24, w, h)?;
     texture.set_blend_mode(BlendMode::Blend);
     Ok(texture)
 }
+
+#[cfg(test)]
+mod tests {
+    use sdl2::{
+        keyboard::Mod,
+        mouse::{MouseButton, MouseState},
+    };
+
+    use super::*;
+
+    #[test]
+    fn render_size_is_scaled_screen_size() {
+        assert_eq!(render_size((1920, 1080), 1.0), (1920, 1080));
+        assert_eq!(render_size((1920, 1080), 0.5), (960, 540));
+        assert_eq!(render_size((1366, 768), 0.33), (451, 253));
+        assert_eq!(render_size((1920, 1080), 0.0001), (1, 1));
+    }
+
+    #[test]
+    fn to_key_maps_slideshow_control_keys() {
+        assert_eq!(to_key(Keycode::Space), Key::Char(' '));
+        assert_eq!(to_key(Keycode::Left), Key::Left);
+        assert_eq!(to_key(Keycode::Right), Key::Right);
+    }
+
+    #[test]
+    fn in_screensaver_mode_key_press_click_and_touch_are_exit_events() {
+        let events = [
+            Event::KeyDown {
+                timestamp: 0,
+                window_id: 1,
+                keycode: Some(Keycode::Space),
+                scancode: None,
+                keymod: Mod::empty(),
+                repeat: false,
+            },
+            Event::MouseButtonDown {
+                timestamp: 0,
+                window_id: 1,
+                which: 0,
+                mouse_btn: MouseButton::Left,
+                clicks: 1,
+                x: 10,
+                y: 10,
+            },
+            Event::FingerDown {
+                timestamp: 0,
+                touch_id: 0,
+                finger_id: 0,
+                x: 0.5,
+                y: 0.5,
+                dx: 0.0,
+                dy: 0.0,
+                pressure: 1.0,
+            },
+        ];
+
+        for event in events {
+            assert!(is_screensaver_exit_event(&event), "{event:?}");
+        }
+    }
+
+    #[test]
+    fn in_screensaver_mode_mouse_motion_is_exit_event_after_startup() {
+        assert!(is_screensaver_exit_event(&new_mouse_motion(3000, 10, 0)));
+        assert!(is_screensaver_exit_event(&new_mouse_motion(3000, -3, -3)));
+    }
+
+    #[test]
+    fn in_screensaver_mode_spurious_mouse_motion_is_not_exit_event() {
+        /* At startup */
+        assert!(!is_screensaver_exit_event(&new_mouse_motion(100, 50, 50)));
+        /* Jitter */
+        assert!(!is_screensaver_exit_event(&new_mouse_motion(3000, 1, -1)));
+    }
+
+    #[test]
+    fn in_screensaver_mode_other_events_are_not_exit_events() {
+        let events = [
+            Event::KeyUp {
+                timestamp: 3000,
+                window_id: 1,
+                keycode: Some(Keycode::Space),
+                scancode: None,
+                keymod: Mod::empty(),
+                repeat: false,
+            },
+            Event::Window {
+                timestamp: 3000,
+                window_id: 1,
+                win_event: sdl2::event::WindowEvent::Exposed,
+            },
+        ];
+
+        for event in events {
+            assert!(!is_screensaver_exit_event(&event), "{event:?}");
+        }
+    }
+
+    fn new_mouse_motion(timestamp: u32, xrel: i32, yrel: i32) -> Event {
+        Event::MouseMotion {
+            timestamp,
+            window_id: 1,
+            which: 0,
+            mousestate: MouseState::from_sdl_state(0),
+            x: 100,
+            y: 100,
+            xrel,
+            yrel,
+        }
+    }
+}
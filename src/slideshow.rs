@@ -1,17 +1,28 @@
-use std::time::Duration;
+use std::{
+    collections::{HashSet, VecDeque},
+    mem,
+    path::Path,
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::{Duration, SystemTime},
+};
 
 #[cfg(test)]
 use crate::test_helpers::fake_sleep as thread_sleep;
+#[cfg(test)]
+use mock_instant::Instant;
 #[cfg(not(test))]
-use std::thread::sleep as thread_sleep;
+use std::{thread::sleep as thread_sleep, time::Instant};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use bytes::Bytes;
 
 use crate::{
-    api_client::ApiClient,
-    cli::{Order, SourceSize},
-    http::{InvalidHttpResponse, StatusCode},
+    api_client::{manifest_client::ManifestPhoto, ApiClient, SortBy},
+    cli::{Order, SourceSize, UndatedPhotos},
+    http::{self, InvalidHttpResponse, StatusCode},
+    metadata::{self, Metadata, PhotoInfo},
+    metadata_cache::MetadataCache,
+    photo_filter::{FileNameFilter, PhotoFilter},
     rand::Random,
 };
 
@@ -25,8 +36,54 @@ pub struct Slideshow<A: ApiClient, R> {
     order: Order,
     random_start: bool,
     source_size: SourceSize,
+    /// Number of times the display sequence has been initialized
+    pass: usize,
+    /// Number of photos in the current pass
+    pass_len: usize,
+    /// Ids of photos in the current pass, to recognize photos added to the album on reload
+    pass_ids: HashSet<String>,
+    /// Last pass in which a photo has been fetched successfully
+    fetched_pass: Option<usize>,
+    /// Album size above which a memory usage warning is logged
+    large_album_warn: usize,
+    /// Set once the large album warning is logged, so that it's not repeated on every pass
+    large_album_warned: bool,
+    bandwidth_budget: Option<BandwidthBudget>,
+    /// Interrupts waiting for the bandwidth budget once its sender is dropped
+    stop_receiver: Option<Receiver<()>>,
+    /// Album metadata fetched in the background for the next pass, see
+    /// [Slideshow::metadata_refresh_due]
+    next_pass: Option<Vec<A::Photo>>,
+    refresh_requested: bool,
+    /// Set by [Slideshow::request_reload]
+    reload_requested: bool,
+    photo_filter: Option<PhotoFilter>,
+    file_name_filter: Option<FileNameFilter>,
+    undated_photos: UndatedPhotos,
+    metadata_cache: Option<MetadataCache>,
+    /// Delay before fetching the next photo when a photo has been removed from the album
+    skip_sleep: Duration,
+    max_retries: u32,
+    /// Like `max_retries`, but for fetching album metadata
+    list_retries: u32,
+    /// Number of photos displayed last which are moved to the end of the next pass in random order
+    no_repeat_window: usize,
+    /// Ids of the last `no_repeat_window` selected photos, the most recent last
+    recently_selected: VecDeque<String>,
 }
 
+pub const DEFAULT_LARGE_ALBUM_WARN: usize = 10_000;
+
+const DEFAULT_SKIP_SLEEP: Duration = Duration::from_secs(1);
+
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry of a failed request, doubled for every following retry
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Number of photos remaining in the display sequence when metadata for the next pass is due
+const REFRESH_AHEAD: usize = 3;
+
 impl<A: ApiClient, R: Random> Slideshow<A, R> {
     pub fn new(api_client: A, random: R) -> Self {
         Self {
@@ -36,6 +93,26 @@ impl<A: ApiClient, R: Random> Slideshow<A, R> {
             order: Order::ByDate,
             random_start: false,
             source_size: SourceSize::L,
+            pass: 0,
+            pass_len: 0,
+            pass_ids: HashSet::new(),
+            fetched_pass: None,
+            large_album_warn: DEFAULT_LARGE_ALBUM_WARN,
+            large_album_warned: false,
+            bandwidth_budget: None,
+            stop_receiver: None,
+            next_pass: None,
+            refresh_requested: false,
+            reload_requested: false,
+            photo_filter: None,
+            file_name_filter: None,
+            undated_photos: UndatedPhotos::AsIs,
+            metadata_cache: None,
+            skip_sleep: DEFAULT_SKIP_SLEEP,
+            max_retries: DEFAULT_MAX_RETRIES,
+            list_retries: DEFAULT_MAX_RETRIES,
+            no_repeat_window: 0,
+            recently_selected: VecDeque::new(),
         }
     }
 
@@ -54,30 +131,376 @@ impl<A: ApiClient, R: Random> Slideshow<A, R> {
         self
     }
 
-    pub fn get_next_photo(&mut self) -> Result<Bytes> {
-        const LOOP_SLEEP_DURATION: Duration = Duration::from_secs(1);
+    pub fn with_large_album_warn(mut self, large_album_warn: usize) -> Self {
+        self.large_album_warn = large_album_warn;
+        self
+    }
+
+    /// Limits amount of photo data downloaded per hour. When exceeded, fetching of the next photo
+    /// is delayed until the hour ends
+    pub fn with_max_bytes_per_hour(mut self, max_bytes_per_hour: Option<u64>) -> Self {
+        self.bandwidth_budget = max_bytes_per_hour.map(BandwidthBudget::new);
+        self
+    }
+
+    /// Once the sender of `stop_receiver` is dropped, waiting for the bandwidth limit (see
+    /// [Slideshow::with_max_bytes_per_hour]) ends and fetching the next photo fails, so that the
+    /// app can quit without waiting for the hour to end
+    pub fn with_stop_receiver(mut self, stop_receiver: Receiver<()>) -> Self {
+        self.stop_receiver = Some(stop_receiver);
+        self
+    }
+
+    pub fn with_skip_sleep(mut self, skip_sleep: Duration) -> Self {
+        self.skip_sleep = skip_sleep;
+        self
+    }
+
+    /// Sets how many times photo requests failing with a transient error are retried, see
+    /// [retry_transient]
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets how many times fetching album metadata is retried, see [Slideshow::with_max_retries]
+    pub fn with_list_retries(mut self, list_retries: u32) -> Self {
+        self.list_retries = list_retries;
+        self
+    }
+
+    /// In random order, moves the last `no_repeat_window` photos of a pass to the end of the next
+    /// pass, so that they are not displayed again right away
+    pub fn with_no_repeat_window(mut self, no_repeat_window: usize) -> Self {
+        self.no_repeat_window = no_repeat_window;
+        self
+    }
+
+    pub fn get_next_photo(&mut self) -> Result<(Bytes, PhotoInfo)> {
         /* Loop here prevents display of error screen when the photo has simply been removed from
          * the album since we fetched its metadata. */
         loop {
-            if self.slideshow_ended() {
-                self.initialize()?;
+            let selected = self.select_next_photo()?;
+            let photo_bytes_result = retry_transient(self.max_retries, || {
+                self.api_client
+                    .get_photo_bytes(&selected.photo, self.source_size)
+            });
+            match self.accept_fetched_photo(selected, photo_bytes_result)? {
+                Some(photo) => break Ok(photo),
+                None => {
+                    /* Save on CPU and request flooding */
+                    thread_sleep(self.skip_sleep);
+                }
             }
+        }
+    }
 
-            let photo = self
-                .photo_display_sequence
-                .pop()
-                .expect("photos should not be empty");
-            let photo_bytes_result = self.api_client.get_photo_bytes(&photo, self.source_size);
-            match photo_bytes_result {
-                Err(error) if photo_removed(&error) => {
-                    log::warn!("{error}");
-                    /* Save on CPU and request flooding */
-                    thread_sleep(LOOP_SLEEP_DURATION);
-                    continue;
+    /// Takes the next photo of the display sequence, to be fetched by the caller and passed to
+    /// [Slideshow::accept_fetched_photo]. Allows fetching several photos concurrently, see
+    /// [Slideshow::get_next_photo] for fetching one photo at a time
+    pub fn select_next_photo(&mut self) -> Result<SelectedPhoto<A::Photo>> {
+        if mem::take(&mut self.reload_requested) {
+            self.reload();
+        }
+        if self.slideshow_ended() {
+            self.initialize()?;
+        }
+
+        if let Some(wait_time) = self
+            .bandwidth_budget
+            .as_mut()
+            .and_then(BandwidthBudget::wait_time)
+        {
+            log::info!("Bandwidth limit reached, pausing for {wait_time:?}");
+            self.wait(wait_time)?;
+        }
+        let photo = self
+            .photo_display_sequence
+            .pop()
+            .expect("photos should not be empty");
+        if self.no_repeat_window > 0 {
+            if self.recently_selected.len() == self.no_repeat_window {
+                self.recently_selected.pop_front();
+            }
+            self.recently_selected.push_back(photo.id());
+        }
+        let mut info = self.photo_info(&photo);
+        info.position = self
+            .pass_len
+            .checked_sub(self.photo_display_sequence.len())
+            .map(|position| (position, self.pass_len));
+        Ok(SelectedPhoto {
+            info,
+            photo,
+            pass: self.pass,
+            ends_pass: self.slideshow_ended(),
+        })
+    }
+
+    /// Sleeps for `duration`, failing when the slideshow is stopped in the meantime, see
+    /// [Slideshow::with_stop_receiver]
+    fn wait(&self, duration: Duration) -> Result<()> {
+        match &self.stop_receiver {
+            Some(stop_receiver) => match stop_receiver.recv_timeout(duration) {
+                Err(RecvTimeoutError::Timeout) => Ok(()),
+                _ => bail!("Slideshow stopped"),
+            },
+            None => {
+                thread_sleep(duration);
+                Ok(())
+            }
+        }
+    }
+
+    /// Handles result of fetching a photo returned by [Slideshow::select_next_photo]. Photos
+    /// must be passed in the order they were selected. Returns `None` when the photo has been
+    /// removed from the album since its metadata was fetched, in which case the next photo should
+    /// be fetched instead
+    pub fn accept_fetched_photo(
+        &mut self,
+        selected: SelectedPhoto<A::Photo>,
+        photo_bytes_result: Result<Bytes>,
+    ) -> Result<Option<(Bytes, PhotoInfo)>> {
+        match photo_bytes_result {
+            Err(error) if photo_removed(&error) => {
+                log::warn!("{error}");
+                if selected.ends_pass && self.fetched_pass != Some(selected.pass) {
+                    /* Otherwise a misconfigured server would keep us looping forever without
+                     * displaying anything */
+                    bail!("All photos failed to load");
+                }
+                Ok(None)
+            }
+            Ok(bytes) => {
+                if let Some(budget) = &mut self.bandwidth_budget {
+                    budget.record(bytes.len());
+                }
+                self.fetched_pass = Some(selected.pass);
+                Ok(Some((bytes, selected.info)))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Handles a photo returned by [Slideshow::select_next_photo] which did not need to be fetched,
+    /// e.g. because it was cached on disk
+    pub fn accept_cached_photo(&mut self, selected: SelectedPhoto<A::Photo>) -> PhotoInfo {
+        self.fetched_pass = Some(selected.pass);
+        selected.info
+    }
+
+    pub fn with_photo_filter(mut self, photo_filter: Option<PhotoFilter>) -> Self {
+        self.photo_filter = photo_filter;
+        self
+    }
+
+    /// Sets file name patterns of photos to display, applied before the
+    /// [Slideshow::with_photo_filter]
+    pub fn with_file_name_filter(mut self, file_name_filter: Option<FileNameFilter>) -> Self {
+        self.file_name_filter = file_name_filter;
+        self
+    }
+
+    pub fn with_undated_photos(mut self, undated_photos: UndatedPhotos) -> Self {
+        self.undated_photos = undated_photos;
+        self
+    }
+
+    /// Stores album metadata on disk, so that it does not have to be fetched again after restart
+    /// while fresh
+    pub fn with_metadata_cache(mut self, metadata_cache: Option<MetadataCache>) -> Self {
+        self.metadata_cache = metadata_cache;
+        self
+    }
+
+    /// Returns sort order of album metadata to fetch in the background once the display sequence
+    /// is running low, so that the next pass can start without waiting for the metadata. Returns
+    /// `Some` only once per pass, the fetched metadata is then passed to
+    /// [Slideshow::set_next_pass]
+    pub fn metadata_refresh_due(&mut self) -> Option<SortBy> {
+        let due = !self.refresh_requested
+            && !self.slideshow_ended()
+            && self.photo_display_sequence.len() <= REFRESH_AHEAD;
+        if due {
+            self.refresh_requested = true;
+        }
+        due.then_some(self.order.into())
+    }
+
+    /// Sets album metadata for the next pass. When fetching it failed, the metadata is fetched
+    /// again once the display sequence ends
+    pub fn set_next_pass(&mut self, photos: Result<Vec<A::Photo>>) {
+        match photos {
+            Ok(photos) => {
+                if let Some(cache) = &self.metadata_cache {
+                    cache.save(self.order.into(), &photos, SystemTime::now());
                 }
-                _ => break photo_bytes_result,
+                self.next_pass = Some(photos);
+            }
+            Err(error) => log::warn!("Failed to fetch album metadata in advance: {error}"),
+        }
+    }
+
+    /// Makes the next [Slideshow::select_next_photo] fetch album metadata again, bypassing the
+    /// metadata cache. Photos added to the album since the current pass started are displayed
+    /// next, instead of waiting for the next pass
+    pub fn request_reload(&mut self) {
+        self.reload_requested = true;
+    }
+
+    /// Whether every photo of the current pass has been selected, so that the next photo would
+    /// start another pass
+    pub fn is_pass_finished(&self) -> bool {
+        self.pass > 0 && self.slideshow_ended()
+    }
+
+    /// Whether the next photo cannot be fetched before metadata for the next pass is available
+    pub fn is_waiting_for_next_pass(&self) -> bool {
+        self.slideshow_ended() && self.next_pass.is_none()
+    }
+
+    /// Fetches a photo outside the display sequence, e.g. when it gets pinned on screen. The photo
+    /// is looked up in metadata of the current pass, and album metadata is fetched again only when
+    /// it's not there, e.g. when the photo has already been displayed in the pass
+    pub fn get_photo_by_id(&self, id: &str) -> Result<(Bytes, PhotoInfo)> {
+        let known_photo = self
+            .photo_display_sequence
+            .iter()
+            .chain(self.next_pass.iter().flatten())
+            .find(|photo| photo.id() == id);
+        let fetched_photo;
+        let photo = match known_photo {
+            Some(photo) => photo,
+            None => {
+                let photos = self.fetch_photo_metadata()?;
+                let Some(photo) = photos.into_iter().find(|photo| photo.id() == id) else {
+                    bail!("Photo {id} not found in the album")
+                };
+                fetched_photo = photo;
+                &fetched_photo
+            }
+        };
+        let bytes = retry_transient(self.max_retries, || {
+            self.api_client.get_photo_bytes(photo, self.source_size)
+        })?;
+        Ok((bytes, self.photo_info(photo)))
+    }
+
+    /// Fetches a photo displayed before the slideshow starts, see [crate::cli::Cli::intro].
+    /// `intro` is either a path to a local file or an id of an album photo. Album photos are looked
+    /// up in metadata of the first pass, which is started here, so that the metadata is not fetched
+    /// twice
+    pub fn get_intro_photo(&mut self, intro: &str) -> Result<(Bytes, PhotoInfo)> {
+        let path = Path::new(intro);
+        if !path.is_file() {
+            if self.pass == 0 {
+                self.initialize()?;
+            }
+            return self.get_photo_by_id(intro);
+        }
+        let bytes =
+            std::fs::read(path).map_err(|error| anyhow!("{}: {error}", path.to_string_lossy()))?;
+        let photo = ManifestPhoto {
+            path: path.to_path_buf(),
+            date: None,
+            location: None,
+        };
+        Ok((Bytes::from(bytes), PhotoInfo::from(&photo)))
+    }
+
+    fn photo_info(&self, photo: &A::Photo) -> PhotoInfo {
+        let mut info = PhotoInfo::from(photo);
+        if self.undated_photos == UndatedPhotos::HideDate && metadata::is_undated(info.date) {
+            info.date = None;
+        }
+        info
+    }
+
+    /// Fetches album metadata, unless it is cached and fresh
+    fn get_photo_metadata(&self) -> Result<Vec<A::Photo>> {
+        let sort_by = self.order.into();
+        let Some(cache) = &self.metadata_cache else {
+            return self.fetch_photo_metadata();
+        };
+        if let Some(photos) = cache.load(sort_by, SystemTime::now()) {
+            return Ok(photos);
+        }
+        let photos = self.fetch_photo_metadata()?;
+        cache.save(sort_by, &photos, SystemTime::now());
+        Ok(photos)
+    }
+
+    /// Fetches album metadata from the server, bypassing the cache
+    fn fetch_photo_metadata(&self) -> Result<Vec<A::Photo>> {
+        retry_transient(self.list_retries, || {
+            self.api_client.get_photo_metadata(self.order.into())
+        })
+    }
+
+    fn reload(&mut self) {
+        let photos = match self.fetch_photo_metadata() {
+            Ok(photos) => photos,
+            Err(error) => {
+                /* The current pass continues with the metadata it has */
+                log::warn!("Failed to reload album: {error}");
+                return;
+            }
+        };
+        if let Some(cache) = &self.metadata_cache {
+            cache.save(self.order.into(), &photos, SystemTime::now());
+        }
+        if self.slideshow_ended() {
+            log::info!("Album reloaded, {} photos", photos.len());
+            self.next_pass = Some(photos);
+            return;
+        }
+        let added = photos
+            .into_iter()
+            .filter(|photo| !self.pass_ids.contains(&photo.id()))
+            .collect::<Vec<_>>();
+        let added = match &self.file_name_filter {
+            Some(file_name_filter) => file_name_filter.filter(added),
+            None => added,
+        };
+        let added = match &self.photo_filter {
+            Some(photo_filter) if !added.is_empty() => photo_filter.filter(added),
+            _ => added,
+        };
+        log::info!("Album reloaded, {} new photos", added.len());
+        self.pass_ids.extend(added.iter().map(Metadata::id));
+        self.pass_len += added.len();
+        /* Popped off the end, so displayed next in album order */
+        self.photo_display_sequence.extend(added.into_iter().rev());
+    }
+
+    /// Moves photos selected recently (in the previous pass) to the end of the display sequence,
+    /// the most recently selected last, keeping the order of the other photos. At most half of the
+    /// photos are moved, as deferring the whole album would repeat the previous pass' order
+    fn defer_recently_selected(&mut self) {
+        if self.recently_selected.is_empty() {
+            return;
+        }
+        let mut photos = mem::take(&mut self.photo_display_sequence);
+        /* The sequence is reversed, so deferred photos go to the front */
+        let mut deferred = vec![];
+        let window = self.recently_selected.len().min(photos.len() / 2);
+        for id in self.recently_selected.iter().rev().take(window) {
+            if let Some(index) = photos.iter().position(|photo| photo.id() == *id) {
+                deferred.push(photos.remove(index));
             }
         }
+        deferred.extend(photos);
+        self.photo_display_sequence = deferred;
+    }
+
+    /// Returns the large album warning the first time the album exceeds the threshold only
+    fn take_large_album_warning(&mut self, item_count: usize) -> Option<String> {
+        if self.large_album_warned {
+            return None;
+        }
+        let warning = large_album_warning(item_count, self.large_album_warn)?;
+        self.large_album_warned = true;
+        Some(warning)
     }
 
     fn slideshow_ended(&self) -> bool {
@@ -89,11 +512,39 @@ impl<A: ApiClient, R: Random> Slideshow<A, R> {
             self.photo_display_sequence.is_empty(),
             "already initialized"
         );
-        let photos = self.api_client.get_photo_metadata(self.order.into())?;
+        self.refresh_requested = false;
+        let photos = match self.next_pass.take() {
+            Some(photos) => photos,
+            None => self.get_photo_metadata()?,
+        };
         if photos.is_empty() {
             bail!("Album is empty");
         }
+        let photos = match &self.file_name_filter {
+            Some(file_name_filter) => file_name_filter.filter(photos),
+            None => photos,
+        };
+        if photos.is_empty() {
+            bail!("No photos match --include-glob and --exclude-glob patterns");
+        }
+        let mut photos = match &self.photo_filter {
+            Some(photo_filter) => photo_filter.filter(photos),
+            None => photos,
+        };
+        if photos.is_empty() {
+            bail!("No photos selected by --photo-filter-script");
+        }
+        if self.order == Order::ByDate && self.undated_photos == UndatedPhotos::SortLast {
+            /* Stable sort keeps the order of photos within both groups */
+            photos.sort_by_key(|photo| metadata::is_undated(photo.date()));
+        }
         let item_count = photos.len();
+        if let Some(warning) = self.take_large_album_warning(item_count) {
+            log::warn!("{warning}");
+        }
+        self.pass += 1;
+        self.pass_len = item_count;
+        self.pass_ids = photos.iter().map(Metadata::id).collect();
         self.photo_display_sequence.reserve(item_count);
         match self.order {
             Order::ByDate | Order::ByName if self.random_start => {
@@ -113,12 +564,110 @@ impl<A: ApiClient, R: Random> Slideshow<A, R> {
             Order::Random => {
                 self.photo_display_sequence.extend(photos);
                 self.random.shuffle(&mut self.photo_display_sequence);
+                self.defer_recently_selected();
+            }
+            Order::RandomDays => {
+                let mut days = group_by_day(photos);
+                self.random.shuffle(&mut days);
+                self.photo_display_sequence
+                    .extend(days.into_iter().flatten().rev());
             }
         }
         Ok(())
     }
 }
 
+/// Photo taken from the display sequence, see [Slideshow::select_next_photo]
+#[derive(Debug)]
+pub struct SelectedPhoto<P> {
+    pub photo: P,
+    info: PhotoInfo,
+    /// Display sequence pass the photo belongs to
+    pass: usize,
+    /// Whether the photo is the last one of its pass
+    ends_pass: bool,
+}
+
+/// Tracks amount of photo data downloaded in hourly windows
+#[derive(Debug)]
+struct BandwidthBudget {
+    max_bytes_per_window: u64,
+    window_start: Instant,
+    used_bytes: u64,
+}
+
+impl BandwidthBudget {
+    const WINDOW: Duration = Duration::from_secs(60 * 60);
+
+    fn new(max_bytes_per_window: u64) -> Self {
+        Self {
+            max_bytes_per_window,
+            window_start: Instant::now(),
+            used_bytes: 0,
+        }
+    }
+
+    fn record(&mut self, bytes: usize) {
+        self.used_bytes += bytes as u64;
+    }
+
+    /// Returns time remaining until the current window ends when the budget is exhausted. Starts
+    /// a new window when the current one has ended
+    fn wait_time(&mut self) -> Option<Duration> {
+        let elapsed = Instant::now() - self.window_start;
+        if elapsed >= Self::WINDOW {
+            self.window_start = Instant::now();
+            self.used_bytes = 0;
+            return None;
+        }
+        (self.used_bytes >= self.max_bytes_per_window).then(|| Self::WINDOW - elapsed)
+    }
+}
+
+fn large_album_warning(item_count: usize, threshold: usize) -> Option<String> {
+    (item_count > threshold).then(|| {
+        format!(
+            "Album contains {item_count} photos, which may use a lot of memory on small devices. \
+             Consider splitting the album or using a smaller --source-size"
+        )
+    })
+}
+
+/// Calls `request` again when it fails with a transient error (see [http::is_transient]), up to
+/// `max_retries` times, waiting twice as long before each retry
+pub fn retry_transient<T>(max_retries: u32, mut request: impl FnMut() -> Result<T>) -> Result<T> {
+    let mut delay = FIRST_RETRY_DELAY;
+    let mut retries = 0;
+    loop {
+        match request() {
+            Err(error) if retries < max_retries && http::is_transient(&error) => {
+                log::warn!("{error}, retrying in {delay:?}");
+                thread_sleep(delay);
+                delay *= 2;
+                retries += 1;
+            }
+            result => break result,
+        }
+    }
+}
+
+/// Groups photos taken on the same calendar day, each group sorted by the time taken. Photos
+/// without a known date form a single group.
+fn group_by_day<P: Metadata>(mut photos: Vec<P>) -> Vec<Vec<P>> {
+    photos.sort_by_key(|photo| photo.date());
+    let mut days: Vec<Vec<P>> = vec![];
+    for photo in photos {
+        let day = photo.date().map(|date| date.date());
+        match days.last_mut() {
+            Some(last_day) if last_day[0].date().map(|date| date.date()) == day => {
+                last_day.push(photo)
+            }
+            _ => days.push(vec![photo]),
+        }
+    }
+    days
+}
+
 /// Photo has been removed since we fetched its metadata, try next one.
 fn photo_removed(error: &anyhow::Error) -> bool {
     matches!(
@@ -132,7 +681,14 @@ fn photo_removed(error: &anyhow::Error) -> bool {
 mod tests {
     use super::*;
 
-    use syno_api::dto::List;
+    use std::sync::{
+        atomic::{self, AtomicUsize},
+        mpsc,
+    };
+
+    use chrono::{DateTime, NaiveDateTime};
+    use mock_instant::MockClock;
+    use syno_api::{dto::List, foto::browse::item::dto::Item};
 
     use crate::{
         api_client::syno_client::SynoApiClient,
@@ -199,7 +755,9 @@ mod tests {
 
         /* Assert */
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), Bytes::from_static(&[42, 1, 255, 50]));
+        let (bytes, info) = result.unwrap();
+        assert_eq!(bytes, Bytes::from_static(&[42, 1, 255, 50]));
+        assert_eq!(info.id, FIRST_PHOTO_ID.to_string());
 
         let expected_remaining_display_sequence = [
             test_helpers::new_photo_dto(3, "photo3"),
@@ -505,6 +1063,110 @@ mod tests {
         );
     }
 
+    #[test]
+    fn when_no_repeat_window_is_set_then_next_random_pass_does_not_start_with_recent_photos() {
+        test_case(0, ["3", "2", "1", "0", "0", "1", "2", "3"]);
+        test_case(2, ["3", "2", "1", "0", "2", "3", "1", "0"]);
+        /* Windows as large as the album or larger defer only half of it, so the shuffle is kept */
+        test_case(4, ["3", "2", "1", "0", "2", "3", "1", "0"]);
+        test_case(8, ["3", "2", "1", "0", "2", "3", "1", "0"]);
+
+        fn test_case(no_repeat_window: usize, expected: [&str; 8]) {
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+            const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+            let mut client_mock = MockHttpClient::new();
+            /* Shuffle keeps the order, so the second pass would start with the last photo of
+             * the first one */
+            for ids in [[0, 1, 2, 3], [3, 2, 1, 0]] {
+                client_mock
+                    .expect_post()
+                    .withf(|_, form, _| test_helpers::is_list_form(form))
+                    .times(1)
+                    .return_once(move |_, _, _| {
+                        Ok(test_helpers::new_success_response_with_json(List {
+                            list: ids
+                                .into_iter()
+                                .map(|id| test_helpers::new_photo_dto(id, &format!("photo{id}")))
+                                .collect(),
+                        }))
+                    });
+            }
+            let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+            let mut slideshow = new_syno_slideshow(
+                &client_mock,
+                FakeRandom::default(),
+                &cookie_store,
+                SHARE_LINK,
+            )
+            .with_ordering(Order::Random)
+            .with_no_repeat_window(no_repeat_window);
+
+            let result = (0..8)
+                .map(|_| slideshow.select_next_photo().unwrap().info.id)
+                .collect::<Vec<_>>();
+
+            assert_eq!(result, expected, "{no_repeat_window}");
+        }
+    }
+
+    #[test]
+    fn when_random_days_order_then_days_are_shuffled_and_photos_within_day_are_sorted_by_date() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const DAY: u64 = 24 * 60 * 60;
+        const HOUR: u64 = 60 * 60;
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        new_photo_dto_taken_at(0, 10 * HOUR),
+                        new_photo_dto_taken_at(1, 8 * HOUR),
+                        new_photo_dto_taken_at(2, DAY + 12 * HOUR),
+                        new_photo_dto_taken_at(3, 2 * DAY + 9 * HOUR),
+                        new_photo_dto_taken_at(4, DAY + 7 * HOUR),
+                    ],
+                }))
+            });
+        client_mock
+            .expect_get()
+            .withf(|_, query| {
+                test_helpers::is_get_photo_form(query, "FakeSharingId", "3", "photo3", "xl")
+            })
+            .return_once(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[42, 1, 255, 50])));
+                Ok(get_photo_response)
+            });
+        /* Days: [1 0] [4 2] [3] */
+        let random_mock =
+            FakeRandom::default().with_shuffle_result(vec![(0, 2) /* [3] [4 2] [1 0] */]);
+        let cookie_store = Jar::default();
+        let mut slideshow =
+            new_syno_slideshow(&client_mock, random_mock, &cookie_store, SHARE_LINK)
+                .with_ordering(Order::RandomDays);
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_ok());
+        assert_eq!(
+            slideshow.photo_display_sequence,
+            vec![
+                new_photo_dto_taken_at(0, 10 * HOUR),
+                new_photo_dto_taken_at(1, 8 * HOUR),
+                new_photo_dto_taken_at(2, DAY + 12 * HOUR),
+                new_photo_dto_taken_at(4, DAY + 7 * HOUR),
+                // photo3 popped
+            ]
+        );
+    }
+
     /// Tests that when photos were removed, slideshow gets re-initialized when reaching the end of the album
     #[test]
     fn get_next_photo_reinitializes_when_display_sequence_is_empty() {
@@ -569,15 +1231,770 @@ mod tests {
         );
     }
 
-    fn new_syno_slideshow<'a, H: HttpClient, C: CookieStore, R: Random>(
-        http_client: &'a H,
-        random: R,
-        cookie_store: &'a C,
-        share_link: &str,
-    ) -> Slideshow<SynoApiClient<'a, H, C>, R> {
-        let share_link = Url::parse(share_link).unwrap();
-        let api_client = SynoApiClient::build(http_client, cookie_store, &share_link).unwrap();
-        Slideshow::new(api_client, random)
+    #[test]
+    fn when_all_photos_are_not_found_then_get_next_photo_returns_error() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                        test_helpers::new_photo_dto(3, "photo3"),
+                    ],
+                }))
+            });
+        client_mock.expect_get().times(3).returning(|_, _| {
+            let mut not_found_response = MockHttpResponse::new();
+            not_found_response
+                .expect_status()
+                .return_const(StatusCode::NOT_FOUND);
+            Ok(not_found_response)
+        });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "All photos failed to load");
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_photo_is_not_found_then_get_next_photo_sleeps_for_configured_duration() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        const SKIP_SLEEP: Duration = Duration::from_millis(250);
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                        test_helpers::new_photo_dto(3, "photo3"),
+                    ],
+                }))
+            });
+        client_mock.expect_get().times(3).returning(|_, _| {
+            let mut not_found_response = MockHttpResponse::new();
+            not_found_response
+                .expect_status()
+                .return_const(StatusCode::NOT_FOUND);
+            Ok(not_found_response)
+        });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_skip_sleep(SKIP_SLEEP);
+        test_helpers::take_fake_sleeps();
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_err());
+        /* No sleep after the last photo, as the error ends the loop */
+        assert_eq!(test_helpers::take_fake_sleeps(), vec![SKIP_SLEEP; 2]);
+    }
+
+    #[test]
+    fn when_server_is_briefly_unavailable_then_get_next_photo_retries_with_backoff() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                }))
+            });
+        client_mock
+            .expect_get()
+            .times(2)
+            .returning(|_, _| Ok(new_unavailable_response()));
+        client_mock.expect_get().return_once(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from_static(&[1])));
+            Ok(get_photo_response)
+        });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+        test_helpers::take_fake_sleeps();
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_ok());
+        assert_eq!(
+            test_helpers::take_fake_sleeps(),
+            [Duration::from_secs(1), Duration::from_secs(2)]
+        );
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_server_stays_unavailable_then_get_next_photo_fails_after_max_retries() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                }))
+            });
+        client_mock
+            .expect_get()
+            .times(3)
+            .returning(|_, _| Ok(new_unavailable_response()));
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_max_retries(2);
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_err_and(|error| error.is::<InvalidHttpResponse>()));
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_listing_album_fails_transiently_then_get_next_photo_retries_list_request() {
+        test_case(1, true);
+        test_case(0, false);
+
+        fn test_case(list_retries: u32, expected_ok: bool) {
+            /* Arrange */
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+            const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+            let mut client_stub = MockHttpClient::new();
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .times(1)
+                .return_once(|_, _, _| Ok(new_unavailable_response()));
+            client_stub
+                .expect_post()
+                .withf(|_, form, _| test_helpers::is_list_form(form))
+                .return_once(|_, _, _| {
+                    Ok(test_helpers::new_success_response_with_json(List {
+                        list: vec![test_helpers::new_photo_dto(1, "photo1")],
+                    }))
+                });
+            client_stub.expect_get().returning(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[1])));
+                Ok(get_photo_response)
+            });
+            let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+            let mut slideshow = new_syno_slideshow(
+                &client_stub,
+                FakeRandom::default(),
+                &cookie_store,
+                SHARE_LINK,
+            )
+            .with_list_retries(list_retries);
+
+            /* Act */
+            let result = slideshow.get_next_photo();
+
+            /* Assert */
+            assert_eq!(result.is_ok(), expected_ok, "list_retries: {list_retries}");
+        }
+    }
+
+    #[test]
+    fn when_undated_photos_sort_last_then_they_are_displayed_after_dated_photos() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .return_once(|_, _, _| {
+                /* Undated photos are reported as taken at time 0 */
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        new_photo_dto_taken_at(1, 0),
+                        new_photo_dto_taken_at(2, 100),
+                        new_photo_dto_taken_at(3, 0),
+                        new_photo_dto_taken_at(4, 200),
+                    ],
+                }))
+            });
+        client_mock
+            .expect_get()
+            .withf(|_, query| {
+                test_helpers::is_get_photo_form(query, "FakeSharingId", "2", "photo2", "xl")
+            })
+            .return_once(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[])));
+                Ok(get_photo_response)
+            });
+        let cookie_store = Jar::default();
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_undated_photos(UndatedPhotos::SortLast);
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_ok());
+        assert_eq!(
+            slideshow.photo_display_sequence,
+            vec![
+                new_photo_dto_taken_at(3, 0),
+                new_photo_dto_taken_at(1, 0),
+                new_photo_dto_taken_at(4, 200),
+                // photo2 popped
+            ]
+        );
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn undated_photos_option_controls_reported_date() {
+        test_case(UndatedPhotos::AsIs, Some(DateTime::UNIX_EPOCH.naive_utc()));
+        test_case(UndatedPhotos::HideDate, None);
+        test_case(
+            UndatedPhotos::SortLast,
+            Some(DateTime::UNIX_EPOCH.naive_utc()),
+        );
+
+        fn test_case(undated_photos: UndatedPhotos, expected_date: Option<NaiveDateTime>) {
+            /* Arrange */
+            const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+            const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+            let mut client_stub = MockHttpClient::new();
+            client_stub.expect_get().return_once(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[])));
+                Ok(get_photo_response)
+            });
+            let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+            let mut slideshow = new_syno_slideshow(
+                &client_stub,
+                FakeRandom::default(),
+                &cookie_store,
+                SHARE_LINK,
+            )
+            .with_undated_photos(undated_photos);
+            slideshow.photo_display_sequence = vec![new_photo_dto_taken_at(1, 0)];
+
+            /* Act */
+            let result = slideshow.get_next_photo();
+
+            /* Assert */
+            assert!(result.is_ok());
+            let (_, info) = result.unwrap();
+            assert_eq!(info.date, expected_date);
+        }
+    }
+
+    #[test]
+    fn photo_info_reports_position_in_pass_as_sequence_advances() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                        test_helpers::new_photo_dto(3, "photo3"),
+                    ],
+                }))
+            });
+        client_mock.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from_static(&[])));
+            Ok(get_photo_response)
+        });
+        let cookie_store = Jar::default();
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_ordering(Order::Random);
+
+        /* Act */
+        let counters = (0..4)
+            .map(|_| slideshow.get_next_photo().unwrap().1.counter())
+            .collect::<Vec<_>>();
+
+        /* Assert */
+        assert_eq!(
+            counters,
+            ["1 / 3", "2 / 3", "3 / 3", "1 / 3"].map(|counter| Some(counter.to_string()))
+        );
+    }
+
+    #[test]
+    fn metadata_refresh_is_due_once_when_display_sequence_runs_low() {
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_stub = MockHttpClient::new();
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from_static(&[])));
+            Ok(get_photo_response)
+        });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_stub,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+        slideshow.photo_display_sequence = (1..=REFRESH_AHEAD as u32 + 1)
+            .rev()
+            .map(|id| test_helpers::new_photo_dto(id, &format!("photo{id}")))
+            .collect();
+
+        let mut refreshes_due = vec![];
+        for _ in 0..REFRESH_AHEAD {
+            refreshes_due.push(slideshow.metadata_refresh_due().is_some());
+            slideshow.get_next_photo().unwrap();
+        }
+
+        assert_eq!(refreshes_due, [false, true, false]);
+    }
+
+    #[test]
+    fn when_next_pass_is_fetched_in_advance_then_get_next_photo_does_not_fetch_metadata() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        /* No expectation for the list request, it would fail the test */
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_get()
+            .withf(|_, query| {
+                test_helpers::is_get_photo_form(query, "FakeSharingId", "1", "photo1", "xl")
+            })
+            .return_once(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[])));
+                Ok(get_photo_response)
+            });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+        slideshow.set_next_pass(Ok(vec![
+            test_helpers::new_photo_dto(1, "photo1"),
+            test_helpers::new_photo_dto(2, "photo2"),
+        ]));
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        assert!(result.is_ok());
+        assert_eq!(
+            slideshow.photo_display_sequence,
+            vec![test_helpers::new_photo_dto(2, "photo2")]
+        );
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_metadata_cache_is_fresh_then_get_next_photo_does_not_fetch_metadata() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        const TTL: Duration = Duration::from_secs(60);
+        let cache_path = std::env::temp_dir().join(format!(
+            "syno-photo-frame-slideshow-metadata-cache-{}.json",
+            std::process::id()
+        ));
+        MetadataCache::new(&cache_path, TTL, SHARE_LINK).save(
+            SortBy::TakenTime,
+            &[
+                test_helpers::new_photo_dto(1, "photo1"),
+                test_helpers::new_photo_dto(2, "photo2"),
+            ],
+            SystemTime::now(),
+        );
+        /* No expectation for the list request, it would fail the test */
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_get()
+            .withf(|_, query| {
+                test_helpers::is_get_photo_form(query, "FakeSharingId", "1", "photo1", "xl")
+            })
+            .return_once(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[])));
+                Ok(get_photo_response)
+            });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_metadata_cache(Some(MetadataCache::new(&cache_path, TTL, SHARE_LINK)));
+
+        /* Act */
+        let result = slideshow.get_next_photo();
+
+        /* Assert */
+        std::fs::remove_file(&cache_path).unwrap();
+        assert!(result.is_ok());
+        assert_eq!(
+            slideshow.photo_display_sequence,
+            vec![test_helpers::new_photo_dto(2, "photo2")]
+        );
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_reload_is_requested_then_photos_added_to_album_are_displayed_next() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        let mut client_mock = MockHttpClient::new();
+        let list_requests = AtomicUsize::new(0);
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .times(2)
+            .returning(move |_, _, _| {
+                let photo_count = match list_requests.fetch_add(1, atomic::Ordering::Relaxed) {
+                    0 => 3,
+                    _ => 5,
+                };
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: (1..=photo_count)
+                        .map(|id| test_helpers::new_photo_dto(id, &format!("photo{id}")))
+                        .collect(),
+                }))
+            });
+        client_mock.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from_static(&[])));
+            Ok(get_photo_response)
+        });
+        let cookie_store = Jar::default();
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+        let first_photo = slideshow.get_next_photo().unwrap().1;
+
+        /* Act */
+        slideshow.request_reload();
+        let photos = (0..4)
+            .map(|_| slideshow.get_next_photo().unwrap().1)
+            .collect::<Vec<_>>();
+
+        /* Assert */
+        assert_eq!(first_photo.counter(), Some("1 / 3".to_string()));
+        assert_eq!(
+            photos
+                .iter()
+                .map(|info| (info.id.as_str(), info.counter().unwrap()))
+                .collect::<Vec<_>>(),
+            [
+                ("4", "2 / 5"),
+                ("5", "3 / 5"),
+                ("2", "4 / 5"),
+                ("3", "5 / 5")
+            ]
+            .map(|(id, counter)| (id, counter.to_string()))
+        );
+        client_mock.checkpoint();
+    }
+
+    #[test]
+    fn when_bandwidth_budget_is_exhausted_then_fetching_waits_until_window_ends() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_stub = MockHttpClient::new();
+        client_stub.expect_get().returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from_static(&[0; 600])));
+            Ok(get_photo_response)
+        });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_stub,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_max_bytes_per_hour(Some(1000));
+        slideshow.photo_display_sequence = vec![
+            test_helpers::new_photo_dto(3, "photo3"),
+            test_helpers::new_photo_dto(2, "photo2"),
+            test_helpers::new_photo_dto(1, "photo1"),
+        ];
+        let budget_wait_time = |slideshow: &mut Slideshow<_, _>| {
+            slideshow
+                .bandwidth_budget
+                .as_mut()
+                .and_then(BandwidthBudget::wait_time)
+        };
+
+        /* Act & Assert */
+        assert!(slideshow.get_next_photo().is_ok());
+        assert_eq!(budget_wait_time(&mut slideshow), None);
+
+        assert!(slideshow.get_next_photo().is_ok());
+        MockClock::advance(Duration::from_secs(20 * 60));
+        assert_eq!(
+            budget_wait_time(&mut slideshow),
+            Some(Duration::from_secs(40 * 60))
+        );
+
+        MockClock::advance(Duration::from_secs(40 * 60));
+        assert_eq!(budget_wait_time(&mut slideshow), None);
+        assert!(slideshow.get_next_photo().is_ok());
+        assert_eq!(budget_wait_time(&mut slideshow), None);
+    }
+
+    #[test]
+    fn when_slideshow_is_stopped_then_waiting_for_bandwidth_budget_ends() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let client_stub = MockHttpClient::new();
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let (stop_sender, stop_receiver) = mpsc::channel();
+        let mut slideshow = new_syno_slideshow(
+            &client_stub,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        )
+        .with_max_bytes_per_hour(Some(1000))
+        .with_stop_receiver(stop_receiver);
+        slideshow.photo_display_sequence = vec![test_helpers::new_photo_dto(1, "photo1")];
+        slideshow.bandwidth_budget.as_mut().unwrap().record(1000);
+        drop(stop_sender);
+
+        /* Act */
+        let result = slideshow.select_next_photo();
+
+        /* Assert */
+        assert!(result.is_err());
+        assert!(test_helpers::take_fake_sleeps().is_empty());
+        assert_eq!(slideshow.photo_display_sequence.len(), 1);
+    }
+
+    #[test]
+    fn get_photo_by_id_looks_photo_up_in_current_pass_before_fetching_album_metadata() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            /* Fetched once for the pass, and once more for the photo which is no longer in it */
+            .times(2)
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                    ],
+                }))
+            });
+        client_mock
+            .expect_get()
+            .withf(|_, query| {
+                test_helpers::is_get_photo_form(query, "FakeSharingId", "2", "photo2", "xl")
+            })
+            .times(2)
+            .returning(|_, _| {
+                let mut get_photo_response = test_helpers::new_ok_response();
+                get_photo_response
+                    .expect_bytes()
+                    .return_once(|| Ok(Bytes::from_static(&[42])));
+                Ok(get_photo_response)
+            });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+
+        /* Act & Assert */
+        assert_eq!(slideshow.select_next_photo().unwrap().info.id, "1");
+        assert_eq!(slideshow.get_photo_by_id("2").unwrap().1.id, "2");
+        assert_eq!(slideshow.select_next_photo().unwrap().info.id, "2");
+        /* Already displayed in the pass */
+        assert_eq!(slideshow.get_photo_by_id("2").unwrap().1.id, "2");
+    }
+
+    #[test]
+    fn album_intro_photo_is_looked_up_in_first_pass() {
+        /* Arrange */
+        const SHARE_LINK: &str = "http://fake.dsm.addr/aa/sharing/FakeSharingId";
+        const EXPECTED_API_URL: &str = "http://fake.dsm.addr/aa/sharing/webapi/entry.cgi";
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_post()
+            .withf(|_, form, _| test_helpers::is_list_form(form))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(test_helpers::new_success_response_with_json(List {
+                    list: vec![
+                        test_helpers::new_photo_dto(1, "photo1"),
+                        test_helpers::new_photo_dto(2, "photo2"),
+                    ],
+                }))
+            });
+        client_mock.expect_get().times(1).returning(|_, _| {
+            let mut get_photo_response = test_helpers::new_ok_response();
+            get_photo_response
+                .expect_bytes()
+                .return_once(|| Ok(Bytes::from_static(&[42])));
+            Ok(get_photo_response)
+        });
+        let cookie_store = logged_in_cookie_store(EXPECTED_API_URL);
+        let mut slideshow = new_syno_slideshow(
+            &client_mock,
+            FakeRandom::default(),
+            &cookie_store,
+            SHARE_LINK,
+        );
+
+        /* Act & Assert */
+        assert_eq!(slideshow.get_intro_photo("2").unwrap().1.id, "2");
+        assert_eq!(slideshow.select_next_photo().unwrap().info.id, "1");
+        assert_eq!(slideshow.select_next_photo().unwrap().info.id, "2");
+    }
+
+    #[test]
+    fn large_album_warning_is_returned_only_above_threshold() {
+        assert!(large_album_warning(9, 10).is_none());
+        assert!(large_album_warning(10, 10).is_none());
+        assert!(large_album_warning(11, 10).is_some_and(|warning| warning.contains("11 photos")));
+    }
+
+    #[test]
+    fn large_album_warning_is_taken_only_once() {
+        let client_stub = MockHttpClient::new();
+        let cookie_store = Jar::default();
+        let mut slideshow = new_syno_slideshow(
+            &client_stub,
+            FakeRandom::default(),
+            &cookie_store,
+            "http://fake.dsm.addr/aa/sharing/FakeSharingId",
+        )
+        .with_large_album_warn(10);
+
+        assert!(slideshow.take_large_album_warning(10).is_none());
+        assert!(slideshow.take_large_album_warning(11).is_some());
+        /* E.g. the next pass, or a background metadata refresh */
+        assert!(slideshow.take_large_album_warning(11).is_none());
+    }
+
+    fn new_syno_slideshow<'a, H: HttpClient, C: CookieStore, R: Random>(
+        http_client: &'a H,
+        random: R,
+        cookie_store: &'a C,
+        share_link: &str,
+    ) -> Slideshow<SynoApiClient<'a, H, C>, R> {
+        let share_link = Url::parse(share_link).unwrap();
+        let api_client = SynoApiClient::build(http_client, cookie_store, &share_link).unwrap();
+        Slideshow::new(api_client, random)
+    }
+
+    fn new_unavailable_response() -> MockHttpResponse {
+        let mut response = MockHttpResponse::new();
+        response
+            .expect_status()
+            .return_const(StatusCode::SERVICE_UNAVAILABLE);
+        response
+    }
+
+    fn new_photo_dto_taken_at(id: u32, time: u64) -> Item {
+        Item {
+            time,
+            ..test_helpers::new_photo_dto(id, &format!("photo{id}"))
+        }
     }
 
     fn logged_in_cookie_store(url: &str) -> impl CookieStore {
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
 use mockall::mock;
 use serde::de::DeserializeOwned;
 use std::time::Duration;
@@ -24,6 +25,13 @@ mock! {
         ) -> Result<MockHttpResponse>;
 
         fn get<'a>(&self, url: &str, query: &[(&'a str, &'a str)]) -> Result<MockHttpResponse>;
+
+        fn get_with_timeout<'a>(
+            &self,
+            url: &str,
+            query: &[(&'a str, &'a str)],
+            timeout: Duration,
+        ) -> Result<MockHttpResponse>;
     }
 }
 
@@ -32,7 +40,7 @@ pub mod rand {
     use std::cell::RefCell;
     use std::ops::Range;
 
-    #[derive(Debug, Default)]
+    #[derive(Debug, Default, Clone)]
     pub struct FakeRandom {
         random_sequence: RefCell<Vec<u32>>,
         shuffle_swap_sequence: Vec<(usize, usize)>,
@@ -67,7 +75,39 @@ pub mod rand {
     }
 }
 
-pub fn fake_sleep(_: Duration) {}
+thread_local! {
+    static FAKE_SLEEPS: std::cell::RefCell<Vec<Duration>> = const { std::cell::RefCell::new(vec![]) };
+}
+
+/// Records the duration instead of sleeping, see [take_fake_sleeps]
+pub fn fake_sleep(duration: Duration) {
+    FAKE_SLEEPS.with(|sleeps| sleeps.borrow_mut().push(duration));
+}
+
+/// Returns durations passed to [fake_sleep] on the current thread since the last call
+pub fn take_fake_sleeps() -> Vec<Duration> {
+    FAKE_SLEEPS.with(|sleeps| sleeps.take())
+}
+
+thread_local! {
+    static FAKE_LOCAL_NOW: std::cell::Cell<Option<NaiveDateTime>> = const { std::cell::Cell::new(None) };
+}
+
+/// Returns the time set with [set_fake_local_now], or noon of 2024-01-01 when not set, so that
+/// tests do not depend on the wall clock
+pub fn fake_local_now() -> NaiveDateTime {
+    FAKE_LOCAL_NOW.with(|now| now.get()).unwrap_or_else(|| {
+        NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    })
+}
+
+/// Sets the time returned by [fake_local_now] on the current thread
+pub fn set_fake_local_now(now: NaiveDateTime) {
+    FAKE_LOCAL_NOW.with(|fake_now| fake_now.set(Some(now)));
+}
 
 /// When `is_logged_in_to_url` is set to Some value, cookie store will simulate logged in state
 pub fn new_cookie_store(is_logged_in_to_url: Option<&str>) -> impl CookieStore {
@@ -1,3 +1,4 @@
+use std::time::Duration;
 #[cfg(not(test))]
 use std::time::Instant;
 
@@ -7,70 +8,235 @@ use mock_instant::Instant;
 use anyhow::Result;
 
 use crate::{
-    cli::Transition,
-    sdl::{Color, Sdl, TextureIndex},
+    cli::{Rotation, SlideDirection, Transition},
+    rand::Random,
+    render::{Color, Rect, Renderer, TextureIndex},
 };
 
 const TRANSITION_ALPHA_MIN: f64 = 0_f64;
 const TRANSITION_ALPHA_MAX: f64 = 255_f64;
-// Possibly parametrize this and take command line argument to control length of the transition
-const FADE_TO_BLACK_DURATION_SECS: f64 = 1_f64;
-const CROSSFADE_DURATION_SECS: f64 = 1_f64;
+/// How much the photos are magnified at the peak of the zoom blur
+const ZOOM_BLUR_MAX_ZOOM: f64 = 0.2;
+/// Number of fainter, more magnified copies of the outgoing photo faking the blur. Each one is a
+/// full-screen copy, so keep it low for slow hardware
+const ZOOM_BLUR_GHOSTS: u32 = 2;
+/// Longest frame time taken into account when advancing a transition. Slower frames (typically
+/// the first one on slow hardware) slow the transition down instead of making it jump
+const MAX_FRAME_DELTA_SECS: f64 = 1_f64 / 15_f64;
 
 impl Transition {
-    pub fn play(&self, sdl: &mut impl Sdl) -> Result<()> {
+    /// Returns the effect to play for the next photo. [Transition::Random] is resolved to one of
+    /// `pool` (see --random-transitions) drawn from `random`, other effects are returned as is
+    pub fn pick(self, pool: &[Transition], random: &impl Random) -> Transition {
         match self {
-            Transition::Crossfade => {
-                self.crossfade(sdl)?;
+            Transition::Random if !pool.is_empty() => {
+                pool[random.gen_range(0..pool.len() as u32) as usize]
+            }
+            Transition::Random => Transition::Crossfade,
+            transition => transition,
+        }
+    }
+
+    /// Plays the transition effect, taking `duration` (see --transition-duration)
+    pub fn play(&self, renderer: &mut impl Renderer, duration: Duration) -> Result<()> {
+        let duration_secs = duration.as_secs_f64();
+        match self {
+            /* Without the bridge image (see blur_bridge) the blur bridge degrades to a crossfade */
+            /* Random should be resolved with Transition::pick first */
+            Transition::Crossfade | Transition::BlurBridge | Transition::Random => {
+                crossfade(renderer, duration_secs)?;
             }
             Transition::FadeToBlack => {
-                self.fade_to_black(sdl, FadeToBlackPhase::Out)?;
-                self.fade_to_black(sdl, FadeToBlackPhase::In)?;
+                self.fade_to_black(renderer, FadeToBlackPhase::Out, duration_secs)?;
+                self.fade_to_black(renderer, FadeToBlackPhase::In, duration_secs)?;
+            }
+            Transition::ZoomBlur => {
+                self.zoom_blur(renderer, duration_secs)?;
+            }
+            Transition::Slide => {
+                slide(renderer, true, Rotation::D0, duration)?;
             }
             Transition::None => {
-                sdl.copy_texture_to_canvas(TextureIndex::Next)?;
-                sdl.present_canvas();
+                renderer.copy_texture_to_canvas(TextureIndex::Next)?;
+                renderer.present_canvas();
             }
         }
         Ok(())
     }
 
-    fn crossfade(&self, sdl: &mut impl Sdl) -> Result<()> {
-        let mut delta;
-        let mut alpha = TRANSITION_ALPHA_MIN;
+    fn zoom_blur(&self, renderer: &mut impl Renderer, duration_secs: f64) -> Result<()> {
+        let mut progress = 0_f64;
         let mut last = Instant::now();
-        const DIFF: f64 = TRANSITION_ALPHA_MAX / CROSSFADE_DURATION_SECS;
-        while alpha.round() < TRANSITION_ALPHA_MAX {
-            sdl.handle_quit_event()?;
-            delta = (Instant::now() - last).as_secs_f64();
-            last = Instant::now();
-            sdl.copy_texture_to_canvas(TextureIndex::Current)?;
-            alpha += delta * DIFF;
-            sdl.set_texture_alpha(alpha.round() as u8, TextureIndex::Next);
-            sdl.copy_texture_to_canvas(TextureIndex::Next)?;
-            sdl.present_canvas();
+        while progress < 1_f64 {
+            renderer.handle_quit_event()?;
+            progress = (progress + frame_delta(&mut last) / duration_secs).min(1_f64);
+            /* Outgoing photo zooms in, and its fainter copies zoomed in even more smear it */
+            let zoom = 1_f64 + ZOOM_BLUR_MAX_ZOOM * progress;
+            renderer.set_texture_alpha(TRANSITION_ALPHA_MAX as u8, TextureIndex::Current);
+            renderer.copy_texture_to_canvas_zoomed(TextureIndex::Current, zoom)?;
+            for ghost in 1..=ZOOM_BLUR_GHOSTS {
+                let ghost_alpha = TRANSITION_ALPHA_MAX * progress / (ghost + 1) as f64;
+                renderer.set_texture_alpha(ghost_alpha.round() as u8, TextureIndex::Current);
+                renderer.copy_texture_to_canvas_zoomed(
+                    TextureIndex::Current,
+                    zoom + ZOOM_BLUR_MAX_ZOOM * progress * ghost as f64 / 4_f64,
+                )?;
+            }
+            /* Incoming photo zooms out into focus while fading in */
+            renderer.set_texture_alpha(
+                (TRANSITION_ALPHA_MAX * progress).round() as u8,
+                TextureIndex::Next,
+            );
+            renderer.copy_texture_to_canvas_zoomed(
+                TextureIndex::Next,
+                1_f64 + ZOOM_BLUR_MAX_ZOOM * (1_f64 - progress),
+            )?;
+            renderer.present_canvas();
         }
+        /* Textures swap roles after the transition, the outgoing one must not stay translucent */
+        renderer.set_texture_alpha(TRANSITION_ALPHA_MAX as u8, TextureIndex::Current);
         Ok(())
     }
 
-    /// Returns false if exit event occurred
-    fn fade_to_black(&self, sdl: &mut impl Sdl, phase: FadeToBlackPhase) -> Result<()> {
-        let mut delta;
-        let mut alpha = phase.init_alpha();
-        let mut last = Instant::now();
-        while !phase.is_finished(alpha) {
-            sdl.handle_quit_event()?;
-            delta = (Instant::now() - last).as_secs_f64();
-            last = Instant::now();
-            alpha += phase.step_alpha(delta);
-            sdl.copy_texture_to_canvas(phase.texture_index())?;
-            sdl.fill_canvas(Color::RGBA(0, 0, 0, alpha.round() as u8))?;
-            sdl.present_canvas();
+    /// Plays one half of the fade to black, taking half of `duration_secs`
+    fn fade_to_black(
+        &self,
+        renderer: &mut impl Renderer,
+        phase: FadeToBlackPhase,
+        duration_secs: f64,
+    ) -> Result<()> {
+        let texture_index = phase.texture_index();
+        fade(renderer, phase, duration_secs / 2f64, texture_index)
+    }
+}
+
+/// Plays [Transition::BlurBridge]. `bridge_image` (e.g. created by [crate::img::blur_bridge])
+/// temporarily replaces the next texture, which is then restored from `next_image`, so that the
+/// next photo ends up in the next texture as after any other transition. Fading into the bridge
+/// image and out of it take `duration` each
+pub fn blur_bridge(
+    renderer: &mut impl Renderer,
+    bridge_image: &[u8],
+    next_image: &[u8],
+    duration: Duration,
+) -> Result<()> {
+    renderer.update_texture(bridge_image, TextureIndex::Next)?;
+    crossfade(renderer, duration.as_secs_f64())?;
+    renderer.swap_textures();
+    renderer.update_texture(next_image, TextureIndex::Next)?;
+    crossfade(renderer, duration.as_secs_f64())
+}
+
+impl SlideDirection {
+    /// Whether photos move to the left in the `slide_number`-th slide (counted from 0)
+    pub fn is_left(self, slide_number: usize) -> bool {
+        match self {
+            SlideDirection::Left => true,
+            SlideDirection::Right => false,
+            SlideDirection::Alternate => slide_number % 2 == 0,
         }
-        Ok(())
     }
 }
 
+/// Plays [Transition::Slide], moving the photos to the left (next photo slides in from the right)
+/// or to the right, as seen on the display rotated by `rotation` (see --rotate)
+pub fn slide(
+    renderer: &mut impl Renderer,
+    to_left: bool,
+    rotation: Rotation,
+    duration: Duration,
+) -> Result<()> {
+    /* Laid out on the upright screen, then mapped to the canvas */
+    let (w, h) = match (renderer.size(), rotation) {
+        (size, Rotation::D0 | Rotation::D180) => size,
+        ((w, h), Rotation::D90 | Rotation::D270) => (h, w),
+    };
+    let upright_size = (w, h);
+    renderer.set_texture_alpha(TRANSITION_ALPHA_MAX as u8, TextureIndex::Current);
+    renderer.set_texture_alpha(TRANSITION_ALPHA_MAX as u8, TextureIndex::Next);
+    let mut progress = 0_f64;
+    let mut last = Instant::now();
+    while progress < 1_f64 {
+        renderer.handle_quit_event()?;
+        progress = (progress + frame_delta(&mut last) / duration.as_secs_f64()).min(1_f64);
+        /* Width of the visible part of the next photo */
+        let offset = (w as f64 * progress).round() as u32;
+        let (current_src_x, current_dst_x, next_src_x, next_dst_x) = if to_left {
+            (offset, 0, 0, w - offset)
+        } else {
+            (0, offset, w - offset, 0)
+        };
+        if offset < w {
+            renderer.copy_texture_region(
+                TextureIndex::Current,
+                Rect::new(current_src_x as i32, 0, w - offset, h).rotate(upright_size, rotation),
+                Rect::new(current_dst_x as i32, 0, w - offset, h).rotate(upright_size, rotation),
+            )?;
+        }
+        if offset > 0 {
+            renderer.copy_texture_region(
+                TextureIndex::Next,
+                Rect::new(next_src_x as i32, 0, offset, h).rotate(upright_size, rotation),
+                Rect::new(next_dst_x as i32, 0, offset, h).rotate(upright_size, rotation),
+            )?;
+        }
+        renderer.present_canvas();
+    }
+    Ok(())
+}
+
+/// Fades in the currently displayed texture from black, e.g. during startup
+pub fn fade_in_from_black(renderer: &mut impl Renderer, duration: Duration) -> Result<()> {
+    fade(
+        renderer,
+        FadeToBlackPhase::In,
+        duration.as_secs_f64(),
+        TextureIndex::Current,
+    )
+}
+
+fn crossfade(renderer: &mut impl Renderer, duration_secs: f64) -> Result<()> {
+    let mut alpha = TRANSITION_ALPHA_MIN;
+    let mut last = Instant::now();
+    while alpha.round() < TRANSITION_ALPHA_MAX {
+        renderer.handle_quit_event()?;
+        let delta = frame_delta(&mut last);
+        renderer.copy_texture_to_canvas(TextureIndex::Current)?;
+        alpha += delta * TRANSITION_ALPHA_MAX / duration_secs;
+        renderer.set_texture_alpha(alpha.round() as u8, TextureIndex::Next);
+        renderer.copy_texture_to_canvas(TextureIndex::Next)?;
+        renderer.present_canvas();
+    }
+    Ok(())
+}
+
+fn fade(
+    renderer: &mut impl Renderer,
+    phase: FadeToBlackPhase,
+    duration_secs: f64,
+    texture_index: TextureIndex,
+) -> Result<()> {
+    let mut alpha = phase.init_alpha();
+    let mut last = Instant::now();
+    while !phase.is_finished(alpha) {
+        renderer.handle_quit_event()?;
+        let delta = frame_delta(&mut last);
+        alpha += phase.step_alpha(delta, duration_secs);
+        renderer.copy_texture_to_canvas(texture_index)?;
+        renderer.fill_canvas(Color::rgba(0, 0, 0, alpha.round() as u8))?;
+        renderer.present_canvas();
+    }
+    Ok(())
+}
+
+/// Returns seconds elapsed since `last` (capped to [MAX_FRAME_DELTA_SECS]) and updates it
+fn frame_delta(last: &mut Instant) -> f64 {
+    let now = Instant::now();
+    let delta = (now - *last).as_secs_f64();
+    *last = now;
+    delta.min(MAX_FRAME_DELTA_SECS)
+}
+
 enum FadeToBlackPhase {
     Out,
     In,
@@ -91,9 +257,8 @@ impl FadeToBlackPhase {
         }
     }
 
-    fn step_alpha(&self, delta: f64) -> f64 {
-        const DIFF: f64 = TRANSITION_ALPHA_MAX / (FADE_TO_BLACK_DURATION_SECS / 2f64);
-        let diff = delta * DIFF;
+    fn step_alpha(&self, delta: f64, duration_secs: f64) -> f64 {
+        let diff = delta * TRANSITION_ALPHA_MAX / duration_secs;
         match self {
             FadeToBlackPhase::Out => diff,
             FadeToBlackPhase::In => -diff,
@@ -110,41 +275,75 @@ impl FadeToBlackPhase {
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
 
     use mock_instant::MockClock;
     use mockall::Sequence;
 
-    use crate::sdl::MockSdl;
+    use crate::{render::MockRenderer, test_helpers::rand::FakeRandom, QuitEvent};
 
     use super::*;
 
+    const ONE_SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn when_transition_is_random_then_pick_draws_effects_from_random_sequence() {
+        const POOL: [Transition; 3] = [
+            Transition::Crossfade,
+            Transition::FadeToBlack,
+            Transition::None,
+        ];
+        let random = FakeRandom::default().with_random_sequence(vec![1, 2, 0]);
+
+        let picked = [(); 3].map(|_| Transition::Random.pick(&POOL, &random));
+
+        assert_eq!(
+            picked,
+            [
+                Transition::FadeToBlack,
+                Transition::None,
+                Transition::Crossfade
+            ]
+        );
+        /* Other effects do not draw from the random sequence, which is now empty */
+        assert_eq!(
+            Transition::ZoomBlur.pick(&POOL, &random),
+            Transition::ZoomBlur
+        );
+    }
+
     #[test]
     fn fade_to_black_play_calls_canvas_methods_in_sequence() {
-        let mut sdl = MockSdl::default();
+        let mut renderer = MockRenderer::default();
         /* First iteration steps alpha by 0 */
         const EXPECTED_PHASE_ITERATIONS: usize = 16;
 
-        sdl.expect_handle_quit_event()
+        renderer
+            .expect_handle_quit_event()
             .times(2 * EXPECTED_PHASE_ITERATIONS)
             .returning(|| Ok(()));
-        let mut sdl_seq = Sequence::default();
+        let mut renderer_seq = Sequence::default();
         const FPS: f64 = 30_f64;
         let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
         for texture_index in [&TextureIndex::Current, &TextureIndex::Next] {
             for _ in 0..EXPECTED_PHASE_ITERATIONS {
-                sdl.expect_copy_texture_to_canvas()
+                renderer
+                    .expect_copy_texture_to_canvas()
                     .withf(move |index| index == texture_index)
                     .once()
-                    .in_sequence(&mut sdl_seq)
+                    .in_sequence(&mut renderer_seq)
                     .return_once(|_| Ok(()));
-                sdl.expect_fill_canvas()
+                renderer
+                    .expect_fill_canvas()
                     .once()
-                    .in_sequence(&mut sdl_seq)
+                    .in_sequence(&mut renderer_seq)
                     .return_once(|_| Ok(()));
-                sdl.expect_present_canvas()
+                renderer.expect_present_canvas()
                     .once()
-                    .in_sequence(&mut sdl_seq)
+                    .in_sequence(&mut renderer_seq)
                     .returning(move || {
                         /* Simulate time passing between calls to Instant::now(), i.e. time it takes to process and
                          * display a frame. Here we pretend that approximately 30 FPS can be achieved. */
@@ -153,41 +352,45 @@ mod tests {
             }
         }
 
-        let result = Transition::FadeToBlack.play(&mut sdl);
+        let result = Transition::FadeToBlack.play(&mut renderer, ONE_SECOND);
 
         assert!(result.is_ok());
-        sdl.checkpoint();
+        renderer.checkpoint();
     }
 
     #[test]
     fn crossfade_play_calls_canvas_methods_in_sequence() {
-        let mut sdl = MockSdl::default();
+        let mut renderer = MockRenderer::default();
         /* First iteration steps alpha by 0 */
         const EXPECTED_ITERATIONS: usize = 31;
-        sdl.expect_handle_quit_event()
+        renderer
+            .expect_handle_quit_event()
             .times(EXPECTED_ITERATIONS)
             .returning(|| Ok(()));
-        let mut sdl_seq = Sequence::default();
+        let mut renderer_seq = Sequence::default();
         const FPS: f64 = 30_f64;
         let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
         for _ in 0..EXPECTED_ITERATIONS {
-            sdl.expect_copy_texture_to_canvas()
+            renderer
+                .expect_copy_texture_to_canvas()
                 .withf(|index| index == &TextureIndex::Current)
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_once(|_| Ok(()));
-            sdl.expect_set_texture_alpha()
+            renderer
+                .expect_set_texture_alpha()
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_const(());
-            sdl.expect_copy_texture_to_canvas()
+            renderer
+                .expect_copy_texture_to_canvas()
                 .withf(|index| index == &TextureIndex::Next)
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_once(|_| Ok(()));
-            sdl.expect_present_canvas()
+            renderer.expect_present_canvas()
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .returning(move || {
                     /* Simulate time passing between calls to Instant::now(), i.e. time it takes to process and
                      * display a frame. Here we pretend that approximately 30 FPS can be achieved. */
@@ -195,10 +398,10 @@ mod tests {
                 });
         }
 
-        let result = Transition::Crossfade.play(&mut sdl);
+        let result = Transition::Crossfade.play(&mut renderer, ONE_SECOND);
 
         assert!(result.is_ok());
-        sdl.checkpoint();
+        renderer.checkpoint();
     }
 
     #[test]
@@ -207,16 +410,21 @@ mod tests {
         test_case(60_f64);
 
         fn test_case(fps: f64) {
-            let mut sdl = MockSdl::default();
-            sdl.expect_handle_quit_event().returning(|| Ok(()));
+            let mut renderer = MockRenderer::default();
+            renderer.expect_handle_quit_event().returning(|| Ok(()));
             let frame_duration = Duration::from_secs_f64(1_f64 / fps);
-            sdl.expect_copy_texture_to_canvas().returning(|_| Ok(()));
-            sdl.expect_fill_canvas().returning(|_| Ok(()));
-            sdl.expect_present_canvas()
+            renderer
+                .expect_copy_texture_to_canvas()
+                .returning(|_| Ok(()));
+            renderer.expect_fill_canvas().returning(|_| Ok(()));
+            renderer
+                .expect_present_canvas()
                 .returning(move || MockClock::advance(frame_duration));
             reset_clock();
 
-            Transition::FadeToBlack.play(&mut sdl).unwrap();
+            Transition::FadeToBlack
+                .play(&mut renderer, ONE_SECOND)
+                .unwrap();
 
             let fade_duration = MockClock::time();
             assert_eq!(fade_duration.as_secs(), 1);
@@ -229,16 +437,21 @@ mod tests {
         test_case(60_f64);
 
         fn test_case(fps: f64) {
-            let mut sdl = MockSdl::default();
-            sdl.expect_handle_quit_event().returning(|| Ok(()));
+            let mut renderer = MockRenderer::default();
+            renderer.expect_handle_quit_event().returning(|| Ok(()));
             let frame_duration = Duration::from_secs_f64(1_f64 / fps);
-            sdl.expect_copy_texture_to_canvas().returning(|_| Ok(()));
-            sdl.expect_set_texture_alpha().return_const(());
-            sdl.expect_present_canvas()
+            renderer
+                .expect_copy_texture_to_canvas()
+                .returning(|_| Ok(()));
+            renderer.expect_set_texture_alpha().return_const(());
+            renderer
+                .expect_present_canvas()
                 .returning(move || MockClock::advance(frame_duration));
             reset_clock();
 
-            Transition::Crossfade.play(&mut sdl).unwrap();
+            Transition::Crossfade
+                .play(&mut renderer, ONE_SECOND)
+                .unwrap();
 
             let fade_duration = MockClock::time();
             assert_eq!(fade_duration.as_secs(), 1);
@@ -247,81 +460,470 @@ mod tests {
 
     #[test]
     fn fade_to_black_play_mutates_alpha() {
-        let mut sdl = MockSdl::default();
-        sdl.expect_handle_quit_event().returning(|| Ok(()));
-        sdl.expect_copy_texture_to_canvas().returning(|_| Ok(()));
+        let mut renderer = MockRenderer::default();
+        renderer.expect_handle_quit_event().returning(|| Ok(()));
+        renderer
+            .expect_copy_texture_to_canvas()
+            .returning(|_| Ok(()));
         const FPS: f64 = 30_f64;
         let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
-        let mut sdl_seq = Sequence::default();
+        let mut renderer_seq = Sequence::default();
         let alpha_prefix = [0, 17, 34];
         for alpha in alpha_prefix {
             /* Check alpha value for first 3 calls to fill_canvas. */
-            sdl.expect_fill_canvas()
-                .withf(move |color| *color == Color::RGBA(0, 0, 0, alpha))
+            renderer
+                .expect_fill_canvas()
+                .withf(move |color| *color == Color::rgba(0, 0, 0, alpha))
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_once(|_| Ok(()));
         }
         /* Set up calls between first and last 3 iterations */
         const EXPECTED_ITERATIONS: usize = 32;
         let alpha_postfix = [34, 17, 0];
-        sdl.expect_fill_canvas()
+        renderer
+            .expect_fill_canvas()
             .times(EXPECTED_ITERATIONS - alpha_prefix.len() - alpha_postfix.len())
             .returning(|_| Ok(()));
         for alpha in alpha_postfix {
             /* Check alpha value for last 3 calls to fill_canvas. */
-            sdl.expect_fill_canvas()
-                .withf(move |color| *color == Color::RGBA(0, 0, 0, alpha))
+            renderer
+                .expect_fill_canvas()
+                .withf(move |color| *color == Color::rgba(0, 0, 0, alpha))
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_once(|_| Ok(()));
         }
-        sdl.expect_present_canvas()
+        renderer
+            .expect_present_canvas()
             .returning(move || MockClock::advance(frame_duration));
 
-        Transition::FadeToBlack.play(&mut sdl).unwrap();
+        Transition::FadeToBlack
+            .play(&mut renderer, ONE_SECOND)
+            .unwrap();
 
-        sdl.checkpoint();
+        renderer.checkpoint();
     }
 
     #[test]
     fn crossfade_play_mutates_alpha() {
-        let mut sdl = MockSdl::default();
-        sdl.expect_handle_quit_event().returning(|| Ok(()));
-        sdl.expect_copy_texture_to_canvas().returning(|_| Ok(()));
+        let mut renderer = MockRenderer::default();
+        renderer.expect_handle_quit_event().returning(|| Ok(()));
+        renderer
+            .expect_copy_texture_to_canvas()
+            .returning(|_| Ok(()));
         const FPS: f64 = 30_f64;
         let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
-        let mut sdl_seq = Sequence::default();
+        let mut renderer_seq = Sequence::default();
         let alpha_prefix: [u8; 3] = [0, 8, 17];
         for alpha in alpha_prefix {
             /* Check alpha value for first 3 calls to fill_canvas. */
-            sdl.expect_set_texture_alpha()
+            renderer
+                .expect_set_texture_alpha()
                 .withf(move |a, i| a == &alpha && i == &TextureIndex::Next)
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_const(());
         }
         /* Set up calls between first and last 3 iterations */
         const EXPECTED_ITERATIONS: usize = 31;
         let alpha_postfix: [u8; 3] = [238, 246, 255];
-        sdl.expect_set_texture_alpha()
+        renderer
+            .expect_set_texture_alpha()
             .times(EXPECTED_ITERATIONS - alpha_prefix.len() - alpha_postfix.len())
-            .in_sequence(&mut sdl_seq)
+            .in_sequence(&mut renderer_seq)
             .return_const(());
         for alpha in alpha_postfix {
             /* Check alpha value for last 3 calls to fill_canvas. */
-            sdl.expect_set_texture_alpha()
+            renderer
+                .expect_set_texture_alpha()
                 .withf(move |a, i| a == &alpha && i == &TextureIndex::Next)
                 .once()
-                .in_sequence(&mut sdl_seq)
+                .in_sequence(&mut renderer_seq)
                 .return_const(());
         }
-        sdl.expect_present_canvas()
+        renderer
+            .expect_present_canvas()
             .returning(move || MockClock::advance(frame_duration));
 
-        Transition::Crossfade.play(&mut sdl).unwrap();
+        Transition::Crossfade
+            .play(&mut renderer, ONE_SECOND)
+            .unwrap();
+
+        renderer.checkpoint();
+    }
+
+    #[test]
+    fn when_duration_is_doubled_then_alpha_steps_are_halved() {
+        fn test_case(transition: Transition, duration: Duration) -> (Vec<u8>, Duration) {
+            let mut renderer = MockRenderer::default();
+            renderer.expect_handle_quit_event().returning(|| Ok(()));
+            renderer
+                .expect_copy_texture_to_canvas()
+                .returning(|_| Ok(()));
+            let alphas = Arc::new(Mutex::new(vec![]));
+            renderer.expect_set_texture_alpha().returning({
+                let alphas = Arc::clone(&alphas);
+                move |alpha, _| alphas.lock().unwrap().push(alpha)
+            });
+            renderer.expect_fill_canvas().returning({
+                let alphas = Arc::clone(&alphas);
+                move |color| {
+                    alphas.lock().unwrap().push(color.a);
+                    Ok(())
+                }
+            });
+            const FPS: f64 = 30_f64;
+            let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
+            renderer
+                .expect_present_canvas()
+                .returning(move || MockClock::advance(frame_duration));
+            reset_clock();
+
+            transition.play(&mut renderer, duration).unwrap();
+
+            let alphas = alphas.lock().unwrap().clone();
+            (alphas, MockClock::time())
+        }
+
+        for transition in [Transition::Crossfade, Transition::FadeToBlack] {
+            let (one_second_alphas, one_second_time) = test_case(transition, ONE_SECOND);
+            let (two_seconds_alphas, two_seconds_time) =
+                test_case(transition, Duration::from_secs(2));
+
+            assert_eq!(one_second_time.as_secs(), 1);
+            assert_eq!(two_seconds_time.as_secs(), 2);
+            /* Steps in the first frames, while alpha is rising in both transitions */
+            let one_second_step = one_second_alphas[4] as f64 / 4_f64;
+            let two_seconds_step = two_seconds_alphas[4] as f64 / 4_f64;
+            assert!((two_seconds_step - one_second_step / 2_f64).abs() <= 0.5);
+        }
+    }
+
+    #[test]
+    fn when_first_frame_is_slow_then_crossfade_does_not_jump() {
+        let mut renderer = MockRenderer::default();
+        renderer.expect_handle_quit_event().returning(|| Ok(()));
+        renderer
+            .expect_copy_texture_to_canvas()
+            .returning(|_| Ok(()));
+        let alphas = Arc::new(Mutex::new(vec![]));
+        let alphas_clone = Arc::clone(&alphas);
+        renderer
+            .expect_set_texture_alpha()
+            .returning(move |alpha, _| alphas_clone.lock().unwrap().push(alpha));
+        renderer
+            .expect_present_canvas()
+            .returning(irregular_frames_clock_advance());
+        reset_clock();
+
+        Transition::Crossfade
+            .play(&mut renderer, ONE_SECOND)
+            .unwrap();
+
+        let alphas = alphas.lock().unwrap();
+        /* Slow first frame is counted as 1/15 s, i.e. 1/15 of the full alpha */
+        assert_eq!(alphas[..2], [0, 17]);
+        assert!(alphas.windows(2).all(|w| w[1] - w[0] <= 17));
+    }
+
+    #[test]
+    fn when_first_frame_is_slow_then_fade_to_black_does_not_jump() {
+        let mut renderer = MockRenderer::default();
+        renderer.expect_handle_quit_event().returning(|| Ok(()));
+        renderer
+            .expect_copy_texture_to_canvas()
+            .returning(|_| Ok(()));
+        let alphas = Arc::new(Mutex::new(vec![]));
+        let alphas_clone = Arc::clone(&alphas);
+        renderer.expect_fill_canvas().returning(move |color| {
+            alphas_clone.lock().unwrap().push(color.a);
+            Ok(())
+        });
+        renderer
+            .expect_present_canvas()
+            .returning(irregular_frames_clock_advance());
+        reset_clock();
+
+        Transition::FadeToBlack
+            .play(&mut renderer, ONE_SECOND)
+            .unwrap();
+
+        let alphas = alphas.lock().unwrap();
+        /* Fade out phase is half a second long, so a capped frame is 2/15 of the full alpha */
+        assert_eq!(alphas[..2], [0, 34]);
+        assert!(alphas.windows(2).all(|w| w[0].abs_diff(w[1]) <= 34));
+    }
+
+    #[test]
+    fn zoom_blur_play_takes_one_second_and_ends_with_next_photo_in_focus() {
+        test_case(30_f64);
+        test_case(60_f64);
+
+        fn test_case(fps: f64) {
+            let mut renderer = MockRenderer::default();
+            renderer.expect_handle_quit_event().returning(|| Ok(()));
+            let frame_duration = Duration::from_secs_f64(1_f64 / fps);
+            let alphas = Arc::new(Mutex::new(vec![]));
+            let alphas_clone = Arc::clone(&alphas);
+            renderer
+                .expect_set_texture_alpha()
+                .returning(move |alpha, index| alphas_clone.lock().unwrap().push((alpha, index)));
+            let zooms = Arc::new(Mutex::new(vec![]));
+            let zooms_clone = Arc::clone(&zooms);
+            renderer
+                .expect_copy_texture_to_canvas_zoomed()
+                .returning(move |index, zoom| {
+                    zooms_clone.lock().unwrap().push((index, zoom));
+                    Ok(())
+                });
+            renderer
+                .expect_present_canvas()
+                .returning(move || MockClock::advance(frame_duration));
+            reset_clock();
+
+            Transition::ZoomBlur
+                .play(&mut renderer, ONE_SECOND)
+                .unwrap();
+
+            assert_eq!(MockClock::time().as_secs(), 1);
+            let alphas = alphas.lock().unwrap();
+            let next_alphas = alphas
+                .iter()
+                .filter(|(_, index)| *index == TextureIndex::Next)
+                .map(|(alpha, _)| *alpha)
+                .collect::<Vec<_>>();
+            assert_eq!(next_alphas.first(), Some(&0));
+            assert_eq!(next_alphas.last(), Some(&255));
+            assert!(next_alphas.windows(2).all(|w| w[0] <= w[1]));
+            /* Outgoing texture is left opaque */
+            assert_eq!(alphas.last(), Some(&(255, TextureIndex::Current)));
+            let zooms = zooms.lock().unwrap();
+            assert!(zooms.iter().all(|(_, zoom)| *zoom >= 1_f64));
+            assert_eq!(zooms.last(), Some(&(TextureIndex::Next, 1_f64)));
+        }
+    }
+
+    #[test]
+    fn slide_takes_one_second_and_pushes_current_photo_off_screen() {
+        const SCREEN_SIZE: (u32, u32) = (192, 108);
+        test_case(true);
+        test_case(false);
+
+        fn test_case(to_left: bool) {
+            let mut renderer = MockRenderer::default();
+            renderer.expect_size().return_const(SCREEN_SIZE);
+            renderer.expect_handle_quit_event().returning(|| Ok(()));
+            renderer.expect_set_texture_alpha().return_const(());
+            let regions = Arc::new(Mutex::new(vec![]));
+            renderer.expect_copy_texture_region().returning({
+                let regions = Arc::clone(&regions);
+                move |index, src, dst| {
+                    assert_eq!((src.w, src.h), (dst.w, dst.h));
+                    regions.lock().unwrap().push((index, src, dst));
+                    Ok(())
+                }
+            });
+            const FPS: f64 = 30_f64;
+            let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
+            renderer
+                .expect_present_canvas()
+                .returning(move || MockClock::advance(frame_duration));
+            reset_clock();
+
+            slide(&mut renderer, to_left, Rotation::D0, ONE_SECOND).unwrap();
+
+            assert_eq!(MockClock::time().as_secs(), 1);
+            let (w, h) = SCREEN_SIZE;
+            let full_screen = Rect::new(0, 0, w, h);
+            let regions = regions.lock().unwrap();
+            assert_eq!(
+                regions.first(),
+                Some(&(TextureIndex::Current, full_screen, full_screen))
+            );
+            assert_eq!(
+                regions.last(),
+                Some(&(TextureIndex::Next, full_screen, full_screen))
+            );
+            for (index, _, dst) in regions.iter() {
+                /* Photos move towards the edge given by the direction */
+                let is_at_left_edge = dst.x == 0;
+                let is_at_right_edge = dst.x as u32 + dst.w == w;
+                match (index, to_left) {
+                    (TextureIndex::Current, true) | (TextureIndex::Next, false) => {
+                        assert!(is_at_left_edge)
+                    }
+                    (TextureIndex::Current, false) | (TextureIndex::Next, true) => {
+                        assert!(is_at_right_edge)
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn slide_moves_photos_along_rotated_display() {
+        /* Canvas of a display rotated by 90 or 270 degrees, i.e. 108 wide and 192 high upright */
+        const SCREEN_SIZE: (u32, u32) = (192, 108);
+        /* Upright, the current photo moves to the left. On the canvas it moves towards the top
+         * when rotated by 90 degrees, and towards the bottom when rotated by 270 degrees */
+        test_case(Rotation::D90, |dst| dst.y == 0);
+        test_case(Rotation::D270, |dst| dst.y as u32 + dst.h == SCREEN_SIZE.1);
+        /* Upside down it moves to the right */
+        test_case(Rotation::D180, |dst| dst.x as u32 + dst.w == SCREEN_SIZE.0);
+
+        fn test_case(rotation: Rotation, is_at_expected_edge: fn(&Rect) -> bool) {
+            let mut renderer = MockRenderer::default();
+            renderer.expect_size().return_const(SCREEN_SIZE);
+            renderer.expect_handle_quit_event().returning(|| Ok(()));
+            renderer.expect_set_texture_alpha().return_const(());
+            let regions = Arc::new(Mutex::new(vec![]));
+            renderer.expect_copy_texture_region().returning({
+                let regions = Arc::clone(&regions);
+                move |index, src, dst| {
+                    assert_eq!((src.w, src.h), (dst.w, dst.h));
+                    regions.lock().unwrap().push((index, dst));
+                    Ok(())
+                }
+            });
+            let frame_duration = Duration::from_secs_f64(1_f64 / 30_f64);
+            renderer
+                .expect_present_canvas()
+                .returning(move || MockClock::advance(frame_duration));
+            reset_clock();
+
+            slide(&mut renderer, true, rotation, ONE_SECOND).unwrap();
+
+            let regions = regions.lock().unwrap();
+            let full_screen = Rect::new(0, 0, SCREEN_SIZE.0, SCREEN_SIZE.1);
+            assert_eq!(regions.last(), Some(&(TextureIndex::Next, full_screen)));
+            for (index, dst) in regions.iter() {
+                assert!(dst.x >= 0 && dst.x as u32 + dst.w <= SCREEN_SIZE.0);
+                assert!(dst.y >= 0 && dst.y as u32 + dst.h <= SCREEN_SIZE.1);
+                if *index == TextureIndex::Current {
+                    assert!(is_at_expected_edge(dst), "{rotation:?} {dst:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn when_quit_event_occurs_during_slide_then_slide_stops() {
+        let mut renderer = MockRenderer::default();
+        renderer.expect_size().return_const((192, 108));
+        renderer.expect_set_texture_alpha().return_const(());
+        renderer
+            .expect_copy_texture_region()
+            .returning(|_, _, _| Ok(()));
+        let frames = Arc::new(Mutex::new(0));
+        renderer.expect_handle_quit_event().returning({
+            let frames = Arc::clone(&frames);
+            move || {
+                let mut frames = frames.lock().unwrap();
+                *frames += 1;
+                if *frames < 5 {
+                    Ok(())
+                } else {
+                    Err(QuitEvent)
+                }
+            }
+        });
+        let frame_duration = Duration::from_secs_f64(1_f64 / 30_f64);
+        renderer
+            .expect_present_canvas()
+            .times(4)
+            .returning(move || MockClock::advance(frame_duration));
+        reset_clock();
+
+        let result = Transition::Slide.play(&mut renderer, ONE_SECOND);
+
+        assert!(result.is_err_and(|e| e.is::<QuitEvent>()));
+    }
+
+    #[test]
+    fn alternate_slide_direction_changes_with_every_slide() {
+        assert_eq!(
+            [0, 1, 2].map(|n| SlideDirection::Alternate.is_left(n)),
+            [true, false, true]
+        );
+        assert!(SlideDirection::Left.is_left(1));
+        assert!(!SlideDirection::Right.is_left(0));
+    }
+
+    #[test]
+    fn blur_bridge_fades_through_bridge_image_and_ends_with_next_image_in_next_texture() {
+        let mut renderer = MockRenderer::default();
+        renderer.expect_handle_quit_event().returning(|| Ok(()));
+        const FPS: f64 = 30_f64;
+        let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
+        let calls = Arc::new(Mutex::new(vec![]));
+        renderer.expect_update_texture().returning({
+            let calls = Arc::clone(&calls);
+            move |image, index| {
+                calls
+                    .lock()
+                    .unwrap()
+                    .push(format!("update {image:?} {index:?}"));
+                Ok(())
+            }
+        });
+        renderer.expect_swap_textures().returning({
+            let calls = Arc::clone(&calls);
+            move || calls.lock().unwrap().push("swap".to_string())
+        });
+        renderer
+            .expect_copy_texture_to_canvas()
+            .returning(|_| Ok(()));
+        renderer.expect_set_texture_alpha().return_const(());
+        renderer
+            .expect_present_canvas()
+            .returning(move || MockClock::advance(frame_duration));
+        reset_clock();
+
+        blur_bridge(&mut renderer, &[1], &[2], ONE_SECOND).unwrap();
+
+        assert_eq!(MockClock::time().as_secs(), 2);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["update [1] Next", "swap", "update [2] Next"]
+        );
+    }
+
+    #[test]
+    fn fade_in_from_black_takes_requested_duration() {
+        let mut renderer = MockRenderer::default();
+        renderer.expect_handle_quit_event().returning(|| Ok(()));
+        const FPS: f64 = 30_f64;
+        let frame_duration = Duration::from_secs_f64(1_f64 / FPS);
+        renderer
+            .expect_copy_texture_to_canvas()
+            .withf(|index| index == &TextureIndex::Current)
+            .returning(|_| Ok(()));
+        let alphas = Arc::new(Mutex::new(vec![]));
+        let alphas_clone = Arc::clone(&alphas);
+        renderer.expect_fill_canvas().returning(move |color| {
+            alphas_clone.lock().unwrap().push(color.a);
+            Ok(())
+        });
+        renderer
+            .expect_present_canvas()
+            .returning(move || MockClock::advance(frame_duration));
+        reset_clock();
+
+        fade_in_from_black(&mut renderer, Duration::from_secs(3)).unwrap();
+
+        let fade_duration = MockClock::time();
+        assert_eq!(fade_duration.as_secs(), 3);
+        let alphas = alphas.lock().unwrap();
+        assert_eq!(alphas.first(), Some(&255));
+        assert_eq!(alphas.last(), Some(&0));
+    }
 
-        sdl.checkpoint();
+    /// Simulates a slow first frame followed by frames at approximately 30 FPS
+    fn irregular_frames_clock_advance() -> impl FnMut() {
+        let mut frame_durations = std::iter::once(Duration::from_millis(900))
+            .chain(std::iter::repeat(Duration::from_secs_f64(1_f64 / 30_f64)));
+        move || MockClock::advance(frame_durations.next().unwrap())
     }
 
     fn reset_clock() {
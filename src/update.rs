@@ -1,6 +1,7 @@
 use std::{
-    sync::mpsc::SyncSender,
+    sync::mpsc::{Receiver, RecvTimeoutError, SyncSender},
     thread::{Scope, ScopedJoinHandle},
+    time::Duration,
 };
 
 use anyhow::Result;
@@ -11,7 +12,7 @@ use crate::{
     cli::Rotation,
     http::HttpClient,
     img::Framed,
-    sdl::{Sdl, TextureIndex},
+    render::{Renderer, TextureIndex},
 };
 
 pub struct UpdateNotification {
@@ -32,12 +33,12 @@ impl UpdateNotification {
     pub fn show_on_current_image(
         &self,
         current_image: &mut DynamicImage,
-        sdl: &mut impl Sdl,
+        renderer: &mut impl Renderer,
     ) -> Result<()> {
         self.overlay(current_image);
-        sdl.update_texture(current_image.as_bytes(), TextureIndex::Current)?;
-        sdl.copy_texture_to_canvas(TextureIndex::Current)?;
-        sdl.present_canvas();
+        renderer.update_texture(current_image.as_bytes(), TextureIndex::Current)?;
+        renderer.copy_texture_to_canvas(TextureIndex::Current)?;
+        renderer.present_canvas();
         Ok(())
     }
 
@@ -46,26 +47,117 @@ impl UpdateNotification {
     }
 }
 
+/// Checks for updates at startup and then every `interval`, sending `true` whenever a version
+/// newer than the installed (or the last reported) one gets published. The thread ends when
+/// `stop_receiver`'s sender is dropped
 pub fn check_for_updates_thread<'a, C: HttpClient + Sync>(
     client: &'a C,
     installed_version: &'a str,
+    interval: Duration,
     thread_scope: &'a Scope<'a, '_>,
     update_check_sender: SyncSender<bool>,
+    stop_receiver: Receiver<()>,
 ) -> ScopedJoinHandle<'a, ()> {
     thread_scope.spawn(move || {
-        match api_crates::get_latest_version(client) {
-            Ok(remote_crate) => {
-                if remote_crate.vers != installed_version {
-                    log::info!(
-                        "New version is available ({installed_version} -> {})",
-                        remote_crate.vers
-                    );
-                    update_check_sender.try_send(true).unwrap_or_default();
+        let mut reported_version = installed_version.to_string();
+        loop {
+            match api_crates::get_latest_version(client) {
+                Ok(remote_crate) => {
+                    if remote_crate.vers != reported_version {
+                        log::info!(
+                            "New version is available ({installed_version} -> {})",
+                            remote_crate.vers
+                        );
+                        update_check_sender.try_send(true).unwrap_or_default();
+                        reported_version = remote_crate.vers;
+                    }
                 }
+                Err(error) => {
+                    log::warn!("Check for updates: {error}");
+                }
+            };
+            if stop_receiver.recv_timeout(interval) != Err(RecvTimeoutError::Timeout) {
+                break;
             }
-            Err(error) => {
-                log::error!("Check for updates: {error}");
-            }
-        };
+        }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+    use crate::{
+        http::{MockHttpResponse, StatusCode},
+        test_helpers::MockHttpClient,
+    };
+
+    #[test]
+    fn update_is_reported_once_for_every_new_version() {
+        let mut client_mock = MockHttpClient::new();
+        for version in ["1.0.0", "1.1.0", "1.1.0", "1.2.0"] {
+            client_mock
+                .expect_get_with_timeout()
+                .times(1)
+                .return_once(move |_, _, _| Ok(new_crates_response(version)));
+        }
+        /* In case the thread checks again before being stopped */
+        client_mock
+            .expect_get_with_timeout()
+            .returning(|_, _, _| Ok(new_crates_response("1.2.0")));
+        let (update_check_sender, update_check_receiver) = mpsc::sync_channel(1);
+        let (stop_sender, stop_receiver) = mpsc::channel();
+
+        let mut reported = vec![];
+        thread::scope(|thread_scope| {
+            check_for_updates_thread(
+                &client_mock,
+                "1.0.0",
+                Duration::from_millis(50),
+                thread_scope,
+                update_check_sender,
+                stop_receiver,
+            );
+            while reported.len() < 2 {
+                reported.push(update_check_receiver.recv().unwrap());
+            }
+            drop(stop_sender);
+        });
+
+        assert_eq!(reported, [true, true]);
+        assert!(update_check_receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn when_stopped_then_thread_ends_without_waiting_for_next_check() {
+        let mut client_mock = MockHttpClient::new();
+        client_mock
+            .expect_get_with_timeout()
+            .times(1)
+            .return_once(|_, _, _| Ok(new_crates_response("1.0.0")));
+        let (update_check_sender, _update_check_receiver) = mpsc::sync_channel(1);
+        let (stop_sender, stop_receiver) = mpsc::channel();
+
+        thread::scope(|thread_scope| {
+            let handle = check_for_updates_thread(
+                &client_mock,
+                "1.0.0",
+                Duration::from_secs(3600),
+                thread_scope,
+                update_check_sender,
+                stop_receiver,
+            );
+            drop(stop_sender);
+            handle.join().unwrap();
+        });
+    }
+
+    fn new_crates_response(version: &str) -> MockHttpResponse {
+        let text = format!(r#"{{"vers": "{version}", "yanked": false}}"#);
+        let mut response_mock = MockHttpResponse::new();
+        response_mock.expect_status().return_const(StatusCode::OK);
+        response_mock.expect_text().return_once(|| Ok(text));
+        response_mock
+    }
+}